@@ -0,0 +1,87 @@
+use anyhow::{anyhow, Result};
+use cairo::{Context, FontSlant, FontWeight};
+use log::warn;
+
+use crate::preferences::{self, CaptionPreferences};
+
+// Space above and below the text, and left of it
+const PADDING: f64 = 8.0;
+const FALLBACK_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// The line written in the bar: the current date and time, followed by the
+// custom text when there is some
+pub fn line(text: &str) -> String {
+    let format = &preferences::get().caption.timestamp_format;
+    let now = glib::DateTime::now_local()
+        .map_err(|e| warn!("Failed to read the local time: {}", e))
+        .ok();
+    let timestamp = now
+        .as_ref()
+        .and_then(|now| now.format(format).ok())
+        .or_else(|| {
+            warn!("Invalid caption timestamp format '{}'", format);
+            now.as_ref()?.format(FALLBACK_FORMAT).ok()
+        })
+        .map(|timestamp| timestamp.to_string())
+        .unwrap_or_default();
+
+    match text.trim() {
+        "" => timestamp,
+        text if timestamp.is_empty() => text.to_string(),
+        text => format!("{} — {}", timestamp, text),
+    }
+}
+
+// Height in export pixels of the strip added below the image
+pub fn bar_height() -> i32 {
+    (preferences::get().caption.font_size + 2.0 * PADDING).ceil() as i32
+}
+
+// Fill the strip starting at `top` across an export `width` pixels wide and
+// write the line into it, with the context in export pixels
+pub fn draw(ctx: &Context, width: f64, top: f64, line: &str) -> Result<()> {
+    let caption: &CaptionPreferences = &preferences::get().caption;
+    let height = bar_height() as f64;
+    let background = gdk4::RGBA::parse(caption.background.as_str()).unwrap_or_else(|_| {
+        warn!("Invalid caption background '{}'", caption.background);
+        gdk4::RGBA::new(0.13, 0.13, 0.13, 1.0)
+    });
+    let foreground = gdk4::RGBA::parse(caption.foreground.as_str()).unwrap_or_else(|_| {
+        warn!("Invalid caption foreground '{}'", caption.foreground);
+        gdk4::RGBA::new(1.0, 1.0, 1.0, 1.0)
+    });
+
+    ctx.save()
+        .map_err(|e| anyhow!("Failed to save context: {}", e))?;
+    ctx.rectangle(0.0, top, width, height);
+    ctx.set_source_rgba(
+        background.red() as f64,
+        background.green() as f64,
+        background.blue() as f64,
+        background.alpha() as f64,
+    );
+    ctx.fill()
+        .map_err(|e| anyhow!("Failed to draw caption bar: {}", e))?;
+
+    ctx.select_font_face("Sans", FontSlant::Normal, FontWeight::Normal);
+    ctx.set_font_size(caption.font_size);
+    let font_extents = ctx
+        .font_extents()
+        .map_err(|e| anyhow!("Failed to measure caption text: {}", e))?;
+    // Center the font's full height, not just this line's glyphs, so every
+    // caption sits at the same baseline
+    let baseline = top + (height - font_extents.height()) / 2.0 + font_extents.ascent();
+    ctx.move_to(PADDING, baseline);
+    ctx.set_source_rgba(
+        foreground.red() as f64,
+        foreground.green() as f64,
+        foreground.blue() as f64,
+        foreground.alpha() as f64,
+    );
+    ctx.show_text(line)
+        .map_err(|e| anyhow!("Failed to draw caption text: {}", e))?;
+
+    ctx.restore()
+        .map_err(|e| anyhow!("Failed to restore context: {}", e))?;
+    Ok(())
+}
@@ -1,6 +1,72 @@
-use anyhow::{anyhow, Result};
-use image::GenericImageView;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, RgbaImage};
 use log::{debug, info, warn};
+use std::process::Command;
+use thiserror::Error;
+
+// Why a capture failed, so the UI can tell a missing display apart from a
+// refused capture or a bad region and suggest the right fix
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("No display available: {0}")]
+    NoDisplay(String),
+
+    #[error("Failed to capture screen: {0}")]
+    CaptureFailed(String),
+
+    // The user backed out of the portal's or slurp's region selector
+    #[error("Screenshot was cancelled")]
+    Cancelled,
+
+    #[error("Screenshot portal is not available: {0}")]
+    PortalUnavailable(String),
+
+    #[error("Capture returned an invalid image: {0}")]
+    InvalidImage(String),
+
+    #[error("Invalid region size {width}x{height}: width and height must be positive")]
+    InvalidRegionSize { width: i32, height: i32 },
+
+    #[error(
+        "Region {width}x{height} at ({x}, {y}) is outside the screen bounds {bounds_width}x{bounds_height}"
+    )]
+    RegionOutOfBounds {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        bounds_width: i32,
+        bounds_height: i32,
+    },
+
+    #[error("Failed to decode image: {0}")]
+    Decode(image::ImageError),
+    #[error("Failed to encode image: {0}")]
+    Encode(#[from] image::ImageError),
+}
+
+type Result<T> = std::result::Result<T, CaptureError>;
+
+fn is_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").map_or(false, |display| !display.is_empty())
+}
+
+// Whether a program can be found on PATH, like `which`
+fn find_program(name: &str) -> bool {
+    std::env::var_os("PATH").map_or(false, |path| {
+        std::env::split_paths(&path).any(|directory| directory.join(name).is_file())
+    })
+}
+
+// grim and slurp capture and select on wlroots compositors (Sway, Hyprland,
+// river, ...), where neither the portal nor XWayland can see the screen
+pub fn has_grim() -> bool {
+    find_program("grim")
+}
+
+pub fn has_slurp() -> bool {
+    find_program("slurp")
+}
 
 pub struct ScreenshotCapture {
     pub use_portal: bool,
@@ -31,9 +97,33 @@ impl ScreenshotCapture {
         false
     }
 
+    // Which backend captures go through, in the order
+    // take_screenshot_blocking tries them, for showing to the user
+    pub fn backend_name(&self) -> &'static str {
+        if is_wayland() && has_grim() {
+            "Wayland (grim)"
+        } else if self.use_portal && is_wayland() {
+            "Wayland (portal)"
+        } else if self.use_portal {
+            // An X11 session under a portal-aware desktop
+            "X11 (portal)"
+        } else {
+            "X11"
+        }
+    }
+
     pub fn take_screenshot_blocking(&self) -> Result<Vec<u8>> {
         info!("Starting screenshot capture process");
 
+        // On wlroots the X11 fallback only sees XWayland windows, so grim
+        // goes first whenever it is installed
+        if is_wayland() && has_grim() {
+            match self.take_screenshot_grim_blocking(None) {
+                Ok(data) => return Ok(data),
+                Err(e) => warn!("grim screenshot failed: {}, trying other backends", e),
+            }
+        }
+
         if self.use_portal {
             info!("Attempting screenshot via portal");
             match self.take_screenshot_portal_blocking() {
@@ -50,7 +140,6 @@ impl ScreenshotCapture {
 
         info!("Taking screenshot via X11 fallback");
         self.take_screenshot_x11_blocking()
-            .map_err(|e| anyhow!("Screenshot capture failed: {}. Please ensure you're running in a graphical environment with proper permissions.", e))
     }
 
     pub fn take_screenshot_region_blocking(
@@ -83,6 +172,119 @@ impl ScreenshotCapture {
         self.take_screenshot_x11_blocking()
     }
 
+    // Ask the XDG desktop portal for a screenshot. With `interactive` the
+    // compositor shows its own selector, so the user picks the region with
+    // native tools. Blocks until the user has finished or cancelled.
+    pub fn take_screenshot_portal_interactive(&self) -> Result<Vec<u8>> {
+        use ashpd::desktop::screenshot::Screenshot;
+        use ashpd::desktop::ResponseError;
+
+        info!("Requesting interactive screenshot from the portal");
+
+        // ashpd is async; this runs on a worker thread, so drive it on a
+        // private main context instead of the GTK one
+        let context = glib::MainContext::new();
+        let uri = context
+            .block_on(async {
+                Screenshot::request()
+                    .interactive(true)
+                    .modal(true)
+                    .send()
+                    .await?
+                    .response()
+            })
+            .map_err(|e| match e {
+                ashpd::Error::Response(ResponseError::Cancelled) => CaptureError::Cancelled,
+                e => CaptureError::PortalUnavailable(e.to_string()),
+            })?
+            .uri()
+            .clone();
+
+        let path = uri.to_file_path().map_err(|_| {
+            CaptureError::PortalUnavailable(format!("portal returned a non-local file {}", uri))
+        })?;
+        let data = std::fs::read(&path).map_err(|e| {
+            CaptureError::CaptureFailed(format!("failed to read {}: {}", path.display(), e))
+        })?;
+
+        // The portal leaves the file behind, and it may not be a PNG
+        let image = image::load_from_memory(&data).map_err(CaptureError::Decode)?;
+        if let Err(e) = std::fs::remove_file(&path) {
+            debug!(
+                "Could not remove portal screenshot {}: {}",
+                path.display(),
+                e
+            );
+        }
+
+        let mut buffer = Vec::new();
+        image.write_to(
+            &mut std::io::Cursor::new(&mut buffer),
+            image::ImageOutputFormat::Png,
+        )?;
+
+        info!("Portal screenshot received, {} bytes", buffer.len());
+        Ok(buffer)
+    }
+
+    // Capture with grim, of the whole layout or of a "x,y wxh" geometry in
+    // layout coordinates. grim writes the PNG to standard output.
+    fn take_screenshot_grim_blocking(&self, geometry: Option<&str>) -> Result<Vec<u8>> {
+        info!("Capturing {} with grim", geometry.unwrap_or("all outputs"));
+
+        let mut command = Command::new("grim");
+        if let Some(geometry) = geometry {
+            command.args(["-g", geometry]);
+        }
+        let output = command
+            .args(["-t", "png", "-"])
+            .output()
+            .map_err(|e| CaptureError::CaptureFailed(format!("failed to run grim: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(CaptureError::CaptureFailed(format!(
+                "grim exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        if image::guess_format(&output.stdout).ok() != Some(image::ImageFormat::Png) {
+            return Err(CaptureError::InvalidImage(
+                "grim did not produce a PNG".to_string(),
+            ));
+        }
+
+        info!("grim screenshot received, {} bytes", output.stdout.len());
+        Ok(output.stdout)
+    }
+
+    // Let the user drag out a region with slurp, then capture it with grim.
+    // Blocks until the selection is made or cancelled with Escape.
+    pub fn take_screenshot_slurp_blocking(&self) -> Result<Vec<u8>> {
+        if !has_slurp() || !has_grim() {
+            return Err(CaptureError::CaptureFailed(
+                "region selection with slurp needs both slurp and grim installed".to_string(),
+            ));
+        }
+
+        info!("Selecting region with slurp");
+        let output = Command::new("slurp")
+            .output()
+            .map_err(|e| CaptureError::CaptureFailed(format!("failed to run slurp: {}", e)))?;
+
+        // slurp exits unsuccessfully, printing "selection cancelled", on Escape
+        if !output.status.success() {
+            return Err(CaptureError::Cancelled);
+        }
+
+        let geometry = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if geometry.is_empty() {
+            return Err(CaptureError::Cancelled);
+        }
+
+        self.take_screenshot_grim_blocking(Some(&geometry))
+    }
+
     fn take_screenshot_x11_blocking(&self) -> Result<Vec<u8>> {
         info!("Using X11 fallback for screenshot capture");
 
@@ -91,10 +293,10 @@ impl ScreenshotCapture {
 
         // Use screenshots crate for X11 fallback
         let screens = screenshots::Screen::all()
-            .map_err(|e| anyhow!("Failed to enumerate screens: {}. Make sure you're running in a graphical environment.", e))?;
+            .map_err(|e| CaptureError::NoDisplay(format!("failed to enumerate screens: {}", e)))?;
 
         if screens.is_empty() {
-            return Err(anyhow!("No screens found. Make sure you're running in a graphical environment with a display."));
+            return Err(CaptureError::NoDisplay("no screens found".to_string()));
         }
 
         // For V1.0, we only capture the primary screen (full screen)
@@ -104,21 +306,25 @@ impl ScreenshotCapture {
             screen.display_info.width, screen.display_info.height
         );
 
-        let image = screen.capture()
-            .map_err(|e| anyhow!("Failed to capture screen: {}. This might be due to permissions or running in a headless environment.", e))?;
+        let image = screen
+            .capture()
+            .map_err(|e| CaptureError::CaptureFailed(e.to_string()))?;
 
         // Convert screenshots::Image to PNG bytes
         let width = image.width() as u32;
         let height = image.height() as u32;
 
         if width == 0 || height == 0 {
-            return Err(anyhow!("Invalid screen dimensions: {}x{}", width, height));
+            return Err(CaptureError::InvalidImage(format!(
+                "screen dimensions are {}x{}",
+                width, height
+            )));
         }
 
         let rgba_data = image.rgba();
 
         if rgba_data.is_empty() {
-            return Err(anyhow!("Screenshot capture returned empty image data"));
+            return Err(CaptureError::InvalidImage("empty image data".to_string()));
         }
 
         info!("Converting {}x{} image to PNG", width, height);
@@ -126,23 +332,24 @@ impl ScreenshotCapture {
         // Create image::RgbaImage and save as PNG
         let img =
             image::RgbaImage::from_raw(width, height, rgba_data.clone()).ok_or_else(|| {
-                anyhow!(
-                    "Failed to create image from raw data. Image size: {}x{}, data length: {}",
+                CaptureError::InvalidImage(format!(
+                    "{} bytes of data for a {}x{} image",
+                    rgba_data.len(),
                     width,
-                    height,
-                    rgba_data.len()
-                )
+                    height
+                ))
             })?;
 
         let mut buffer = Vec::new();
         img.write_to(
             &mut std::io::Cursor::new(&mut buffer),
             image::ImageOutputFormat::Png,
-        )
-        .map_err(|e| anyhow!("Failed to convert image to PNG: {}", e))?;
+        )?;
 
         if buffer.is_empty() {
-            return Err(anyhow!("PNG conversion resulted in empty buffer"));
+            return Err(CaptureError::InvalidImage(
+                "PNG conversion resulted in empty buffer".to_string(),
+            ));
         }
 
         info!("Screenshot converted to PNG, {} bytes", buffer.len());
@@ -163,8 +370,7 @@ impl ScreenshotCapture {
         );
 
         // Load the image from bytes
-        let image = image::load_from_memory(image_data)
-            .map_err(|e| anyhow!("Failed to load image for cropping: {}", e))?;
+        let image = image::load_from_memory(image_data).map_err(CaptureError::Decode)?;
 
         let (img_width, img_height) = image.dimensions();
         info!("Original image dimensions: {}x{}", img_width, img_height);
@@ -176,7 +382,14 @@ impl ScreenshotCapture {
         let crop_height = height.min(img_height as i32 - y).max(1) as u32;
 
         if crop_x >= img_width || crop_y >= img_height {
-            return Err(anyhow!("Crop region is outside image bounds"));
+            return Err(CaptureError::RegionOutOfBounds {
+                x,
+                y,
+                width,
+                height,
+                bounds_width: img_width as i32,
+                bounds_height: img_height as i32,
+            });
         }
 
         info!(
@@ -189,20 +402,267 @@ impl ScreenshotCapture {
 
         // Convert back to PNG bytes
         let mut buffer = Vec::new();
-        cropped
-            .write_to(
-                &mut std::io::Cursor::new(&mut buffer),
-                image::ImageOutputFormat::Png,
-            )
-            .map_err(|e| anyhow!("Failed to convert cropped image to PNG: {}", e))?;
+        cropped.write_to(
+            &mut std::io::Cursor::new(&mut buffer),
+            image::ImageOutputFormat::Png,
+        )?;
 
         info!("Cropped image converted to PNG, {} bytes", buffer.len());
         Ok(buffer)
     }
 }
 
+// Validate a requested capture region and clamp it to the screen bounds.
+// Zero or negative sizes and regions entirely off-screen are rejected.
+pub fn clamp_region(
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    screen_width: i32,
+    screen_height: i32,
+) -> Result<(i32, i32, i32, i32)> {
+    if width <= 0 || height <= 0 {
+        return Err(CaptureError::InvalidRegionSize { width, height });
+    }
+
+    let left = x.max(0);
+    let top = y.max(0);
+    let right = x.saturating_add(width).min(screen_width);
+    let bottom = y.saturating_add(height).min(screen_height);
+
+    if right <= left || bottom <= top {
+        return Err(CaptureError::RegionOutOfBounds {
+            x,
+            y,
+            width,
+            height,
+            bounds_width: screen_width,
+            bounds_height: screen_height,
+        });
+    }
+
+    if (left, top, right - left, bottom - top) != (x, y, width, height) {
+        debug!(
+            "Clamped region {}x{} at ({}, {}) to {}x{} at ({}, {})",
+            width,
+            height,
+            x,
+            y,
+            right - left,
+            bottom - top,
+            left,
+            top
+        );
+    }
+
+    Ok((left, top, right - left, bottom - top))
+}
+
+// Blur everything outside the given rectangle, leaving the region itself
+// untouched. The blur is a heavy down/up-scale so the hidden content can't be
+// recovered from the result.
+pub fn blur_outside_region(
+    image: &RgbaImage,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> RgbaImage {
+    let (image_width, image_height) = image.dimensions();
+    debug!(
+        "Blurring {}x{} image outside {}x{} at ({}, {})",
+        image_width, image_height, width, height, x, y
+    );
+
+    let small_width = (image_width / 16).max(1);
+    let small_height = (image_height / 16).max(1);
+    let small = imageops::resize(image, small_width, small_height, FilterType::Triangle);
+    let mut blurred = imageops::resize(&small, image_width, image_height, FilterType::Triangle);
+
+    // Copy the kept region back from the original
+    let x = x.min(image_width);
+    let y = y.min(image_height);
+    let width = width.min(image_width - x);
+    let height = height.min(image_height - y);
+    let kept = imageops::crop_imm(image, x, y, width, height).to_image();
+    imageops::replace(&mut blurred, &kept, x as i64, y as i64);
+
+    blurred
+}
+
+// Blur the given rectangle of a PNG capture, leaving the rest untouched, and
+// return the result as PNG. The rectangle may reach past the image edges, as
+// partly off-screen windows do; only the visible part is blurred.
+pub fn blur_region_png(png: &[u8], x: i32, y: i32, width: u32, height: u32) -> Result<Vec<u8>> {
+    let mut image = image::load_from_memory(png)
+        .map_err(CaptureError::Decode)?
+        .to_rgba8();
+    blur_region(&mut image, x, y, width, height);
+
+    let mut buffer = Vec::new();
+    image.write_to(
+        &mut std::io::Cursor::new(&mut buffer),
+        image::ImageOutputFormat::Png,
+    )?;
+    Ok(buffer)
+}
+
+// Blur the given rectangle of an image in place; only the part inside the
+// image is touched
+pub fn blur_region(image: &mut RgbaImage, x: i32, y: i32, width: u32, height: u32) {
+    let (image_width, image_height) = image.dimensions();
+    let left = x.clamp(0, image_width as i32) as u32;
+    let top = y.clamp(0, image_height as i32) as u32;
+    let right = x.saturating_add(width as i32).clamp(0, image_width as i32) as u32;
+    let bottom = y
+        .saturating_add(height as i32)
+        .clamp(0, image_height as i32) as u32;
+    debug!(
+        "Blurring {}x{} at ({}, {}) in {}x{} image",
+        right.saturating_sub(left),
+        bottom.saturating_sub(top),
+        left,
+        top,
+        image_width,
+        image_height
+    );
+
+    if right > left && bottom > top {
+        // Same heavy down/up-scale as blur_outside_region
+        let region = imageops::crop_imm(&*image, left, top, right - left, bottom - top).to_image();
+        let small = imageops::resize(
+            &region,
+            ((right - left) / 16).max(1),
+            ((bottom - top) / 16).max(1),
+            FilterType::Triangle,
+        );
+        let blurred = imageops::resize(&small, right - left, bottom - top, FilterType::Triangle);
+        imageops::replace(image, &blurred, left as i64, top as i64);
+    }
+}
+
+// Downscale an image so it fits within `max_width`x`max_height`, keeping the
+// aspect ratio. Uses Lanczos3 so previews and thumbnails stay crisp instead of
+// relying on Cairo's cheap scaling. Images that already fit are returned as-is.
+pub fn downscale_to_fit(image: DynamicImage, max_width: u32, max_height: u32) -> DynamicImage {
+    let (width, height) = image.dimensions();
+
+    if max_width == 0 || max_height == 0 || (width <= max_width && height <= max_height) {
+        return image;
+    }
+
+    debug!(
+        "Downscaling image from {}x{} to fit {}x{}",
+        width, height, max_width, max_height
+    );
+    image.resize(max_width, max_height, FilterType::Lanczos3)
+}
+
 impl Default for ScreenshotCapture {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A PNG whose pixels hold their own coordinates in red and green
+    fn synthetic_png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, 255])
+        });
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(image)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn region_inside_the_screen_is_unchanged() {
+        assert_eq!(
+            clamp_region(10, 20, 300, 200, 1920, 1080).unwrap(),
+            (10, 20, 300, 200)
+        );
+    }
+
+    #[test]
+    fn region_is_clamped_to_the_screen() {
+        assert_eq!(
+            clamp_region(-10, 1000, 100, 200, 1920, 1080).unwrap(),
+            (0, 1000, 90, 80)
+        );
+    }
+
+    #[test]
+    fn empty_or_negative_regions_are_rejected() {
+        for (width, height) in [(0, 10), (10, 0), (-5, 10), (10, -5)] {
+            assert!(matches!(
+                clamp_region(0, 0, width, height, 1920, 1080),
+                Err(CaptureError::InvalidRegionSize { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn regions_off_the_screen_are_rejected() {
+        for (x, y) in [(1920, 0), (0, 1080), (-200, 0), (0, -200)] {
+            assert!(matches!(
+                clamp_region(x, y, 100, 100, 1920, 1080),
+                Err(CaptureError::RegionOutOfBounds {
+                    bounds_width: 1920,
+                    bounds_height: 1080,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn region_is_cropped_from_the_capture() {
+        let png = ScreenshotCapture::new()
+            .crop_image_region(&synthetic_png(40, 30), 10, 5, 8, 6)
+            .unwrap();
+        let cropped = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        assert_eq!(cropped.dimensions(), (8, 6));
+        assert_eq!(cropped.get_pixel(0, 0).0, [10, 5, 0, 255]);
+        assert_eq!(cropped.get_pixel(7, 5).0, [17, 10, 0, 255]);
+    }
+
+    #[test]
+    fn crop_off_the_capture_is_out_of_bounds() {
+        let result =
+            ScreenshotCapture::new().crop_image_region(&synthetic_png(40, 30), 40, 0, 8, 6);
+
+        assert!(matches!(
+            result,
+            Err(CaptureError::RegionOutOfBounds {
+                x: 40,
+                bounds_width: 40,
+                bounds_height: 30,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn undecodable_captures_are_decode_errors() {
+        let garbage = b"not an image";
+
+        assert!(matches!(
+            ScreenshotCapture::new().crop_image_region(garbage, 0, 0, 8, 6),
+            Err(CaptureError::Decode(_))
+        ));
+        assert!(matches!(
+            blur_region_png(garbage, 0, 0, 8, 6),
+            Err(CaptureError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+
+use crate::preferences::AfterCapture;
+
+pub const USAGE: &str = "Usage: flint [OPTIONS]
+
+Options:
+  --region X,Y,W,H    Capture the given screen region and open it in the editor
+  --full              Capture the whole screen and open it in the editor
+  --monitor NAME      Capture the monitor on connector NAME (e.g. DP-1)
+  --active-window     Capture the focused window (X11 only)
+  --stdout            With a capture option, write the PNG to stdout and exit
+  --output PATH       With a capture option, save the PNG to PATH and exit
+  --action ACTION     What to do after capturing: editor, copy, save or ask
+                      (overrides after_capture in flint.toml)
+  -h, --help          Show this help and exit";
+
+#[derive(Debug, Clone, Default)]
+pub struct CliOptions {
+    pub region: Option<(i32, i32, i32, i32)>,
+    pub full: bool,
+    pub monitor: Option<String>,
+    pub active_window: bool,
+    pub stdout: bool,
+    pub output: Option<PathBuf>,
+    pub action: Option<AfterCapture>,
+    pub show_help: bool,
+}
+
+impl CliOptions {
+    pub fn parse<I>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+
+            match flag.as_str() {
+                "--region" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| anyhow!("--region requires a value X,Y,W,H"))?;
+                    options.region = Some(Self::parse_region(&value)?);
+                }
+                "--action" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| anyhow!("--action requires a value"))?;
+                    options.action = Some(value.parse().map_err(|e: String| anyhow!(e))?);
+                }
+                "--monitor" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| anyhow!("--monitor requires a connector name"))?;
+                    options.monitor = Some(value);
+                }
+                "--output" => {
+                    let value = inline_value
+                        .or_else(|| args.next())
+                        .ok_or_else(|| anyhow!("--output requires a path"))?;
+                    options.output = Some(PathBuf::from(value));
+                }
+                "--full" => options.full = true,
+                "--active-window" => options.active_window = true,
+                "--stdout" => options.stdout = true,
+                "-h" | "--help" => options.show_help = true,
+                _ => return Err(anyhow!("Unknown argument: {}", arg)),
+            }
+        }
+
+        let sources = [
+            options.full,
+            options.region.is_some(),
+            options.monitor.is_some(),
+            options.active_window,
+        ];
+        if sources.iter().filter(|&&source| source).count() > 1 {
+            return Err(anyhow!(
+                "Only one of --full, --region, --monitor and --active-window can be used"
+            ));
+        }
+        if options.stdout && !options.has_capture() && !options.show_help {
+            return Err(anyhow!(
+                "--stdout requires --full, --region, --monitor or --active-window"
+            ));
+        }
+        if options.output.is_some() && !options.has_capture() && !options.show_help {
+            return Err(anyhow!(
+                "--output requires --full, --region, --monitor or --active-window"
+            ));
+        }
+        if options.stdout && options.output.is_some() {
+            return Err(anyhow!("--stdout and --output cannot be used together"));
+        }
+        if (options.stdout || options.output.is_some()) && options.action.is_some() {
+            return Err(anyhow!("--action cannot be used with --stdout or --output"));
+        }
+
+        Ok(options)
+    }
+
+    // Whether a capture was requested on the command line
+    pub fn has_capture(&self) -> bool {
+        self.full || self.region.is_some() || self.monitor.is_some() || self.active_window
+    }
+
+    // Whether the capture is written out without showing any window
+    pub fn is_headless(&self) -> bool {
+        self.stdout || self.output.is_some()
+    }
+
+    fn parse_region(value: &str) -> Result<(i32, i32, i32, i32)> {
+        let parts = value
+            .split(',')
+            .map(|part| part.trim().parse::<i32>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("Invalid region '{}': {}", value, e))?;
+
+        match parts.as_slice() {
+            [x, y, width, height] => Ok((*x, *y, *width, *height)),
+            _ => Err(anyhow!("Invalid region '{}': expected X,Y,W,H", value)),
+        }
+    }
+}
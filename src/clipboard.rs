@@ -0,0 +1,191 @@
+use anyhow::{anyhow, Result};
+use arboard::{Clipboard, ImageData, SetExtLinux};
+use log::{info, warn};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// arboard stops serving the clipboard once its last handle is dropped, so one
+// handle stays open for as long as Flint runs. GTK runs on one thread, so
+// thread-local storage is enough.
+thread_local! {
+    static CLIPBOARD: RefCell<Option<Clipboard>> = RefCell::new(None);
+    static HISTORY: RefCell<Vec<Vec<u8>>> = RefCell::new(Vec::new());
+}
+
+// Most recent copies kept for re-copying, as PNG bytes, newest first
+const HISTORY_LIMIT: usize = 5;
+
+fn image_data(width: u32, height: u32, rgba: &[u8]) -> ImageData<'_> {
+    ImageData {
+        width: width as usize,
+        height: height as usize,
+        bytes: Cow::Borrowed(rgba),
+    }
+}
+
+fn open() -> Result<Clipboard> {
+    Clipboard::new().map_err(|e| anyhow!("Failed to access clipboard: {}", e))
+}
+
+// Run against the long-lived handle, opening it on first use. A failed
+// handle is dropped so the next copy starts afresh.
+fn with_clipboard<T>(action: impl FnOnce(&mut Clipboard) -> Result<T>) -> Result<T> {
+    CLIPBOARD.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        let clipboard = match slot.as_mut() {
+            Some(clipboard) => clipboard,
+            None => slot.insert(open()?),
+        };
+
+        let result = action(clipboard);
+        if result.is_err() {
+            *slot = None;
+        }
+        result
+    })
+}
+
+fn is_wayland() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").map_or(false, |display| !display.is_empty())
+}
+
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let image = image::RgbaImage::from_raw(width, height, rgba.to_vec())
+        .ok_or_else(|| anyhow!("Image data does not match its size"))?;
+    let mut png = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| anyhow!("Failed to encode PNG: {}", e))?;
+    Ok(png)
+}
+
+// wl-copy forks a helper that keeps serving the image after Flint exits,
+// which also covers compositors arboard cannot talk to
+fn copy_image_with_wl_copy(width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    let png = encode_png(width, height, rgba)?;
+    copy_encoded_image(&png, "image/png")?;
+
+    info!(
+        "Copied {}x{} image to clipboard with wl-copy",
+        width, height
+    );
+    Ok(())
+}
+
+// Copy already encoded image data under its MIME type. Only wl-copy can
+// offer types other than PNG, so this needs Wayland.
+pub fn copy_encoded_image(data: &[u8], mime_type: &str) -> Result<()> {
+    if !is_wayland() {
+        return Err(anyhow!("Copying {} needs wl-copy on Wayland", mime_type));
+    }
+
+    let mut child = Command::new("wl-copy")
+        .args(["--type", mime_type])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run wl-copy: {}", e))?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to open wl-copy input"))?
+        .write_all(data)?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("wl-copy exited with {}", status));
+    }
+
+    info!("Copied {} bytes of {} to clipboard", data.len(), mime_type);
+    Ok(())
+}
+
+// On Wayland, fall back to wl-copy when arboard cannot set the clipboard
+fn fall_back_to_wl_copy(width: u32, height: u32, rgba: &[u8], error: anyhow::Error) -> Result<()> {
+    if !is_wayland() {
+        return Err(error);
+    }
+
+    warn!("{}, falling back to wl-copy", error);
+    copy_image_with_wl_copy(width, height, rgba)
+        .map_err(|fallback_error| anyhow!("{} ({})", error, fallback_error))
+}
+
+pub fn copy_image(width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    let result = with_clipboard(|clipboard| {
+        clipboard
+            .set_image(image_data(width, height, rgba))
+            .map_err(|e| anyhow!("Failed to set clipboard image: {}", e))
+    });
+
+    match result {
+        Ok(_) => {
+            info!("Copied {}x{} image to clipboard", width, height);
+            Ok(())
+        }
+        Err(e) => fall_back_to_wl_copy(width, height, rgba, e),
+    }
+}
+
+// On X11 and Wayland the clipboard contents live in the owning process. This
+// blocks until another application takes the clipboard over, so callers that
+// exit right after copying should run it off the main thread.
+pub fn copy_image_and_wait(width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    info!("Serving {}x{} image on the clipboard", width, height);
+
+    let result = open().and_then(|mut clipboard| {
+        clipboard
+            .set()
+            .wait()
+            .image(image_data(width, height, rgba))
+            .map_err(|e| anyhow!("Failed to set clipboard image: {}", e))
+    });
+
+    result.or_else(|e| fall_back_to_wl_copy(width, height, rgba, e))
+}
+
+// Add a copied image to the history, dropping the oldest past the limit
+pub fn remember_image(width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    let png = encode_png(width, height, rgba)?;
+    HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        history.insert(0, png);
+        history.truncate(HISTORY_LIMIT);
+    });
+    Ok(())
+}
+
+pub fn history() -> Vec<Vec<u8>> {
+    HISTORY.with(|history| history.borrow().clone())
+}
+
+// Copy a history entry again; it becomes the newest entry
+pub fn copy_from_history(index: usize) -> Result<()> {
+    let png = HISTORY
+        .with(|history| {
+            let mut history = history.borrow_mut();
+            (index < history.len()).then(|| {
+                let png = history.remove(index);
+                history.insert(0, png.clone());
+                png
+            })
+        })
+        .ok_or_else(|| anyhow!("No clipboard history entry {}", index))?;
+
+    let image = image::load_from_memory(&png)
+        .map_err(|e| anyhow!("Failed to decode history entry: {}", e))?
+        .to_rgba8();
+    copy_image(image.width(), image.height(), image.as_raw())
+}
+
+pub fn copy_text(text: &str) -> Result<()> {
+    with_clipboard(|clipboard| {
+        clipboard
+            .set_text(text)
+            .map_err(|e| anyhow!("Failed to set clipboard text: {}", e))
+    })
+}
@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+use cairo::{Context, FontSlant, FontWeight, Format, ImageSurface};
+use log::info;
+
+use crate::editor::AnnotationEditor;
+use crate::stamps;
+
+// Space around and between frames, and the strip above each frame that holds
+// its label
+const MARGIN: i32 = 16;
+const LABEL_HEIGHT: i32 = 28;
+
+// Lay PNG frames out left to right, top to bottom in a grid with `cols`
+// columns, each labelled with its frame number, and return the sheet as PNG.
+// Cells are sized for the largest frame so repeated captures line up.
+pub fn compose_grid(images: &[Vec<u8>], cols: usize) -> Result<Vec<u8>> {
+    if images.is_empty() {
+        return Err(anyhow!("No frames to put on the contact sheet"));
+    }
+
+    let frames = images
+        .iter()
+        .map(|png| Ok(image::load_from_memory(png)?.to_rgba8()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let cols = cols.clamp(1, frames.len());
+    let rows = (frames.len() + cols - 1) / cols;
+    let cell_width = frames.iter().map(|frame| frame.width()).max().unwrap_or(0) as i32;
+    let cell_height =
+        frames.iter().map(|frame| frame.height()).max().unwrap_or(0) as i32 + LABEL_HEIGHT;
+
+    let sheet_width = cols as i32 * (cell_width + MARGIN) + MARGIN;
+    let sheet_height = rows as i32 * (cell_height + MARGIN) + MARGIN;
+    let sheet = ImageSurface::create(Format::ARgb32, sheet_width, sheet_height)
+        .map_err(|e| anyhow!("Failed to create contact sheet: {}", e))?;
+
+    {
+        let ctx = Context::new(&sheet)?;
+        ctx.set_source_rgb(0.95, 0.95, 0.95);
+        ctx.paint()?;
+
+        ctx.select_font_face("Sans", FontSlant::Normal, FontWeight::Bold);
+        ctx.set_font_size(16.0);
+
+        for (index, frame) in frames.iter().enumerate() {
+            let x = (MARGIN + (index % cols) as i32 * (cell_width + MARGIN)) as f64;
+            let y = (MARGIN + (index / cols) as i32 * (cell_height + MARGIN)) as f64;
+
+            ctx.set_source_rgb(0.2, 0.2, 0.2);
+            ctx.move_to(x, y + LABEL_HEIGHT as f64 - 8.0);
+            ctx.show_text(&format!("Frame {}", index + 1))?;
+
+            let surface = stamps::surface_from_image(frame)?;
+            ctx.set_source_surface(&surface, x, y + LABEL_HEIGHT as f64)?;
+            ctx.paint()?;
+        }
+    }
+
+    let image = AnnotationEditor::surface_to_rgba(&sheet)?;
+    let mut png = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut png),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| anyhow!("Failed to encode contact sheet: {}", e))?;
+
+    info!(
+        "Composed {} frames into a {}x{} contact sheet",
+        frames.len(),
+        sheet_width,
+        sheet_height
+    );
+    Ok(png)
+}
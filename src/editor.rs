@@ -1,54 +1,160 @@
 use anyhow::{anyhow, Result};
 use arboard::Clipboard;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use cairo::{Context, Format, ImageSurface};
 use gdk4::ModifierType;
 use gtk4::prelude::*;
 use gtk4::{
-    Application, ApplicationWindow, Box, DrawingArea, FileChooserAction, FileChooserDialog,
-    Orientation, ResponseType,
+    glib, Application, ApplicationWindow, Box, DrawingArea, Entry, FileChooserAction,
+    FileChooserDialog, Orientation, ResponseType,
 };
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder};
+use image::ImageEncoder;
 use log::{debug, error, info, warn};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::path::Path;
 use std::rc::Rc;
-
-use crate::tools::{AnnotationTools, Point};
-use crate::ui::{StatusBar, Toolbar};
-
-fn get_screen_dimensions() -> (i32, i32) {
-    // Get screen dimensions using GDK
+use std::sync::mpsc;
+use std::thread;
+
+use crate::caption;
+use crate::capture;
+use crate::clipboard;
+use crate::faces;
+use crate::history;
+use crate::open_with;
+use crate::preferences::{self, EditorBackground, ExportFormat, PngCompression};
+use crate::project::{self, Project};
+use crate::recovery;
+use crate::stamps;
+use crate::tools::{AnnotationTools, FullResolution, Point, ToolType};
+use crate::ui::{self, AnnotationEntry, AnnotationList, ColorInfoPanel, StatusBar, Toolbar};
+use crate::upload::{HttpUploader, Uploader};
+use crate::watermark;
+use crate::window_manager;
+
+// Display scale (800%) past which individual pixels are outlined
+const PIXEL_GRID_MIN_SCALE: f64 = 8.0;
+
+// Data URIs longer than this (1 MiB) get a warning, since many editors and
+// sites choke on or truncate text that long
+const DATA_URI_WARN_LENGTH: usize = 1024 * 1024;
+
+// The monitor the capture came from, or the primary one if it has since been
+// unplugged or the capture's monitor is unknown
+fn get_editor_monitor(monitor_index: Option<u32>) -> Option<gdk4::Monitor> {
     let display = gdk4::Display::default().expect("Failed to get default display");
     let monitors = display.monitors();
+    let monitor_at = |index: u32| {
+        monitors
+            .item(index)
+            .and_then(|item| item.downcast::<gdk4::Monitor>().ok())
+    };
+
+    match monitor_index.map(monitor_at) {
+        Some(Some(monitor)) => Some(monitor),
+        Some(None) => {
+            warn!(
+                "Capture monitor {} is gone, using the primary monitor",
+                monitor_index.unwrap_or_default()
+            );
+            monitor_at(0)
+        }
+        None => monitor_at(0),
+    }
+}
 
-    if monitors.n_items() > 0 {
-        let monitor = monitors
-            .item(0)
-            .unwrap()
-            .downcast::<gdk4::Monitor>()
-            .unwrap();
-        let geometry = monitor.geometry();
-        (geometry.width(), geometry.height())
-    } else {
-        // Fallback to common screen resolution
-        (1920, 1080)
+// Offscreen copy of the background, scaled screenshot and finished strokes
+struct RenderCache {
+    surface: ImageSurface,
+    width: i32,
+    height: i32,
+    scale_factor: i32,
+    revision: u64,
+}
+
+impl FullResolution {
+    fn surface(&self) -> Result<ImageSurface> {
+        let image = image::load_from_memory(&self.png)
+            .map_err(|e| anyhow!("Failed to load full-resolution image: {}", e))?;
+        AnnotationEditor::surface_from_rgba(&image.to_rgba8())
+    }
+}
+
+// How an export is rendered: its size relative to the image, and what is
+// left out of it or added over and below it
+#[derive(Clone, Copy)]
+struct ExportOptions<'a> {
+    scale: f64,
+    // Leave the screenshot out, for a transparent overlay
+    annotations_only: bool,
+    add_watermark: bool,
+    caption_line: Option<&'a str>,
+}
+
+impl Default for ExportOptions<'_> {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            annotations_only: false,
+            add_watermark: false,
+            caption_line: None,
+        }
     }
 }
 
+// Widgets and shared state the drawing area's draw function and input
+// controllers are wired to
+struct DrawingEvents {
+    drawing_area: DrawingArea,
+    tools: Rc<RefCell<AnnotationTools>>,
+    is_drawing: Rc<RefCell<bool>>,
+    pointer_position: Rc<RefCell<Option<Point>>>,
+    screenshot_surface: Rc<RefCell<Option<ImageSurface>>>,
+    full_resolution: Rc<RefCell<Option<FullResolution>>>,
+    render_cache: Rc<RefCell<Option<RenderCache>>>,
+    status_bar: StatusBar,
+    toolbar: Toolbar,
+    annotation_list: AnnotationList,
+    color_panel: ColorInfoPanel,
+}
+
 pub struct AnnotationEditor {
     window: ApplicationWindow,
+    monitor: Option<gdk4::Monitor>,
     drawing_area: DrawingArea,
     toolbar: Toolbar,
     status_bar: StatusBar,
+    annotation_list: AnnotationList,
+    color_panel: ColorInfoPanel,
     tools: Rc<RefCell<AnnotationTools>>,
     screenshot_surface: Rc<RefCell<Option<ImageSurface>>>,
+    full_resolution: Rc<RefCell<Option<FullResolution>>>,
+    render_cache: Rc<RefCell<Option<RenderCache>>>,
+    // The capture whose recovery copy a save releases, if it came from one
+    recovery_capture: Rc<Cell<Option<u64>>>,
     image_width: i32,
     image_height: i32,
 }
 
 impl AnnotationEditor {
-    pub fn new(app: &Application, image_data: Vec<u8>) -> Result<Self> {
-        // Get screen dimensions to calculate window size
-        let (screen_width, screen_height) = get_screen_dimensions();
+    // With `downscale`, a capture over max_editor_megapixels is edited as a
+    // smaller copy; see is_over_pixel_budget
+    pub fn new(
+        app: &Application,
+        image_data: Vec<u8>,
+        monitor_index: Option<u32>,
+        downscale: bool,
+    ) -> Result<Self> {
+        // Size the window relative to the monitor the capture was taken on
+        let monitor = get_editor_monitor(monitor_index);
+        let (screen_width, screen_height) = monitor
+            .as_ref()
+            .map(|monitor| (monitor.geometry().width(), monitor.geometry().height()))
+            // Fallback to common screen resolution
+            .unwrap_or((1920, 1080));
         let window_width = screen_width / 2;
         let window_height = screen_height / 2;
 
@@ -63,12 +169,31 @@ impl AnnotationEditor {
 
         // Load the screenshot image
         let screenshot_surface = Rc::new(RefCell::new(None));
-        let (image_width, image_height) =
-            Self::load_image_data(&image_data, screenshot_surface.clone())?;
+        let (rgba_image, working_scale) =
+            Self::load_image_data(&image_data, screenshot_surface.clone(), downscale)?;
+        let (image_width, image_height) = (rgba_image.width() as i32, rgba_image.height() as i32);
+        let full_resolution = Rc::new(RefCell::new((working_scale < 1.0).then(|| {
+            FullResolution {
+                png: image_data,
+                scale: working_scale,
+            }
+        })));
 
         // Initialize tools
         let tools = Rc::new(RefCell::new(AnnotationTools::new()));
+        let stamps = stamps::load_stamps();
+        let stamp_names: Vec<String> = stamps.iter().map(|stamp| stamp.name.clone()).collect();
+        tools.borrow_mut().stamps = stamps;
+        if let Some(strokes) = history::carried_strokes(image_width, image_height) {
+            info!(
+                "Carrying over {} annotations from the last capture",
+                strokes.len()
+            );
+            tools.borrow_mut().load_strokes(strokes);
+        }
         let is_drawing = Rc::new(RefCell::new(false));
+        let pointer_position = Rc::new(RefCell::new(None::<Point>));
+        let render_cache = Rc::new(RefCell::new(None));
 
         // Create UI components
         let main_box = Box::new(Orientation::Vertical, 0);
@@ -86,26 +211,51 @@ impl AnnotationEditor {
 
         // Create toolbar
         let toolbar = Toolbar::new();
+        toolbar.set_stamp_names(&stamp_names);
 
         // Create status bar
         let status_bar = StatusBar::new();
+        if working_scale < 1.0 {
+            status_bar.set_status(&format!(
+                "Large capture: editing a {:.0}% copy, saving and copying keep full resolution",
+                working_scale * 100.0
+            ));
+        }
+
+        // Side panel listing the annotations, hidden until toggled
+        let annotation_list = AnnotationList::new();
+
+        // Histogram and hovered color, also hidden until toggled
+        let color_panel = ColorInfoPanel::new();
+        color_panel.set_histogram(&rgba_image);
 
         // Setup drawing area events
-        Self::setup_drawing_events(
-            &drawing_area,
-            tools.clone(),
-            is_drawing.clone(),
-            screenshot_surface.clone(),
-            status_bar.clone(),
-        );
+        Self::setup_drawing_events(DrawingEvents {
+            drawing_area: drawing_area.clone(),
+            tools: tools.clone(),
+            is_drawing: is_drawing.clone(),
+            pointer_position: pointer_position.clone(),
+            screenshot_surface: screenshot_surface.clone(),
+            full_resolution: full_resolution.clone(),
+            render_cache: render_cache.clone(),
+            status_bar: status_bar.clone(),
+            toolbar: toolbar.clone(),
+            annotation_list: annotation_list.clone(),
+            color_panel: color_panel.clone(),
+        });
 
         // Set drawing area to be focusable and grab focus
         drawing_area.set_can_focus(true);
         drawing_area.set_focusable(true);
 
         // Assemble the UI
+        let content_box = Box::new(Orientation::Horizontal, 0);
+        content_box.append(&drawing_area);
+        content_box.append(annotation_list.get_widget());
+        content_box.append(color_panel.get_widget());
+
         main_box.append(toolbar.get_widget());
-        main_box.append(&drawing_area);
+        main_box.append(&content_box);
         main_box.append(status_bar.get_widget());
 
         window.set_child(Some(&main_box));
@@ -117,11 +267,17 @@ impl AnnotationEditor {
 
         let editor = Self {
             window,
+            monitor,
             drawing_area,
             toolbar,
             status_bar,
+            annotation_list,
+            color_panel,
             tools,
             screenshot_surface,
+            full_resolution,
+            render_cache,
+            recovery_capture: Rc::new(Cell::new(None)),
             image_width,
             image_height,
         };
@@ -132,15 +288,64 @@ impl AnnotationEditor {
         Ok(editor)
     }
 
+    // Size of the working copy relative to a capture of this size: below 1
+    // when it is over max_editor_megapixels
+    fn budget_scale(width: u32, height: u32) -> f64 {
+        let budget = preferences::get().ui.max_editor_megapixels * 1_000_000.0;
+        let pixels = width as f64 * height as f64;
+        if budget > 0.0 && pixels > budget {
+            (budget / pixels).sqrt()
+        } else {
+            1.0
+        }
+    }
+
+    // Whether the capture is too big to edit at full size without trouble.
+    // Only the header is read, so this is cheap to ask before opening it.
+    pub fn is_over_pixel_budget(image_data: &[u8]) -> bool {
+        image::io::Reader::new(std::io::Cursor::new(image_data))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+            .map_or(false, |(width, height)| {
+                Self::budget_scale(width, height) < 1.0
+            })
+    }
+
+    // Returns the working image and its size relative to the capture, which
+    // is below 1 when a capture over max_editor_megapixels was downscaled
     fn load_image_data(
         image_data: &[u8],
         screenshot_surface: Rc<RefCell<Option<ImageSurface>>>,
-    ) -> Result<(i32, i32)> {
+        downscale: bool,
+    ) -> Result<(image::RgbaImage, f64)> {
         info!("Loading image data: {} bytes", image_data.len());
 
         let image = image::load_from_memory(image_data)
             .map_err(|e| anyhow!("Failed to load image from memory: {}", e))?;
-        let rgba_image = image.to_rgba8();
+        let (full_width, full_height) = (image.width(), image.height());
+        let working_scale = if downscale {
+            Self::budget_scale(full_width, full_height)
+        } else {
+            1.0
+        };
+
+        // Resize the decoded image as it is, before the RGBA copy, so a huge
+        // capture is never held twice at full size
+        let rgba_image = if working_scale < 1.0 {
+            let width = ((full_width as f64 * working_scale).floor() as u32).max(1);
+            let height = ((full_height as f64 * working_scale).floor() as u32).max(1);
+            warn!(
+                "{}x{} capture is over the editor's pixel budget, editing a {}x{} copy",
+                full_width, full_height, width, height
+            );
+            image
+                .resize_exact(width, height, image::imageops::FilterType::Triangle)
+                .to_rgba8()
+        } else {
+            image.to_rgba8()
+        };
+        drop(image);
         let (width, height) = rgba_image.dimensions();
 
         info!(
@@ -148,6 +353,19 @@ impl AnnotationEditor {
             width, height
         );
 
+        let surface = Self::surface_from_rgba(&rgba_image)?;
+        history::remember_image(surface.clone());
+        *screenshot_surface.borrow_mut() = Some(surface);
+
+        info!("Successfully loaded and converted image to Cairo surface");
+        // The exact ratio per axis differs slightly after rounding; exports
+        // size themselves from the horizontal one
+        Ok((rgba_image, width as f64 / full_width as f64))
+    }
+
+    fn surface_from_rgba(rgba_image: &image::RgbaImage) -> Result<ImageSurface> {
+        let (width, height) = rgba_image.dimensions();
+
         // Create Cairo surface from image data with proper stride
         let stride = cairo::Format::ARgb32
             .stride_for_width(width)
@@ -156,27 +374,16 @@ impl AnnotationEditor {
 
         info!("Converting RGBA to Cairo BGRA format, stride: {}", stride);
 
-        // Convert RGBA to BGRA (Cairo's native format on little-endian)
-        for y in 0..height {
-            for x in 0..width {
-                let src_pixel = rgba_image.get_pixel(x, y);
-                let dst_idx = (y as i32 * stride + x as i32 * 4) as usize;
-
-                if dst_idx + 3 < surface_data.len() {
-                    let r = src_pixel[0];
-                    let g = src_pixel[1];
-                    let b = src_pixel[2];
-                    let a = src_pixel[3];
-
-                    // Cairo expects BGRA on little-endian systems
-                    surface_data[dst_idx] = b; // Blue
-                    surface_data[dst_idx + 1] = g; // Green
-                    surface_data[dst_idx + 2] = r; // Red
-                    surface_data[dst_idx + 3] = a; // Alpha
-                } else {
-                    error!("Buffer overflow prevented at pixel ({}, {})", x, y);
-                    break;
-                }
+        // Convert RGBA to BGRA (Cairo's native format on little-endian) a row
+        // at a time; stride padding at the end of each row stays zeroed
+        let row_len = width as usize * 4;
+        for (dst_row, src_row) in surface_data
+            .chunks_exact_mut(stride as usize)
+            .zip(rgba_image.as_raw().chunks_exact(row_len))
+        {
+            dst_row[..row_len].copy_from_slice(src_row);
+            for pixel in dst_row[..row_len].chunks_exact_mut(4) {
+                pixel.swap(0, 2);
             }
         }
 
@@ -184,54 +391,204 @@ impl AnnotationEditor {
             "Creating Cairo surface with dimensions {}x{}",
             width, height
         );
-        let surface = ImageSurface::create_for_data(
+        ImageSurface::create_for_data(
             surface_data,
             Format::ARgb32,
             width as i32,
             height as i32,
             stride,
         )
-        .map_err(|e| anyhow!("Failed to create Cairo surface: {}", e))?;
+        .map_err(|e| anyhow!("Failed to create Cairo surface: {}", e))
+    }
 
-        *screenshot_surface.borrow_mut() = Some(surface);
+    pub fn surface_to_rgba(source: &ImageSurface) -> Result<image::RgbaImage> {
+        let width = source.width();
+        let height = source.height();
 
-        info!("Successfully loaded and converted image to Cairo surface");
-        Ok((width as i32, height as i32))
+        // Paint onto a private surface so we can take exclusive access to its data
+        let mut surface = ImageSurface::create(Format::ARgb32, width, height)
+            .map_err(|e| anyhow!("Failed to create surface: {}", e))?;
+        {
+            let ctx =
+                Context::new(&surface).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+            ctx.set_source_surface(source, 0.0, 0.0)
+                .map_err(|e| anyhow!("Failed to set source surface: {}", e))?;
+            ctx.paint()
+                .map_err(|e| anyhow!("Failed to paint surface: {}", e))?;
+        }
+
+        Self::read_rgba(&mut surface)
+    }
+
+    // Read a surface nothing else references as straight-alpha RGBA
+    fn read_rgba(surface: &mut ImageSurface) -> Result<image::RgbaImage> {
+        surface.flush();
+        let width = surface.width() as usize;
+        let height = surface.height() as usize;
+        let stride = surface.stride() as usize;
+        let data = surface
+            .data()
+            .map_err(|e| anyhow!("Failed to access surface data: {}", e))?;
+
+        let mut rgba_data = Vec::with_capacity(width * height * 4);
+        for row in data.chunks(stride).take(height) {
+            for pixel in row[..width * 4].chunks_exact(4) {
+                // Cairo ARGB format is premultiplied BGRA on little-endian
+                let alpha = pixel[3] as u32;
+                let unpremultiply = |channel: u8| match alpha {
+                    0 => 0,
+                    255 => channel,
+                    _ => ((channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8,
+                };
+                rgba_data.extend_from_slice(&[
+                    unpremultiply(pixel[2]),
+                    unpremultiply(pixel[1]),
+                    unpremultiply(pixel[0]),
+                    pixel[3],
+                ]);
+            }
+        }
+
+        image::RgbaImage::from_raw(width as u32, height as u32, rgba_data)
+            .ok_or_else(|| anyhow!("Failed to create image from surface data"))
     }
 
     fn setup_toolbar_callbacks(&self) {
         // Tool changed callback
         let tools_clone = self.tools.clone();
         let drawing_area_clone = self.drawing_area.clone();
+        let toolbar_clone = self.toolbar.clone();
+        let status_bar_clone = self.status_bar.clone();
         self.toolbar.connect_tool_changed(move |tool| {
             debug!("Tool changed to: {:?}", tool);
-            tools_clone.borrow_mut().set_tool(tool);
+            let (color, thickness) = {
+                let mut tools = tools_clone.borrow_mut();
+                tools.set_tool(tool);
+                (tools.current_color, tools.current_thickness)
+            };
+            // Show the tool's remembered color in the toolbar
+            toolbar_clone.select_color(color);
+            toolbar_clone.refresh_color_swatch();
+            status_bar_clone.set_tool_info(tool, thickness);
             drawing_area_clone.queue_draw();
         });
 
+        // Stamp changed callback
+        let tools_clone = self.tools.clone();
+        self.toolbar.connect_stamp_changed(move |index| {
+            debug!("Stamp changed to: {}", index);
+            tools_clone.borrow_mut().current_stamp = index;
+        });
+
+        // Arrow shape changed callback
+        let tools_clone = self.tools.clone();
+        self.toolbar.connect_arrow_shape_changed(move |shape| {
+            debug!("Arrow shape changed to: {:?}", shape);
+            tools_clone.borrow_mut().set_arrow_shape(shape);
+        });
+
+        // Arrow head changed callback
+        let tools_clone = self.tools.clone();
+        self.toolbar.connect_arrow_head_changed(move |head| {
+            debug!("Arrow head changed to: {:?}", head);
+            tools_clone.borrow_mut().set_arrow_head(head);
+        });
+
         // Color changed callback
+        self.toolbar.set_color_swatch_source(self.tools.clone());
         let tools_clone = self.tools.clone();
         let drawing_area_clone = self.drawing_area.clone();
+        let toolbar_clone = self.toolbar.clone();
         self.toolbar.connect_color_changed(move |color| {
             debug!("Color changed to: {:?}", color);
-            tools_clone.borrow_mut().set_color(color);
+            {
+                let mut tools = tools_clone.borrow_mut();
+                tools.set_color(color);
+                // Picking a color with a group selected recolors all of it
+                tools.recolor_group(color);
+            }
+            toolbar_clone.refresh_color_swatch();
             drawing_area_clone.queue_draw();
         });
 
         // Thickness changed callback
         let tools_clone = self.tools.clone();
         let drawing_area_clone = self.drawing_area.clone();
+        let status_bar_clone = self.status_bar.clone();
         self.toolbar.connect_thickness_changed(move |thickness| {
             debug!("Thickness changed to: {}", thickness);
-            tools_clone.borrow_mut().set_thickness(thickness);
+            let tool = {
+                let mut tools = tools_clone.borrow_mut();
+                tools.set_thickness(thickness);
+                tools.current_tool
+            };
+            status_bar_clone.set_tool_info(tool, thickness);
+            drawing_area_clone.queue_draw();
+        });
+
+        // Corner radius changed callback
+        let tools_clone = self.tools.clone();
+        let drawing_area_clone = self.drawing_area.clone();
+        self.toolbar.connect_corner_radius_changed(move |radius| {
+            debug!("Corner radius changed to: {}", radius);
+            tools_clone.borrow_mut().set_corner_radius(radius);
             drawing_area_clone.queue_draw();
         });
 
+        // Stabilizer changed callback
+        let tools_clone = self.tools.clone();
+        self.toolbar.connect_stabilizer_changed(move |strength| {
+            debug!("Stabilizer changed to: {}", strength);
+            tools_clone.borrow_mut().set_stabilizer(strength);
+        });
+
+        // Carry-over toggle: when this editor closes, its annotations are
+        // either kept for the next capture of the same size or let go
+        let carry_over = Rc::new(Cell::new(preferences::get().general.carry_over_annotations));
+        self.toolbar.set_carry_over_active(carry_over.get());
+        let carry_over_toggle = carry_over.clone();
+        self.toolbar.connect_carry_over_toggled(move |active| {
+            debug!(
+                "Carry over annotations {}",
+                if active { "on" } else { "off" }
+            );
+            carry_over_toggle.set(active);
+        });
+        let tools_for_carry = self.tools.clone();
+        let (image_width, image_height) = (self.image_width, self.image_height);
+        self.window.connect_close_request(move |_| {
+            if carry_over.get() {
+                let strokes = tools_for_carry.borrow().strokes.clone();
+                history::remember_strokes(image_width, image_height, strokes);
+            } else {
+                history::forget_strokes();
+            }
+            glib::Propagation::Proceed
+        });
+
+        // Pixel snap toggled callback
+        let tools_clone = self.tools.clone();
+        self.toolbar.connect_pixel_snap_toggled(move |active| {
+            debug!("Pixel snapping {}", if active { "on" } else { "off" });
+            tools_clone.borrow_mut().set_pixel_snap(active);
+        });
+
+        // Line ends and corners changed callback
+        let tools_clone = self.tools.clone();
+        self.toolbar.connect_line_style_changed(move |cap, join| {
+            debug!("Line style changed to: {:?} ends, {:?} corners", cap, join);
+            let mut tools = tools_clone.borrow_mut();
+            tools.set_cap_style(cap);
+            tools.set_join_style(join);
+        });
+
         // Save button callback
         let window_for_save = self.window.clone();
         let screenshot_surface_for_save = self.screenshot_surface.clone();
+        let full_resolution_for_save = self.full_resolution.clone();
         let tools_for_save = self.tools.clone();
         let status_bar_for_save = self.status_bar.clone();
+        let recovery_capture_for_save = self.recovery_capture.clone();
         let image_width_for_save = self.image_width;
         let image_height_for_save = self.image_height;
 
@@ -240,8 +597,10 @@ impl AnnotationEditor {
             Self::handle_save_action(
                 &window_for_save,
                 &screenshot_surface_for_save,
+                &full_resolution_for_save,
                 &tools_for_save,
                 &status_bar_for_save,
+                &recovery_capture_for_save,
                 image_width_for_save,
                 image_height_for_save,
             );
@@ -249,6 +608,7 @@ impl AnnotationEditor {
 
         // Copy button callback
         let screenshot_surface_for_copy = self.screenshot_surface.clone();
+        let full_resolution_for_copy = self.full_resolution.clone();
         let tools_for_copy = self.tools.clone();
         let status_bar_for_copy = self.status_bar.clone();
         let image_width_for_copy = self.image_width;
@@ -258,6 +618,7 @@ impl AnnotationEditor {
             info!("Copy button clicked");
             Self::handle_copy_action(
                 &screenshot_surface_for_copy,
+                &full_resolution_for_copy,
                 &tools_for_copy,
                 &status_bar_for_copy,
                 image_width_for_copy,
@@ -265,6 +626,190 @@ impl AnnotationEditor {
             );
         });
 
+        // Copy as data URI callback
+        let screenshot_surface_for_data_uri = self.screenshot_surface.clone();
+        let full_resolution_for_data_uri = self.full_resolution.clone();
+        let tools_for_data_uri = self.tools.clone();
+        let status_bar_for_data_uri = self.status_bar.clone();
+        let image_width_for_data_uri = self.image_width;
+        let image_height_for_data_uri = self.image_height;
+
+        self.toolbar.connect_copy_data_uri_clicked(move || {
+            info!("Copy as data URI clicked");
+            Self::handle_copy_data_uri_action(
+                &screenshot_surface_for_data_uri,
+                &full_resolution_for_data_uri,
+                &tools_for_data_uri,
+                &status_bar_for_data_uri,
+                image_width_for_data_uri,
+                image_height_for_data_uri,
+            );
+        });
+
+        // Clipboard history: the menu is refilled each time it opens, since
+        // copies from other editor windows land in the same history
+        let toolbar_for_history = self.toolbar.clone();
+        let status_bar_for_history = self.status_bar.clone();
+        self.toolbar.connect_history_opened(move || {
+            let thumbnails: Vec<gdk4::Texture> = clipboard::history()
+                .iter()
+                .filter_map(|png| {
+                    let image = image::load_from_memory(png)
+                        .map_err(|e| warn!("Failed to load history thumbnail: {}", e))
+                        .ok()?
+                        .to_rgba8();
+                    let (width, height) = image.dimensions();
+                    let texture = gdk4::MemoryTexture::new(
+                        width as i32,
+                        height as i32,
+                        gdk4::MemoryFormat::R8g8b8a8,
+                        &glib::Bytes::from_owned(image.into_raw()),
+                        width as usize * 4,
+                    );
+                    Some(texture.upcast())
+                })
+                .collect();
+
+            let status_bar = status_bar_for_history.clone();
+            toolbar_for_history.set_history_entries(&thumbnails, move |index| {
+                match clipboard::copy_from_history(index) {
+                    Ok(_) => status_bar.set_status("Copied earlier screenshot to clipboard"),
+                    Err(e) => {
+                        error!("Failed to copy from clipboard history: {}", e);
+                        status_bar.set_status(&format!("Error copying to clipboard: {}", e));
+                    }
+                }
+            });
+        });
+
+        // Annotation list callbacks
+        let annotation_list = self.annotation_list.clone();
+        self.toolbar.connect_list_toggled(move |active| {
+            annotation_list.set_revealed(active);
+        });
+
+        let color_panel = self.color_panel.clone();
+        self.toolbar.connect_color_info_toggled(move |active| {
+            color_panel.set_revealed(active);
+        });
+
+        let tools_clone = self.tools.clone();
+        let drawing_area_clone = self.drawing_area.clone();
+        self.annotation_list.connect_selected(move |index| {
+            tools_clone.borrow_mut().select_stroke(index);
+            drawing_area_clone.queue_draw();
+        });
+
+        let tools_clone = self.tools.clone();
+        let drawing_area_clone = self.drawing_area.clone();
+        self.annotation_list
+            .connect_visibility_toggled(move |index, visible| {
+                tools_clone.borrow_mut().set_stroke_visible(index, visible);
+                drawing_area_clone.queue_draw();
+            });
+
+        let tools_clone = self.tools.clone();
+        let drawing_area_clone = self.drawing_area.clone();
+        self.annotation_list
+            .connect_note_changed(move |index, note| {
+                tools_clone.borrow_mut().set_stroke_note(index, &note);
+                drawing_area_clone.queue_draw();
+            });
+
+        let tools_clone = self.tools.clone();
+        let drawing_area_clone = self.drawing_area.clone();
+        let status_bar_clone = self.status_bar.clone();
+        self.annotation_list.connect_delete_clicked(move |index| {
+            tools_clone.borrow_mut().remove_stroke(index);
+            status_bar_clone.set_status(&format!("Deleted annotation #{}", index + 1));
+            drawing_area_clone.queue_draw();
+        });
+
+        // Upload button callback
+        let screenshot_surface_for_upload = self.screenshot_surface.clone();
+        let full_resolution_for_upload = self.full_resolution.clone();
+        let tools_for_upload = self.tools.clone();
+        let status_bar_for_upload = self.status_bar.clone();
+        let image_width_for_upload = self.image_width;
+        let image_height_for_upload = self.image_height;
+
+        self.toolbar.connect_upload_clicked(move || {
+            info!("Upload button clicked");
+            Self::handle_upload_action(
+                &screenshot_surface_for_upload,
+                &full_resolution_for_upload,
+                &tools_for_upload,
+                &status_bar_for_upload,
+                image_width_for_upload,
+                image_height_for_upload,
+            );
+        });
+
+        // Open With: the applications are listed again each time the menu
+        // opens, so newly installed ones show up
+        let toolbar_for_open_with = self.toolbar.clone();
+        let screenshot_surface_for_open_with = self.screenshot_surface.clone();
+        let full_resolution_for_open_with = self.full_resolution.clone();
+        let tools_for_open_with = self.tools.clone();
+        let status_bar_for_open_with = self.status_bar.clone();
+        let image_width_for_open_with = self.image_width;
+        let image_height_for_open_with = self.image_height;
+
+        self.toolbar.connect_open_with_opened(move || {
+            let apps = open_with::image_apps();
+
+            let screenshot_surface = screenshot_surface_for_open_with.clone();
+            let full_resolution = full_resolution_for_open_with.clone();
+            let tools = tools_for_open_with.clone();
+            let status_bar = status_bar_for_open_with.clone();
+            let picked_apps = apps.clone();
+            toolbar_for_open_with.set_open_with_entries(&apps, move |index| {
+                let app = &picked_apps[index];
+                info!("Open with {} clicked", app.name());
+                let result = Self::render_png_static(
+                    &screenshot_surface,
+                    &full_resolution,
+                    &tools,
+                    image_width_for_open_with,
+                    image_height_for_open_with,
+                )
+                .and_then(|png_data| open_with::open(&png_data, app));
+                match result {
+                    Ok(_) => status_bar.set_status(&format!("Opened in {}", app.display_name())),
+                    Err(e) => {
+                        error!("Failed to open screenshot in another application: {}", e);
+                        status_bar.set_status(&format!("Error opening screenshot: {}", e));
+                    }
+                }
+            });
+        });
+
+        // Project callbacks
+        let window_for_project = self.window.clone();
+        let screenshot_surface_for_project = self.screenshot_surface.clone();
+        let full_resolution_for_project = self.full_resolution.clone();
+        let tools_for_project = self.tools.clone();
+        let status_bar_for_project = self.status_bar.clone();
+
+        self.toolbar.connect_save_project_clicked(move || {
+            info!("Save project clicked");
+            Self::handle_save_project_action(
+                &window_for_project,
+                &screenshot_surface_for_project,
+                &full_resolution_for_project,
+                &tools_for_project,
+                &status_bar_for_project,
+            );
+        });
+
+        let window_for_open = self.window.clone();
+        let status_bar_for_open = self.status_bar.clone();
+
+        self.toolbar.connect_open_project_clicked(move || {
+            info!("Open project clicked");
+            Self::handle_open_project_action(&window_for_open, &status_bar_for_open);
+        });
+
         // Clear button callback
         let tools_for_clear = self.tools.clone();
         let drawing_area_for_clear = self.drawing_area.clone();
@@ -276,113 +821,354 @@ impl AnnotationEditor {
             if stroke_count > 0 {
                 tools_for_clear.borrow_mut().clear_all();
                 drawing_area_for_clear.queue_draw();
-                status_bar_for_clear.set_status(&format!("Cleared {} annotations", stroke_count));
+                status_bar_for_clear.set_status(&format!(
+                    "Cleared {} annotations (Ctrl+Z to undo)",
+                    stroke_count
+                ));
             } else {
                 status_bar_for_clear.set_status("No annotations to clear");
             }
         });
-    }
 
-    fn setup_drawing_events(
-        drawing_area: &DrawingArea,
-        tools: Rc<RefCell<AnnotationTools>>,
-        is_drawing: Rc<RefCell<bool>>,
-        screenshot_surface: Rc<RefCell<Option<ImageSurface>>>,
-        status_bar: StatusBar,
-    ) {
-        // Setup draw function
-        let tools_draw = tools.clone();
-        let screenshot_surface_draw = screenshot_surface.clone();
+        // Blur outside button callback
+        let tools_for_blur = self.tools.clone();
+        let screenshot_surface_for_blur = self.screenshot_surface.clone();
+        let full_resolution_for_blur = self.full_resolution.clone();
+        let render_cache_for_blur = self.render_cache.clone();
+        let drawing_area_for_blur = self.drawing_area.clone();
+        let status_bar_for_blur = self.status_bar.clone();
+
+        self.toolbar.connect_blur_outside_clicked(move || {
+            info!("Blur outside button clicked");
+            let bounds = tools_for_blur.borrow().last_rectangle_bounds();
+            let (x, y, width, height) = match bounds {
+                Some(bounds) => bounds,
+                None => {
+                    status_bar_for_blur
+                        .set_status("Draw a rectangle around the area to keep first");
+                    return;
+                }
+            };
 
-        drawing_area.set_draw_func(move |_area, ctx, width, height| {
-            debug!("Drawing callback: area={}x{}", width, height);
+            // The blur only exists in the working copy, so the unblurred
+            // full-size capture is set aside until the blur is undone
+            let had_full_resolution = full_resolution_for_blur.borrow().is_some();
+            match Self::blur_outside(
+                &screenshot_surface_for_blur,
+                &full_resolution_for_blur,
+                &tools_for_blur,
+                x,
+                y,
+                width,
+                height,
+            ) {
+                Ok(_) => {
+                    // The screenshot changed, so the cached base layer is stale
+                    render_cache_for_blur.borrow_mut().take();
+                    drawing_area_for_blur.queue_draw();
+                    if had_full_resolution {
+                        status_bar_for_blur.set_status(
+                            "Blurred everything outside the rectangle; exports now use the reduced size",
+                        );
+                    } else {
+                        status_bar_for_blur
+                            .set_status("Blurred everything outside the rectangle");
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to blur outside region: {}", e);
+                    status_bar_for_blur.set_status(&format!("Error blurring image: {}", e));
+                }
+            }
+        });
 
-            // Create a subtle gradient background for a modern look
-            let gradient = cairo::LinearGradient::new(0.0, 0.0, 0.0, height as f64);
-            gradient.add_color_stop_rgb(0.0, 0.15, 0.17, 0.21); // Top: #262D35
-            gradient.add_color_stop_rgb(1.0, 0.12, 0.14, 0.18); // Bottom: slightly darker
-            ctx.set_source(&gradient).unwrap();
-            ctx.paint().unwrap();
+        // Blur faces button callback
+        let tools_for_faces = self.tools.clone();
+        let screenshot_surface_for_faces = self.screenshot_surface.clone();
+        let full_resolution_for_faces = self.full_resolution.clone();
+        let render_cache_for_faces = self.render_cache.clone();
+        let drawing_area_for_faces = self.drawing_area.clone();
+        let status_bar_for_faces = self.status_bar.clone();
+
+        self.toolbar.connect_blur_faces_clicked(move || {
+            info!("Blur faces button clicked");
+            let model_path = &preferences::get().privacy.face_model;
+            if model_path.is_empty() {
+                status_bar_for_faces
+                    .set_status("Set privacy.face_model in flint.toml to blur faces");
+                return;
+            }
 
-            // Add a subtle texture pattern
-            ctx.save().unwrap();
-            ctx.set_source_rgba(1.0, 1.0, 1.0, 0.01); // Very subtle white dots
-            for x in (0..width).step_by(20) {
-                for y in (0..height).step_by(20) {
-                    ctx.arc(x as f64, y as f64, 0.5, 0.0, 2.0 * std::f64::consts::PI);
-                    ctx.fill().unwrap();
+            // As with blur outside, the full-size capture is unblurred
+            let had_full_resolution = full_resolution_for_faces.borrow().is_some();
+            match Self::blur_faces(
+                &screenshot_surface_for_faces,
+                &full_resolution_for_faces,
+                &tools_for_faces,
+                model_path,
+            ) {
+                Ok(0) => status_bar_for_faces.set_status("No faces found"),
+                Ok(count) => {
+                    render_cache_for_faces.borrow_mut().take();
+                    drawing_area_for_faces.queue_draw();
+                    if had_full_resolution {
+                        status_bar_for_faces.set_status(&format!(
+                            "Blurred {} face(s); exports now use the reduced size",
+                            count
+                        ));
+                    } else {
+                        status_bar_for_faces.set_status(&format!("Blurred {} face(s)", count));
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to blur faces: {}", e);
+                    status_bar_for_faces.set_status(&format!("Error blurring faces: {}", e));
                 }
             }
-            ctx.restore().unwrap();
+        });
+    }
 
-            // Draw the screenshot first
-            if let Some(ref surface) = *screenshot_surface_draw.borrow() {
-                debug!("Drawing screenshot surface");
+    // Rebuild the annotation list rows if the finished strokes changed since
+    // it was last filled. The tools are released first, as filling the list
+    // selects a row and the list's callbacks borrow them again.
+    fn sync_annotation_list(tools: &RefCell<AnnotationTools>, annotation_list: &AnnotationList) {
+        let (revision, entries, selected) = {
+            let tools = tools.borrow();
+            let revision = tools.revision();
+            if !annotation_list.needs_sync(revision) {
+                return;
+            }
 
-                let image_width = surface.width() as f64;
-                let image_height = surface.height() as f64;
-                let area_width = width as f64;
-                let area_height = height as f64;
+            let entries: Vec<_> = tools
+                .strokes
+                .iter()
+                .map(|stroke| AnnotationEntry {
+                    tool_type: stroke.tool_type,
+                    color: stroke.color,
+                    visible: stroke.visible,
+                    note: stroke.note.clone(),
+                })
+                .collect();
+            (revision, entries, tools.selected_stroke)
+        };
+        annotation_list.sync(revision, &entries, selected);
+    }
 
-                // Calculate scale factor to fit image within the drawing area
-                let scale_x = area_width / image_width;
-                let scale_y = area_height / image_height;
-                let scale = scale_x.min(scale_y);
+    // Show the screenshot an undo or redo went back or forward to, along
+    // with the full-size capture that matches it. The stroke revision
+    // changed with it, so the cached base layer is redrawn.
+    fn pick_up_screenshot_change(
+        tools: &RefCell<AnnotationTools>,
+        screenshot_surface: &RefCell<Option<ImageSurface>>,
+        full_resolution: &RefCell<Option<FullResolution>>,
+    ) {
+        let change = tools.borrow_mut().take_screenshot_change();
+        if let Some(change) = change {
+            *screenshot_surface.borrow_mut() = Some(change.surface);
+            *full_resolution.borrow_mut() = change.full_resolution;
+        }
+    }
 
-                // Calculate centered position
-                let scaled_width = image_width * scale;
-                let scaled_height = image_height * scale;
-                let offset_x = (area_width - scaled_width) / 2.0;
-                let offset_y = (area_height - scaled_height) / 2.0;
+    // Bake a blur of every detected face into the screenshot and return how
+    // many were found
+    fn blur_faces(
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &RefCell<Option<FullResolution>>,
+        tools: &RefCell<AnnotationTools>,
+        model_path: &str,
+    ) -> Result<usize> {
+        let mut rgba_image = match *screenshot_surface.borrow() {
+            Some(ref surface) => Self::surface_to_rgba(surface)?,
+            None => return Err(anyhow!("No screenshot loaded")),
+        };
 
-                ctx.save().unwrap();
-                ctx.translate(offset_x, offset_y);
-                ctx.scale(scale, scale);
-                ctx.set_source_surface(surface, 0.0, 0.0).unwrap();
-                ctx.paint().unwrap();
-                ctx.restore().unwrap();
+        let faces = faces::detect_faces(&rgba_image, model_path)?;
+        for &(x, y, width, height) in &faces {
+            capture::blur_region(&mut rgba_image, x, y, width, height);
+        }
 
-                debug!(
-                    "Image scaled by {:.2} and positioned at ({:.1}, {:.1})",
-                    scale, offset_x, offset_y
-                );
-            } else {
-                warn!("No screenshot surface available to draw");
-                // Draw a placeholder with subtle dark background
-                ctx.set_source_rgb(0.18, 0.20, 0.24); // Slightly lighter than main background
-                ctx.rectangle(0.0, 0.0, width as f64, height as f64);
-                ctx.fill().unwrap();
+        if !faces.is_empty() {
+            let blurred = Self::surface_from_rgba(&rgba_image)?;
+            Self::replace_screenshot(screenshot_surface, full_resolution, tools, blurred);
+        }
+        Ok(faces.len())
+    }
+
+    // Swap in an edited screenshot, keeping the old one for undo. The
+    // full-size capture no longer matches it, so it is kept with the undo
+    // step rather than used for exports.
+    fn replace_screenshot(
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &RefCell<Option<FullResolution>>,
+        tools: &RefCell<AnnotationTools>,
+        surface: ImageSurface,
+    ) {
+        let before = screenshot_surface.borrow_mut().replace(surface.clone());
+        if let Some(before) = before {
+            let full_resolution = full_resolution.borrow_mut().take();
+            tools
+                .borrow_mut()
+                .record_screenshot_change(before, surface, full_resolution);
+        }
+    }
+
+    // Bake a blur of everything outside the rectangle into the screenshot
+    // itself, so exports can never reveal the hidden content
+    fn blur_outside(
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &RefCell<Option<FullResolution>>,
+        tools: &RefCell<AnnotationTools>,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> Result<()> {
+        let rgba_image = match *screenshot_surface.borrow() {
+            Some(ref surface) => Self::surface_to_rgba(surface)?,
+            None => return Err(anyhow!("No screenshot loaded")),
+        };
+
+        let blurred = capture::blur_outside_region(
+            &rgba_image,
+            x.max(0.0) as u32,
+            y.max(0.0) as u32,
+            width.max(0.0) as u32,
+            height.max(0.0) as u32,
+        );
+
+        let blurred = Self::surface_from_rgba(&blurred)?;
+        Self::replace_screenshot(screenshot_surface, full_resolution, tools, blurred);
+        Ok(())
+    }
 
-                // Draw text indicating no image with light text
-                ctx.set_source_rgb(0.7, 0.7, 0.7); // Light gray text for dark theme
-                ctx.move_to(20.0, height as f64 / 2.0);
-                ctx.show_text("No screenshot loaded").unwrap();
+    fn setup_drawing_events(events: DrawingEvents) {
+        let DrawingEvents {
+            drawing_area,
+            tools,
+            is_drawing,
+            pointer_position,
+            screenshot_surface,
+            full_resolution,
+            render_cache,
+            status_bar,
+            toolbar,
+            annotation_list,
+            color_panel,
+        } = events;
+        let annotation_list_key = annotation_list.clone();
+
+        // Every change to the finished strokes is followed by a redraw, so
+        // bring the annotation list in step once each frame has been painted.
+        // The area gets a new frame clock each time it is realized, so the
+        // handler is dropped again when it is unrealized.
+        let tools_list = tools.clone();
+        let after_paint = Rc::new(RefCell::new(None));
+        let after_paint_realize = after_paint.clone();
+        drawing_area.connect_realize(move |area| {
+            let Some(frame_clock) = area.frame_clock() else {
+                warn!("Drawing area has no frame clock; annotation list will not update");
+                return;
+            };
+            let tools_list = tools_list.clone();
+            let annotation_list = annotation_list.clone();
+            let handler = frame_clock.connect_after_paint(move |_| {
+                Self::sync_annotation_list(&tools_list, &annotation_list);
+            });
+            *after_paint_realize.borrow_mut() = Some((frame_clock, handler));
+        });
+        drawing_area.connect_unrealize(move |_| {
+            if let Some((frame_clock, handler)) = after_paint.borrow_mut().take() {
+                frame_clock.disconnect(handler);
             }
+        });
 
-            // Draw annotations on top (they need to be scaled too)
-            if let Some(ref surface) = *screenshot_surface_draw.borrow() {
-                let image_width = surface.width() as f64;
-                let image_height = surface.height() as f64;
-                let area_width = width as f64;
-                let area_height = height as f64;
+        // Setup draw function
+        let tools_draw = tools.clone();
+        let screenshot_surface_draw = screenshot_surface.clone();
+        let render_cache_draw = render_cache.clone();
+        let pointer_position_draw = pointer_position.clone();
 
-                let scale_x = area_width / image_width;
-                let scale_y = area_height / image_height;
-                let scale = scale_x.min(scale_y);
+        drawing_area.set_draw_func(move |area, ctx, width, height| {
+            debug!("Drawing callback: area={}x{}", width, height);
 
-                let scaled_width = image_width * scale;
-                let scaled_height = image_height * scale;
-                let offset_x = (area_width - scaled_width) / 2.0;
-                let offset_y = (area_height - scaled_height) / 2.0;
+            // Reuse the cached background, image and finished strokes unless
+            // the size, scale or finished-stroke set changed since last frame
+            let scale_factor = area.scale_factor();
+            let revision = tools_draw.borrow().revision();
+            let mut cache = render_cache_draw.borrow_mut();
+            let is_stale = match *cache {
+                Some(ref cached) => {
+                    cached.width != width
+                        || cached.height != height
+                        || cached.scale_factor != scale_factor
+                        || cached.revision != revision
+                }
+                None => true,
+            };
 
-                ctx.save().unwrap();
-                ctx.translate(offset_x, offset_y);
-                ctx.scale(scale, scale);
-                tools_draw.borrow().draw_all(ctx);
-                ctx.restore().unwrap();
-            } else {
-                // If no image, draw annotations without scaling
-                tools_draw.borrow().draw_all(ctx);
+            if is_stale {
+                let started = std::time::Instant::now();
+                *cache = Self::render_base_layer(
+                    width,
+                    height,
+                    scale_factor,
+                    screenshot_surface_draw.borrow().as_ref(),
+                    &tools_draw.borrow(),
+                )
+                .map(|surface| RenderCache {
+                    surface,
+                    width,
+                    height,
+                    scale_factor,
+                    revision,
+                })
+                .map_err(|e| warn!("Failed to cache base layer: {}", e))
+                .ok();
+                debug!("Rebuilt cached base layer in {:?}", started.elapsed());
+            }
+
+            match *cache {
+                Some(ref cached) => {
+                    ctx.set_source_surface(&cached.surface, 0.0, 0.0).unwrap();
+                    ctx.paint().unwrap();
+                }
+                None => Self::draw_base_layer(
+                    ctx,
+                    width,
+                    height,
+                    screenshot_surface_draw.borrow().as_ref(),
+                    &tools_draw.borrow(),
+                ),
+            }
+
+            // Only the in-progress stroke is drawn fresh each frame
+            match *screenshot_surface_draw.borrow() {
+                Some(ref surface) => {
+                    let (scale, offset_x, offset_y) =
+                        Self::image_transform(width, height, surface.width(), surface.height());
+
+                    ctx.save().unwrap();
+                    ctx.translate(offset_x, offset_y);
+                    ctx.scale(scale, scale);
+                    tools_draw.borrow().draw_current(ctx);
+                    tools_draw.borrow().draw_text_overlay(ctx);
+                    tools_draw.borrow().draw_snap_indicator(ctx);
+                    tools_draw.borrow().draw_measurement(ctx);
+                    tools_draw.borrow().draw_erase_box(ctx);
+                    tools_draw.borrow().draw_selection(ctx);
+                    ctx.restore().unwrap();
+
+                    if scale >= PIXEL_GRID_MIN_SCALE {
+                        Self::draw_pixel_grid(
+                            ctx,
+                            surface.width(),
+                            surface.height(),
+                            (scale, offset_x, offset_y),
+                            pointer_position_draw.borrow().as_ref(),
+                        );
+                    }
+                }
+                None => tools_draw.borrow().draw_current(ctx),
             }
         });
 
@@ -392,100 +1178,295 @@ impl AnnotationEditor {
         let is_drawing_click = is_drawing.clone();
         let drawing_area_click = drawing_area.clone();
         let screenshot_surface_click = screenshot_surface.clone();
+        let status_bar_click = status_bar.clone();
 
-        gesture_click.connect_pressed(move |_, _, x, y| {
+        gesture_click.connect_pressed(move |gesture, _, x, y| {
             debug!("Mouse pressed at screen coords ({}, {})", x, y);
 
             // Convert screen coordinates to image coordinates
-            let (image_x, image_y) = if let Some(ref surface) = *screenshot_surface_click.borrow() {
-                let allocation = drawing_area_click.allocation();
-                let area_width = allocation.width() as f64;
-                let area_height = allocation.height() as f64;
-                let image_width = surface.width() as f64;
-                let image_height = surface.height() as f64;
-
-                let scale_x = area_width / image_width;
-                let scale_y = area_height / image_height;
-                let scale = scale_x.min(scale_y);
-
-                let scaled_width = image_width * scale;
-                let scaled_height = image_height * scale;
-                let offset_x = (area_width - scaled_width) / 2.0;
-                let offset_y = (area_height - scaled_height) / 2.0;
-
-                let image_x = (x - offset_x) / scale;
-                let image_y = (y - offset_y) / scale;
-
-                debug!("Converted to image coords ({:.1}, {:.1})", image_x, image_y);
-                (image_x, image_y)
-            } else {
-                (x, y)
+            let (image_x, image_y) = match *screenshot_surface_click.borrow() {
+                Some(ref surface) => Self::widget_to_image(&drawing_area_click, surface, x, y),
+                None => (x, y),
             };
+            debug!("Converted to image coords ({:.1}, {:.1})", image_x, image_y);
 
-            *is_drawing_click.borrow_mut() = true;
-            tools_click
-                .borrow_mut()
-                .start_stroke(Point::new(image_x, image_y));
-            drawing_area_click.queue_draw();
-        });
+            let mut tools = tools_click.borrow_mut();
+            let point = Point::new(image_x, image_y);
 
-        let tools_release = tools.clone();
-        let is_drawing_release = is_drawing.clone();
-        let drawing_area_release = drawing_area.clone();
+            // Ctrl+click adds the annotation under the pointer to the group
+            // or takes it out again; Ctrl+drag from empty space selects
+            // everything the box touches
+            if gesture
+                .current_event_state()
+                .contains(ModifierType::CONTROL_MASK)
+            {
+                tools.finish_stroke();
+                match tools.stroke_at(&point) {
+                    Some(index) => {
+                        tools.toggle_group_stroke(index);
+                        Self::show_group_status(&status_bar_click, tools.selected_group.len());
+                    }
+                    None => {
+                        tools.start_group_box(point);
+                        *is_drawing_click.borrow_mut() = true;
+                    }
+                }
+                drawing_area_click.queue_draw();
+                return;
+            }
 
-        gesture_click.connect_released(move |_, _, _, _| {
-            debug!("Mouse released");
-            if *is_drawing_release.borrow() {
-                tools_release.borrow_mut().finish_stroke();
-                *is_drawing_release.borrow_mut() = false;
-                drawing_area_release.queue_draw();
+            // Pressing inside the group picks all of it up to move;
+            // anywhere else lets it go
+            if !tools.selected_group.is_empty() {
+                if tools.begin_group_drag(&point) {
+                    *is_drawing_click.borrow_mut() = true;
+                    drawing_area_click.queue_draw();
+                    return;
+                }
+                tools.clear_group();
             }
-        });
 
-        drawing_area.add_controller(gesture_click);
+            // The eraser end of a tablet stylus erases whatever tool is
+            // selected; turning the pen back to its tip draws again
+            let stylus_eraser = gesture
+                .current_event()
+                .and_then(|event| event.device_tool())
+                .map_or(false, |tool| {
+                    tool.tool_type() == gdk4::DeviceToolType::Eraser
+                });
+
+            // A selected shape's handles take the press whatever the tool,
+            // unless the stylus is erasing
+            let scale = screenshot_surface_click
+                .borrow()
+                .as_ref()
+                .map(|surface| {
+                    let allocation = drawing_area_click.allocation();
+                    Self::image_transform(
+                        allocation.width(),
+                        allocation.height(),
+                        surface.width(),
+                        surface.height(),
+                    )
+                    .0
+                })
+                .unwrap_or(1.0);
+            let tolerance = AnnotationTools::resize_handle_tolerance(scale);
+            if !stylus_eraser && tools.begin_shape_resize(&Point::new(image_x, image_y), tolerance)
+            {
+                *is_drawing_click.borrow_mut() = true;
+                drawing_area_click.queue_draw();
+                return;
+            }
 
-        // Mouse motion
+            if tools.current_tool == ToolType::Eraser || stylus_eraser {
+                // Commit any text being typed before erasing around it
+                tools.finish_stroke();
+                // Dragging out a box erases what it touches on release
+                tools.start_erase_box(Point::new(image_x, image_y));
+                *is_drawing_click.borrow_mut() = true;
+                drawing_area_click.queue_draw();
+                return;
+            }
+
+            if tools.current_tool == ToolType::Text {
+                // Clicking places a new caret; commit whatever was being typed
+                tools.finish_stroke();
+                let image_width = screenshot_surface_click
+                    .borrow()
+                    .as_ref()
+                    .map(|surface| surface.width() as f64)
+                    .unwrap_or(f64::MAX);
+                tools.start_text(Point::new(image_x, image_y), image_width - image_x);
+                // Keep tracking the drag so the user can size a wrap box
+                *is_drawing_click.borrow_mut() = true;
+                drawing_area_click.grab_focus();
+                drawing_area_click.queue_draw();
+                return;
+            }
+
+            if tools.current_tool == ToolType::Measure {
+                // Measuring only reads the image; nothing is committed
+                tools.start_measurement(Point::new(image_x, image_y));
+                *is_drawing_click.borrow_mut() = true;
+                drawing_area_click.queue_draw();
+                return;
+            }
+
+            if tools.current_tool == ToolType::Stamp {
+                // Pressing on a stamp picks it up to move; elsewhere places one
+                let point = Point::new(image_x, image_y);
+                match tools.stamp_at(&point) {
+                    Some(index) => tools.begin_stamp_drag(index, &point),
+                    None => tools.place_stamp(point),
+                }
+                *is_drawing_click.borrow_mut() = true;
+                drawing_area_click.grab_focus();
+                drawing_area_click.queue_draw();
+                return;
+            }
+
+            *is_drawing_click.borrow_mut() = true;
+            // Holding Shift continues the last stroke of the same tool
+            let resume = gesture
+                .current_event_state()
+                .contains(ModifierType::SHIFT_MASK);
+            if !(resume && tools.resume_last_stroke(Point::new(image_x, image_y))) {
+                tools.start_stroke(Point::new(image_x, image_y));
+            }
+            drawing_area_click.queue_draw();
+        });
+
+        let tools_release = tools.clone();
+        let is_drawing_release = is_drawing.clone();
+        let drawing_area_release = drawing_area.clone();
+        let status_bar_release = status_bar.clone();
+
+        gesture_click.connect_released(move |_, _, _, _| {
+            debug!("Mouse released");
+            if *is_drawing_release.borrow() {
+                // Text stays open for typing after its box has been placed
+                if !tools_release.borrow().is_editing_text() {
+                    let mut tools = tools_release.borrow_mut();
+                    tools.end_stamp_drag();
+                    tools.end_group_drag();
+                    if tools.is_selecting_group() {
+                        tools.end_group_box();
+                        Self::show_group_status(&status_bar_release, tools.selected_group.len());
+                    }
+                    tools.end_shape_resize();
+                    tools.end_stroke_resume();
+                    tools.end_measurement();
+                    let erased = tools.end_erase_box();
+                    if erased > 0 {
+                        status_bar_release.set_status(&format!(
+                            "Erased {} annotation{}",
+                            erased,
+                            if erased == 1 { "" } else { "s" }
+                        ));
+                    }
+                    tools.finish_stroke();
+                }
+                *is_drawing_release.borrow_mut() = false;
+                drawing_area_release.queue_draw();
+            }
+        });
+
+        drawing_area.add_controller(gesture_click);
+
+        // Mouse motion
         let motion_controller = gtk4::EventControllerMotion::new();
         let tools_motion = tools.clone();
         let is_drawing_motion = is_drawing.clone();
         let drawing_area_motion = drawing_area.clone();
         let status_bar_motion = status_bar.clone();
+        let pointer_position_motion = pointer_position.clone();
         let screenshot_surface_motion = screenshot_surface.clone();
+        let color_panel_motion = color_panel.clone();
 
-        motion_controller.connect_motion(move |_, x, y| {
+        motion_controller.connect_motion(move |controller, x, y| {
             // Convert screen coordinates to image coordinates for display
-            let (image_x, image_y) = if let Some(ref surface) = *screenshot_surface_motion.borrow()
-            {
-                let allocation = drawing_area_motion.allocation();
-                let area_width = allocation.width() as f64;
-                let area_height = allocation.height() as f64;
-                let image_width = surface.width() as f64;
-                let image_height = surface.height() as f64;
-
-                let scale_x = area_width / image_width;
-                let scale_y = area_height / image_height;
-                let scale = scale_x.min(scale_y);
-
-                let scaled_width = image_width * scale;
-                let scaled_height = image_height * scale;
-                let offset_x = (area_width - scaled_width) / 2.0;
-                let offset_y = (area_height - scaled_height) / 2.0;
+            let (image_x, image_y) = match *screenshot_surface_motion.borrow() {
+                Some(ref surface) => Self::widget_to_image(&drawing_area_motion, surface, x, y),
+                None => (x, y),
+            };
 
-                let image_x = (x - offset_x) / scale;
-                let image_y = (y - offset_y) / scale;
+            // Past the pixel-grid zoom, or with the color panel open, also
+            // read the hovered pixel's color
+            let hovered_pixel = screenshot_surface_motion
+                .borrow()
+                .as_ref()
+                .and_then(|surface| {
+                    let allocation = drawing_area_motion.allocation();
+                    let (scale, _, _) = Self::image_transform(
+                        allocation.width(),
+                        allocation.height(),
+                        surface.width(),
+                        surface.height(),
+                    );
+                    let (column, row) = (image_x.floor() as i32, image_y.floor() as i32);
+                    let in_pixel_grid = scale >= PIXEL_GRID_MIN_SCALE;
+                    if !(in_pixel_grid || color_panel_motion.is_revealed())
+                        || column < 0
+                        || row < 0
+                        || column >= surface.width()
+                        || row >= surface.height()
+                    {
+                        return None;
+                    }
 
-                (image_x, image_y)
-            } else {
-                (x, y)
-            };
+                    Self::pixel_color(surface, column, row)
+                        .map_err(|e| warn!("Failed to read hovered pixel: {}", e))
+                        .ok()
+                        .map(|color| (column, row, color, in_pixel_grid))
+                });
 
             // Show image coordinates in status bar
-            status_bar_motion.set_coordinates(image_x, image_y);
+            match hovered_pixel {
+                Some((column, row, color, true)) => {
+                    status_bar_motion.set_pixel_info(column, row, color)
+                }
+                _ => status_bar_motion.set_coordinates(image_x, image_y),
+            }
+            if color_panel_motion.is_revealed() {
+                color_panel_motion
+                    .set_pixel(hovered_pixel.map(|(column, row, color, _)| (column, row, color)));
+            }
+            *pointer_position_motion.borrow_mut() = Some(Point::new(image_x, image_y));
+
+            // The hovered pixel outline follows the pointer
+            if matches!(hovered_pixel, Some((_, _, _, true))) {
+                drawing_area_motion.queue_draw();
+            }
 
             if *is_drawing_motion.borrow() {
-                tools_motion
-                    .borrow_mut()
-                    .add_point_to_stroke(Point::new(image_x, image_y));
+                let mut tools = tools_motion.borrow_mut();
+                if tools.is_dragging_group() {
+                    tools.drag_group_to(&Point::new(image_x, image_y));
+                } else if tools.is_selecting_group() {
+                    tools.update_group_box(Point::new(image_x, image_y));
+                } else if tools.is_resizing_shape() {
+                    let keep_aspect = controller
+                        .current_event_state()
+                        .contains(ModifierType::SHIFT_MASK);
+                    tools.resize_shape_to(&Point::new(image_x, image_y), keep_aspect);
+                } else if tools.is_dragging_stamp() {
+                    tools.drag_stamp_to(&Point::new(image_x, image_y));
+                } else if tools.is_erasing() {
+                    tools.update_erase_box(Point::new(image_x, image_y));
+                } else if tools.is_measuring() {
+                    tools.update_measurement(Point::new(image_x, image_y));
+                    if let Some((distance, angle)) = tools.measurement() {
+                        status_bar_motion.set_status(&format!(
+                            "Distance: {:.1} px, angle: {:.1}°",
+                            distance, angle
+                        ));
+                    }
+                } else if tools.is_editing_text() {
+                    let image_width = screenshot_surface_motion
+                        .borrow()
+                        .as_ref()
+                        .map(|surface| surface.width() as f64)
+                        .unwrap_or(f64::MAX);
+                    tools.resize_text_box(image_x, image_width);
+                } else {
+                    let point = Point::new(image_x, image_y);
+                    // Holding Alt turns snapping off for free placement
+                    let point = match *screenshot_surface_motion.borrow() {
+                        Some(ref surface)
+                            if !controller
+                                .current_event_state()
+                                .contains(ModifierType::ALT_MASK) =>
+                        {
+                            tools.snap_point(point, surface.width() as f64, surface.height() as f64)
+                        }
+                        _ => {
+                            tools.snap_indicator = None;
+                            point
+                        }
+                    };
+                    tools.add_point_to_stroke(point);
+                }
                 drawing_area_motion.queue_draw();
             }
         });
@@ -502,19 +1483,237 @@ impl AnnotationEditor {
         let tools_key = tools.clone();
         let drawing_area_key = drawing_area.clone();
         let is_drawing_key = is_drawing.clone();
+        let pointer_position_key = pointer_position.clone();
+        let screenshot_surface_key = screenshot_surface.clone();
+        let full_resolution_key = full_resolution.clone();
+        let status_bar_key = status_bar.clone();
+        let toolbar_key = toolbar.clone();
+        let stroke_key_name = &preferences::get().keyboard.stroke_key;
+        let stroke_key = gdk4::Key::from_name(stroke_key_name);
+        if stroke_key.is_none() && !stroke_key_name.is_empty() {
+            warn!("Unknown stroke key '{}'", stroke_key_name);
+        }
 
         key_controller.connect_key_pressed(move |_, key, _, modifier| {
+            // Ctrl+V with the text tool pastes clipboard text as an annotation
+            if key == gdk4::Key::v
+                && modifier.contains(ModifierType::CONTROL_MASK)
+                && tools_key.borrow().current_tool == ToolType::Text
+            {
+                let image_width = screenshot_surface_key
+                    .borrow()
+                    .as_ref()
+                    .map(|surface| surface.width() as f64)
+                    .unwrap_or(f64::MAX);
+                Self::paste_text_annotation(
+                    &tools_key,
+                    pointer_position_key.borrow().clone(),
+                    image_width,
+                    &status_bar_key,
+                );
+                drawing_area_key.queue_draw();
+                return glib::Propagation::Stop;
+            }
+
+            // While typing a text annotation, keys edit the text
+            if tools_key.borrow().is_editing_text() {
+                match key {
+                    // Shift+Enter commits, plain Enter starts a new line
+                    gdk4::Key::Return | gdk4::Key::KP_Enter
+                        if modifier.contains(ModifierType::SHIFT_MASK) =>
+                    {
+                        tools_key.borrow_mut().finish_stroke();
+                    }
+                    gdk4::Key::Return | gdk4::Key::KP_Enter => {
+                        tools_key.borrow_mut().insert_text("\n");
+                    }
+                    gdk4::Key::Escape => {
+                        tools_key.borrow_mut().finish_stroke();
+                        *is_drawing_key.borrow_mut() = false;
+                    }
+                    gdk4::Key::BackSpace => {
+                        tools_key.borrow_mut().delete_last_char();
+                    }
+                    _ => match key.to_unicode() {
+                        Some(c)
+                            if !c.is_control()
+                                && !modifier.contains(ModifierType::CONTROL_MASK) =>
+                        {
+                            tools_key.borrow_mut().insert_text(&c.to_string());
+                        }
+                        _ => return glib::Propagation::Proceed,
+                    },
+                }
+                drawing_area_key.queue_draw();
+                return glib::Propagation::Stop;
+            }
+
             match (key, modifier) {
                 (gdk4::Key::Escape, _) => {
                     if *is_drawing_key.borrow() {
                         tools_key.borrow_mut().cancel_stroke();
                         *is_drawing_key.borrow_mut() = false;
                         drawing_area_key.queue_draw();
+                    } else if !tools_key.borrow().selected_group.is_empty() {
+                        tools_key.borrow_mut().clear_group();
+                        drawing_area_key.queue_draw();
+                    }
+                    glib::Propagation::Stop
+                }
+                // Delete removes the whole group at once
+                (gdk4::Key::Delete | gdk4::Key::BackSpace, _)
+                    if !tools_key.borrow().selected_group.is_empty()
+                        && !*is_drawing_key.borrow() =>
+                {
+                    let deleted = tools_key.borrow_mut().delete_group();
+                    status_bar_key.set_status(&format!(
+                        "Deleted {} annotation{}",
+                        deleted,
+                        if deleted == 1 { "" } else { "s" }
+                    ));
+                    drawing_area_key.queue_draw();
+                    glib::Propagation::Stop
+                }
+                // C cycles through the palette colors
+                (gdk4::Key::c | gdk4::Key::C, state)
+                    if !state.contains(ModifierType::CONTROL_MASK)
+                        && !state.contains(ModifierType::ALT_MASK) =>
+                {
+                    let (name, color) = tools_key.borrow_mut().cycle_color();
+                    toolbar_key.select_color(color);
+                    status_bar_key.set_color_status(name, color);
+                    drawing_area_key.queue_draw();
+                    glib::Propagation::Stop
+                }
+                // The stroke key starts a stroke at the pointer, which then
+                // follows it, and commits it on the next press
+                (pressed, state)
+                    if Some(pressed) == stroke_key
+                        && !state.contains(ModifierType::CONTROL_MASK)
+                        && !state.contains(ModifierType::ALT_MASK)
+                        && matches!(
+                            tools_key.borrow().current_tool,
+                            ToolType::Pencil
+                                | ToolType::Line
+                                | ToolType::Arrow
+                                | ToolType::Highlighter
+                                | ToolType::Rectangle
+                        ) =>
+                {
+                    let mut tools = tools_key.borrow_mut();
+                    if *is_drawing_key.borrow() {
+                        // A stroke the pointer never moved on has nothing
+                        // to keep
+                        let is_empty = tools
+                            .current_stroke
+                            .as_ref()
+                            .map_or(true, |stroke| stroke.points.len() < 2);
+                        if is_empty {
+                            tools.cancel_stroke();
+                            status_bar_key.set_status("Nothing drawn");
+                        } else {
+                            tools.finish_stroke();
+                            status_bar_key.set_status("Stroke finished");
+                        }
+                        *is_drawing_key.borrow_mut() = false;
+                    } else {
+                        let Some(point) = pointer_position_key.borrow().clone() else {
+                            status_bar_key.set_status("Move the pointer over the image first");
+                            return glib::Propagation::Stop;
+                        };
+                        tools.start_stroke(point);
+                        *is_drawing_key.borrow_mut() = true;
+                        status_bar_key.set_status(
+                            "Drawing - move the pointer, then press the key again to finish",
+                        );
                     }
+                    drawing_area_key.queue_draw();
+                    glib::Propagation::Stop
+                }
+                // X swaps between the current and the previous tool
+                (gdk4::Key::x | gdk4::Key::X, state)
+                    if !state.contains(ModifierType::CONTROL_MASK)
+                        && !state.contains(ModifierType::ALT_MASK) =>
+                {
+                    let swapped = tools_key.borrow_mut().swap_tool();
+                    match swapped {
+                        Some(tool) => {
+                            toolbar_key.select_tool(tool);
+                            let (color, thickness) = {
+                                let tools = tools_key.borrow();
+                                (tools.current_color, tools.current_thickness)
+                            };
+                            toolbar_key.select_color(color);
+                            status_bar_key.set_tool_info(tool, thickness);
+                        }
+                        None => status_bar_key.set_status("No previous tool to switch to"),
+                    }
+                    drawing_area_key.queue_draw();
+                    glib::Propagation::Stop
+                }
+                // Plus/minus resize the selected stamp
+                (gdk4::Key::plus | gdk4::Key::equal | gdk4::Key::KP_Add, _)
+                    if tools_key.borrow().is_stamp_selected() =>
+                {
+                    tools_key.borrow_mut().scale_selected_stamp(1.1);
+                    drawing_area_key.queue_draw();
+                    glib::Propagation::Stop
+                }
+                (gdk4::Key::minus | gdk4::Key::KP_Subtract, _)
+                    if tools_key.borrow().is_stamp_selected() =>
+                {
+                    tools_key.borrow_mut().scale_selected_stamp(1.0 / 1.1);
+                    drawing_area_key.queue_draw();
+                    glib::Propagation::Stop
+                }
+                // Tab and Shift+Tab step through the annotations
+                (gdk4::Key::Tab | gdk4::Key::ISO_Left_Tab, state)
+                    if !state.contains(ModifierType::CONTROL_MASK) && !*is_drawing_key.borrow() =>
+                {
+                    let forward =
+                        key == gdk4::Key::Tab && !state.contains(ModifierType::SHIFT_MASK);
+                    let selected = tools_key.borrow_mut().cycle_selection(forward);
+                    annotation_list_key.select(selected);
+                    match selected {
+                        Some(index) => {
+                            status_bar_key.set_status(&format!("Annotation {}", index + 1))
+                        }
+                        None => status_bar_key.set_status("No annotations"),
+                    }
+                    drawing_area_key.queue_draw();
                     glib::Propagation::Stop
                 }
                 (gdk4::Key::z, ModifierType::CONTROL_MASK) => {
-                    // Could implement undo here in future versions
+                    if tools_key.borrow_mut().undo() {
+                        Self::pick_up_screenshot_change(
+                            &tools_key,
+                            &screenshot_surface_key,
+                            &full_resolution_key,
+                        );
+                        status_bar_key.set_status("Undone");
+                        drawing_area_key.queue_draw();
+                    } else {
+                        status_bar_key.set_status("Nothing to undo");
+                    }
+                    glib::Propagation::Stop
+                }
+                // Ctrl+Shift+Z or Ctrl+Y
+                (gdk4::Key::Z | gdk4::Key::z | gdk4::Key::y, state)
+                    if (key == gdk4::Key::y && state == ModifierType::CONTROL_MASK)
+                        || (key != gdk4::Key::y
+                            && state == ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK) =>
+                {
+                    if tools_key.borrow_mut().redo() {
+                        Self::pick_up_screenshot_change(
+                            &tools_key,
+                            &screenshot_surface_key,
+                            &full_resolution_key,
+                        );
+                        status_bar_key.set_status("Redone");
+                        drawing_area_key.queue_draw();
+                    } else {
+                        status_bar_key.set_status("Nothing to redo");
+                    }
                     glib::Propagation::Stop
                 }
                 _ => glib::Propagation::Proceed,
@@ -523,12 +1722,383 @@ impl AnnotationEditor {
 
         drawing_area.add_controller(key_controller);
         drawing_area.set_can_focus(true);
+
+        // Scrolling over the canvas changes the thickness without a trip to
+        // the toolbar; scrolling up makes strokes thicker
+        let scroll_controller = gtk4::EventControllerScroll::new(
+            gtk4::EventControllerScrollFlags::VERTICAL | gtk4::EventControllerScrollFlags::DISCRETE,
+        );
+        let status_bar_scroll = status_bar.clone();
+        scroll_controller.connect_scroll(move |_, _, dy| {
+            if dy == 0.0 {
+                return glib::Propagation::Proceed;
+            }
+            let thickness = toolbar.step_thickness(-dy.signum());
+            status_bar_scroll.set_status(&format!("Thickness: {:.0}px", thickness));
+            glib::Propagation::Stop
+        });
+        drawing_area.add_controller(scroll_controller);
+    }
+
+    fn show_group_status(status_bar: &StatusBar, count: usize) {
+        match count {
+            0 => status_bar.set_status("No annotations selected"),
+            1 => status_bar.set_status("1 annotation selected"),
+            count => status_bar.set_status(&format!("{} annotations selected", count)),
+        }
+    }
+
+    // Scale and offset that fit the image centered within the drawing area
+    fn image_transform(
+        area_width: i32,
+        area_height: i32,
+        image_width: i32,
+        image_height: i32,
+    ) -> (f64, f64, f64) {
+        let area_width = area_width as f64;
+        let area_height = area_height as f64;
+        let image_width = image_width as f64;
+        let image_height = image_height as f64;
+
+        // Calculate scale factor to fit image within the drawing area
+        let scale_x = area_width / image_width;
+        let scale_y = area_height / image_height;
+        let scale = scale_x.min(scale_y);
+
+        // Calculate centered position
+        let offset_x = (area_width - image_width * scale) / 2.0;
+        let offset_y = (area_height - image_height * scale) / 2.0;
+
+        (scale, offset_x, offset_y)
+    }
+
+    // Widget coordinates to image coordinates, the same transform the canvas
+    // is drawn with. Annotations are stored in these: pixels of the
+    // screenshot surface, whatever the window size or display scale. The
+    // surface holds the capture's own pixels, or a working copy scaled by
+    // `FullResolution::scale`, which exports undo exactly.
+    fn widget_to_image(
+        drawing_area: &DrawingArea,
+        surface: &ImageSurface,
+        x: f64,
+        y: f64,
+    ) -> (f64, f64) {
+        let allocation = drawing_area.allocation();
+        Self::area_to_image(allocation.width(), allocation.height(), surface, x, y)
+    }
+
+    // widget_to_image for a drawing area of the given size
+    fn area_to_image(
+        area_width: i32,
+        area_height: i32,
+        surface: &ImageSurface,
+        x: f64,
+        y: f64,
+    ) -> (f64, f64) {
+        let (scale, offset_x, offset_y) =
+            Self::image_transform(area_width, area_height, surface.width(), surface.height());
+
+        ((x - offset_x) / scale, (y - offset_y) / scale)
+    }
+
+    // Faint lines along image pixel boundaries and an outline around the
+    // hovered pixel. Drawn in widget space on top of the in-progress overlay,
+    // which is never part of an export.
+    fn draw_pixel_grid(
+        ctx: &Context,
+        image_width: i32,
+        image_height: i32,
+        (scale, offset_x, offset_y): (f64, f64, f64),
+        hovered: Option<&Point>,
+    ) {
+        // Round to whole widget pixels plus a half so 1px lines stay crisp
+        let to_area = |offset: f64, index: f64| (offset + index * scale).round() + 0.5;
+        let right = offset_x + image_width as f64 * scale;
+        let bottom = offset_y + image_height as f64 * scale;
+
+        ctx.save().unwrap();
+        ctx.set_line_width(1.0);
+        ctx.set_source_rgba(0.5, 0.5, 0.5, 0.35);
+        for column in 0..=image_width {
+            let x = to_area(offset_x, column as f64);
+            ctx.move_to(x, offset_y);
+            ctx.line_to(x, bottom);
+        }
+        for row in 0..=image_height {
+            let y = to_area(offset_y, row as f64);
+            ctx.move_to(offset_x, y);
+            ctx.line_to(right, y);
+        }
+        ctx.stroke().unwrap();
+
+        if let Some(point) = hovered {
+            let (column, row) = (point.x.floor(), point.y.floor());
+            if column >= 0.0
+                && row >= 0.0
+                && column < image_width as f64
+                && row < image_height as f64
+            {
+                let x = to_area(offset_x, column);
+                let y = to_area(offset_y, row);
+                let size = to_area(offset_x, column + 1.0) - x;
+
+                // Dark halo under a white outline shows on any pixel color
+                ctx.rectangle(x, y, size, size);
+                ctx.set_source_rgba(0.0, 0.0, 0.0, 0.8);
+                ctx.set_line_width(3.0);
+                ctx.stroke_preserve().unwrap();
+                ctx.set_source_rgb(1.0, 1.0, 1.0);
+                ctx.set_line_width(1.0);
+                ctx.stroke().unwrap();
+            }
+        }
+        ctx.restore().unwrap();
+    }
+
+    // Straight-alpha RGBA of a single screenshot pixel
+    fn pixel_color(source: &ImageSurface, x: i32, y: i32) -> Result<[u8; 4]> {
+        let mut surface = ImageSurface::create(Format::ARgb32, 1, 1)
+            .map_err(|e| anyhow!("Failed to create surface: {}", e))?;
+        {
+            let ctx =
+                Context::new(&surface).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+            ctx.set_source_surface(source, -x as f64, -y as f64)
+                .map_err(|e| anyhow!("Failed to set source surface: {}", e))?;
+            ctx.paint()
+                .map_err(|e| anyhow!("Failed to paint surface: {}", e))?;
+        }
+
+        Ok(Self::read_rgba(&mut surface)?.get_pixel(0, 0).0)
+    }
+
+    fn render_base_layer(
+        width: i32,
+        height: i32,
+        scale_factor: i32,
+        screenshot: Option<&ImageSurface>,
+        tools: &AnnotationTools,
+    ) -> Result<ImageSurface> {
+        // Render at device resolution so the cache stays sharp on HiDPI
+        let surface =
+            ImageSurface::create(Format::ARgb32, width * scale_factor, height * scale_factor)
+                .map_err(|e| anyhow!("Failed to create cache surface: {}", e))?;
+        surface.set_device_scale(scale_factor as f64, scale_factor as f64);
+
+        let ctx = Context::new(&surface).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+        Self::draw_base_layer(&ctx, width, height, screenshot, tools);
+        drop(ctx);
+
+        Ok(surface)
+    }
+
+    // One 20px cell of the dot grid, repeated across the whole background.
+    // Painting it takes about 1ms on a 1920x1080 area and 3-4ms at
+    // 3840x2160, where one arc per grid point took 10-12ms and 42-44ms; see
+    // texture_pattern_timing.
+    fn texture_pattern() -> Result<cairo::SurfacePattern> {
+        const CELL_SIZE: i32 = 20;
+
+        let tile = ImageSurface::create(Format::ARgb32, CELL_SIZE, CELL_SIZE)
+            .map_err(|e| anyhow!("Failed to create texture tile: {}", e))?;
+        let ctx = Context::new(&tile).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+
+        ctx.set_source_rgba(1.0, 1.0, 1.0, 0.01); // Very subtle white dots
+
+        // Dots sit on the grid corners, so each corner carries a quarter of one
+        for (x, y) in [
+            (0, 0),
+            (CELL_SIZE, 0),
+            (0, CELL_SIZE),
+            (CELL_SIZE, CELL_SIZE),
+        ] {
+            ctx.arc(x as f64, y as f64, 0.5, 0.0, 2.0 * std::f64::consts::PI);
+            ctx.fill().unwrap();
+        }
+        drop(ctx);
+
+        let pattern = cairo::SurfacePattern::create(&tile);
+        pattern.set_extend(cairo::Extend::Repeat);
+        Ok(pattern)
+    }
+
+    // Everything that only changes when the image, size or finished strokes do
+    fn draw_base_layer(
+        ctx: &Context,
+        width: i32,
+        height: i32,
+        screenshot: Option<&ImageSurface>,
+        tools: &AnnotationTools,
+    ) {
+        let background = preferences::get().ui.editor_background;
+
+        if background == EditorBackground::Gradient {
+            // Create a subtle gradient background for a modern look
+            let gradient = cairo::LinearGradient::new(0.0, 0.0, 0.0, height as f64);
+            gradient.add_color_stop_rgb(0.0, 0.15, 0.17, 0.21); // Top: #262D35
+            gradient.add_color_stop_rgb(1.0, 0.12, 0.14, 0.18); // Bottom: slightly darker
+            ctx.set_source(&gradient).unwrap();
+            ctx.paint().unwrap();
+
+            // Add a subtle texture pattern, tiled from a single cell instead of
+            // filling one arc per grid point
+            match Self::texture_pattern() {
+                Ok(pattern) => {
+                    ctx.save().unwrap();
+                    ctx.set_source(&pattern).unwrap();
+                    ctx.paint().unwrap();
+                    ctx.restore().unwrap();
+                }
+                Err(e) => warn!("Skipping background texture: {}", e),
+            }
+        } else {
+            let color = &preferences::get().ui.editor_background_color;
+            let color = gdk4::RGBA::parse(color.as_str()).unwrap_or_else(|_| {
+                warn!("Invalid editor background color '{}'", color);
+                gdk4::RGBA::new(0.15, 0.17, 0.21, 1.0)
+            });
+            ctx.set_source_rgb(
+                color.red() as f64,
+                color.green() as f64,
+                color.blue() as f64,
+            );
+            ctx.paint().unwrap();
+        }
+
+        // Draw the screenshot first
+        if let Some(surface) = screenshot {
+            debug!("Drawing screenshot surface");
+
+            let (scale, offset_x, offset_y) =
+                Self::image_transform(width, height, surface.width(), surface.height());
+
+            // Opaque screenshots cover the checkerboard completely, so it
+            // only shows through where the image is transparent
+            if background == EditorBackground::Checkerboard {
+                match ui::checkerboard_pattern() {
+                    Ok(pattern) => {
+                        ctx.save().unwrap();
+                        ctx.rectangle(
+                            offset_x,
+                            offset_y,
+                            surface.width() as f64 * scale,
+                            surface.height() as f64 * scale,
+                        );
+                        ctx.set_source(&pattern).unwrap();
+                        ctx.fill().unwrap();
+                        ctx.restore().unwrap();
+                    }
+                    Err(e) => warn!("Skipping checkerboard: {}", e),
+                }
+            }
+
+            ctx.save().unwrap();
+            ctx.translate(offset_x, offset_y);
+            ctx.scale(scale, scale);
+            ctx.set_source_surface(surface, 0.0, 0.0).unwrap();
+            // Zoomed far in, show pixels as sharp blocks instead of a blur
+            if scale >= PIXEL_GRID_MIN_SCALE {
+                ctx.source().set_filter(cairo::Filter::Nearest);
+            }
+            ctx.paint().unwrap();
+
+            // Draw finished annotations on top (they need to be scaled too)
+            tools.draw_finished(ctx);
+            ctx.restore().unwrap();
+
+            debug!(
+                "Image scaled by {:.2} and positioned at ({:.1}, {:.1})",
+                scale, offset_x, offset_y
+            );
+        } else {
+            warn!("No screenshot surface available to draw");
+            // Draw a placeholder with subtle dark background
+            ctx.set_source_rgb(0.18, 0.20, 0.24); // Slightly lighter than main background
+            ctx.rectangle(0.0, 0.0, width as f64, height as f64);
+            ctx.fill().unwrap();
+
+            // Draw text indicating no image with light text
+            ctx.set_source_rgb(0.7, 0.7, 0.7); // Light gray text for dark theme
+            ctx.move_to(20.0, height as f64 / 2.0);
+            ctx.show_text("No screenshot loaded").unwrap();
+
+            // If no image, draw annotations without scaling
+            tools.draw_finished(ctx);
+        }
+    }
+
+    fn paste_text_annotation(
+        tools: &Rc<RefCell<AnnotationTools>>,
+        pointer_position: Option<Point>,
+        image_width: f64,
+        status_bar: &StatusBar,
+    ) {
+        let text = match Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => text.replace("\r\n", "\n"),
+            Err(e) => {
+                warn!("Failed to read text from clipboard: {}", e);
+                status_bar.set_status("Clipboard has no text to paste");
+                return;
+            }
+        };
+
+        if text.is_empty() {
+            status_bar.set_status("Clipboard has no text to paste");
+            return;
+        }
+
+        let mut tools = tools.borrow_mut();
+
+        if tools.is_editing_text() {
+            // Paste at the caret of the annotation being typed
+            tools.insert_text(&text);
+        } else if let Some(point) = pointer_position {
+            // Otherwise place a new annotation under the cursor and commit it
+            let max_width = image_width - point.x;
+            tools.start_text(point, max_width);
+            tools.insert_text(&text);
+            tools.finish_stroke();
+        } else {
+            status_bar.set_status("Click on the image to choose where to paste");
+            return;
+        }
+
+        info!(
+            "Pasted {} characters as a text annotation",
+            text.chars().count()
+        );
+        status_bar.set_status("Pasted text annotation");
+    }
+
+    // Offer to re-crop the capture. `callback` gets the editor window, which
+    // is hidden while the new region is chosen.
+    pub fn connect_recrop<F: Fn(&ApplicationWindow) + 'static>(&self, callback: F) {
+        let window = self.window.clone();
+        self.toolbar
+            .connect_recrop_clicked(move || callback(&window));
+    }
+
+    // Run `callback` once the editor window has been closed
+    // A save releases this capture's recovery copy, as closing does
+    pub fn set_recovery_capture(&self, capture: u64) {
+        self.recovery_capture.set(Some(capture));
+    }
+
+    pub fn connect_closed<F: Fn() + 'static>(&self, callback: F) {
+        self.window.connect_close_request(move |_| {
+            callback();
+            glib::Propagation::Proceed
+        });
     }
 
     pub fn show(&self) {
         info!("Showing annotation editor window");
         self.status_bar
             .set_status("Ready - Select a tool and start annotating");
+        {
+            let tools = self.tools.borrow();
+            self.status_bar
+                .set_tool_info(tools.current_tool, tools.current_thickness);
+        }
 
         // Force a redraw to ensure the screenshot is displayed
         self.drawing_area.queue_draw();
@@ -536,16 +2106,45 @@ impl AnnotationEditor {
         // Show and present the window
         self.window.set_visible(true);
         self.window.present();
+        if let Some(ref monitor) = self.monitor {
+            Self::center_on_monitor(&self.window, monitor);
+        }
         gtk4::prelude::GtkWindowExt::set_focus(&self.window, Some(&self.drawing_area));
 
         info!("Editor window presented and focused");
     }
 
+    // GTK 4 cannot position windows, so on X11 move the window ourselves.
+    // Wayland compositors always decide placement on their own.
+    fn center_on_monitor(window: &ApplicationWindow, monitor: &gdk4::Monitor) {
+        let window_id = match window_manager::x11_window_id(window) {
+            Some(window_id) => window_id,
+            None => {
+                debug!("Leaving editor placement to the compositor");
+                return;
+            }
+        };
+
+        let geometry = monitor.geometry();
+        let scale_factor = monitor.scale_factor().max(1);
+        let x = geometry.x() + (geometry.width() - window.default_width()).max(0) / 2;
+        let y = geometry.y() + (geometry.height() - window.default_height()).max(0) / 2;
+
+        match window_manager::WindowManager::new()
+            .and_then(|manager| manager.move_window(window_id, x * scale_factor, y * scale_factor))
+        {
+            Ok(_) => info!("Placed editor on monitor at ({}, {})", x, y),
+            Err(e) => warn!("Failed to place editor on its monitor: {}", e),
+        }
+    }
+
     fn handle_save_action(
         window: &ApplicationWindow,
         screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
         tools: &Rc<RefCell<AnnotationTools>>,
         status_bar: &StatusBar,
+        recovery_capture: &Rc<Cell<Option<u64>>>,
         image_width: i32,
         image_height: i32,
     ) {
@@ -559,28 +2158,145 @@ impl AnnotationEditor {
             ],
         );
 
-        dialog.set_current_name("flint-screenshot.png");
+        dialog.set_current_name(&format!(
+            "flint-screenshot.{}",
+            preferences::get().general.format.extension()
+        ));
+
+        // Export scale: annotations are redrawn at the target resolution
+        dialog.add_choice(
+            "scale",
+            "Export scale",
+            &[("1", "1x"), ("2", "2x"), ("3", "3x")],
+        );
+        dialog.set_choice("scale", "1");
+
+        // Annotations alone on a transparent background, for compositing elsewhere
+        dialog.add_choice(
+            "content",
+            "Include",
+            &[
+                ("all", "Screenshot and annotations"),
+                ("annotations", "Annotations only (transparent)"),
+            ],
+        );
+        dialog.set_choice("content", "all");
+
+        // Only offered once a watermark has been set up in flint.toml
+        if watermark::is_configured() {
+            dialog.add_choice("watermark", "Watermark", &[]);
+            dialog.set_choice(
+                "watermark",
+                if preferences::get().watermark.enabled {
+                    "true"
+                } else {
+                    "false"
+                },
+            );
+        }
+
+        // Date and time bar below the image, followed by the text typed in
+        // the entry under the file chooser
+        let caption_preferences = &preferences::get().caption;
+        dialog.add_choice("caption", "Caption bar", &[]);
+        dialog.set_choice(
+            "caption",
+            if caption_preferences.enabled {
+                "true"
+            } else {
+                "false"
+            },
+        );
+        let caption_entry = Entry::new();
+        caption_entry.set_text(&caption_preferences.text);
+        caption_entry.set_placeholder_text(Some("Caption bar text (after the date and time)"));
+        caption_entry.set_margin_start(12);
+        caption_entry.set_margin_end(12);
+        caption_entry.set_margin_bottom(6);
+        dialog.content_area().append(&caption_entry);
+
+        // Annotation types and coordinates in a JSON file next to the image
+        dialog.add_choice("sidecar", "Also save annotation coordinates (.json)", &[]);
+        dialog.set_choice("sidecar", "false");
 
         let screenshot_surface_clone = screenshot_surface.clone();
+        let full_resolution_clone = full_resolution.clone();
         let tools_clone = tools.clone();
         let status_bar_clone = status_bar.clone();
+        let recovery_capture = recovery_capture.clone();
 
         dialog.connect_response(move |dialog, response| {
             if response == ResponseType::Accept {
                 if let Some(file) = dialog.file() {
                     if let Some(path) = file.path() {
-                        info!("Attempting to save to: {}", path.display());
+                        let scale = dialog
+                            .choice("scale")
+                            .and_then(|choice| choice.parse::<f64>().ok())
+                            .unwrap_or(1.0);
+                        let annotations_only =
+                            dialog.choice("content").as_deref() == Some("annotations");
+                        let add_watermark = dialog.choice("watermark").as_deref() == Some("true");
+                        let write_sidecar = dialog.choice("sidecar").as_deref() == Some("true");
+                        let caption_line = (dialog.choice("caption").as_deref() == Some("true"))
+                            .then(|| caption::line(&caption_entry.text()));
+                        info!(
+                            "Attempting to save to: {} at {}x scale{}",
+                            path.display(),
+                            scale,
+                            if annotations_only {
+                                ", annotations only"
+                            } else {
+                                ""
+                            }
+                        );
                         match Self::render_to_file_static(
                             &path,
                             &screenshot_surface_clone,
+                            &full_resolution_clone,
                             &tools_clone,
                             image_width,
                             image_height,
+                            &ExportOptions {
+                                scale,
+                                annotations_only,
+                                add_watermark,
+                                caption_line: caption_line.as_deref(),
+                            },
                         ) {
                             Ok(_) => {
                                 status_bar_clone
                                     .set_status(&format!("Saved to {}", path.display()));
                                 info!("Screenshot saved successfully to: {}", path.display());
+                                if let Some(capture) = recovery_capture.get() {
+                                    recovery::release(capture);
+                                }
+
+                                if write_sidecar {
+                                    // Match the exported image's pixels, which
+                                    // a downscaled working copy does not
+                                    let full_scale = full_resolution_clone
+                                        .borrow()
+                                        .as_ref()
+                                        .map_or(1.0, |full| full.scale);
+                                    match project::write_sidecar(
+                                        &path,
+                                        &tools_clone.borrow().strokes,
+                                        scale / full_scale,
+                                    ) {
+                                        Ok(sidecar) => status_bar_clone.set_status(&format!(
+                                            "Saved to {} with coordinates in {}",
+                                            path.display(),
+                                            sidecar.display()
+                                        )),
+                                        Err(e) => {
+                                            error!("Failed to write annotation sidecar: {}", e);
+                                            status_bar_clone.set_status(&format!(
+                                                "Saved image, but failed to write coordinates: {}",
+                                                e
+                                            ));
+                                        }
+                                    }
+                                }
                             }
                             Err(e) => {
                                 error!("Failed to save file to {}: {}", path.display(), e);
@@ -604,171 +2320,902 @@ impl AnnotationEditor {
         dialog.present();
     }
 
+    fn project_file_filter() -> gtk4::FileFilter {
+        let filter = gtk4::FileFilter::new();
+        filter.set_name(Some("Flint projects"));
+        filter.add_pattern(&format!("*.{}", project::PROJECT_EXTENSION));
+        filter
+    }
+
+    fn handle_save_project_action(
+        window: &ApplicationWindow,
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
+        tools: &Rc<RefCell<AnnotationTools>>,
+        status_bar: &StatusBar,
+    ) {
+        let dialog = FileChooserDialog::new(
+            Some("Save Project"),
+            Some(window),
+            FileChooserAction::Save,
+            &[
+                ("Cancel", ResponseType::Cancel),
+                ("Save", ResponseType::Accept),
+            ],
+        );
+        dialog.set_current_name(&format!("flint-screenshot.{}", project::PROJECT_EXTENSION));
+        dialog.add_filter(&Self::project_file_filter());
+
+        let screenshot_surface = screenshot_surface.clone();
+        let full_resolution = full_resolution.clone();
+        let tools = tools.clone();
+        let status_bar = status_bar.clone();
+
+        dialog.connect_response(move |dialog, response| {
+            if response == ResponseType::Accept {
+                if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                    let path = if path.extension().is_none() {
+                        path.with_extension(project::PROJECT_EXTENSION)
+                    } else {
+                        path
+                    };
+                    match Self::save_project(&path, &screenshot_surface, &full_resolution, &tools) {
+                        Ok(_) => {
+                            status_bar.set_status(&format!("Project saved to {}", path.display()))
+                        }
+                        Err(e) => {
+                            error!("Failed to save project to {}: {}", path.display(), e);
+                            status_bar.set_status(&format!("Error saving project: {}", e));
+                        }
+                    }
+                }
+            }
+            dialog.close();
+        });
+
+        dialog.present();
+    }
+
+    // The base image is stored unannotated so every stroke stays editable.
+    // A downscaled capture is stored at full resolution, with the strokes
+    // scaled up to match it.
+    fn save_project(
+        path: &Path,
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
+        tools: &Rc<RefCell<AnnotationTools>>,
+    ) -> Result<()> {
+        let mut strokes = tools.borrow().strokes.clone();
+        if let Some(ref full) = *full_resolution.borrow() {
+            for stroke in &mut strokes {
+                stroke.scale_by(1.0 / full.scale);
+            }
+            return Project::new(full.png.clone(), strokes).save(path);
+        }
+
+        let image = match *screenshot_surface.borrow() {
+            Some(ref surface) => Self::surface_to_rgba(surface)?,
+            None => return Err(anyhow!("No screenshot loaded")),
+        };
+
+        let mut image_png = Vec::new();
+        image
+            .write_to(
+                &mut std::io::Cursor::new(&mut image_png),
+                image::ImageOutputFormat::Png,
+            )
+            .map_err(|e| anyhow!("Failed to encode PNG: {}", e))?;
+
+        Project::new(image_png, strokes).save(path)
+    }
+
+    fn handle_open_project_action(window: &ApplicationWindow, status_bar: &StatusBar) {
+        let app = match window.application() {
+            Some(app) => app,
+            None => return,
+        };
+
+        let dialog = FileChooserDialog::new(
+            Some("Open Project"),
+            Some(window),
+            FileChooserAction::Open,
+            &[
+                ("Cancel", ResponseType::Cancel),
+                ("Open", ResponseType::Accept),
+            ],
+        );
+        dialog.add_filter(&Self::project_file_filter());
+
+        let status_bar = status_bar.clone();
+
+        dialog.connect_response(move |dialog, response| {
+            if response == ResponseType::Accept {
+                if let Some(path) = dialog.file().and_then(|file| file.path()) {
+                    match Self::open_project(&app, &path) {
+                        Ok(editor) => editor.show(),
+                        Err(e) => {
+                            error!("Failed to open project {}: {}", path.display(), e);
+                            status_bar.set_status(&format!("Error opening project: {}", e));
+                        }
+                    }
+                }
+            }
+            dialog.close();
+        });
+
+        dialog.present();
+    }
+
+    // Reopen a saved project in a new editor window
+    pub fn open_project(app: &Application, path: &Path) -> Result<Self> {
+        let project = Project::load(path)?;
+        let editor = Self::new(app, project.image_png, None, true)?;
+
+        // Projects hold full-resolution coordinates
+        let mut strokes = project.strokes;
+        if let Some(ref full) = *editor.full_resolution.borrow() {
+            for stroke in &mut strokes {
+                stroke.scale_by(full.scale);
+            }
+        }
+        editor.tools.borrow_mut().load_strokes(strokes);
+        Ok(editor)
+    }
+
     fn handle_copy_action(
         screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
         tools: &Rc<RefCell<AnnotationTools>>,
         status_bar: &StatusBar,
         image_width: i32,
         image_height: i32,
     ) {
-        match Self::copy_to_clipboard_static(screenshot_surface, tools, image_width, image_height) {
+        match Self::copy_to_clipboard_static(
+            screenshot_surface,
+            full_resolution,
+            tools,
+            image_width,
+            image_height,
+        ) {
             Ok(_) => {
                 status_bar.set_status("Copied to clipboard");
                 info!("Screenshot copied to clipboard");
             }
             Err(e) => {
                 error!("Failed to copy to clipboard: {}", e);
-                status_bar.set_status("Error copying to clipboard");
+                status_bar.set_status(&format!("Error copying to clipboard: {}", e));
             }
         }
     }
 
-    fn render_to_file_static<P: AsRef<Path>>(
-        path: P,
+    // Copy the PNG as `data:image/png;base64,...` text, for pasting straight
+    // into Markdown or HTML
+    fn handle_copy_data_uri_action(
         screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
         tools: &Rc<RefCell<AnnotationTools>>,
+        status_bar: &StatusBar,
         image_width: i32,
         image_height: i32,
-    ) -> Result<()> {
-        let path_ref = path.as_ref();
-        info!("Creating render surface {}x{}", image_width, image_height);
+    ) {
+        let png_data = match Self::render_png_static(
+            screenshot_surface,
+            full_resolution,
+            tools,
+            image_width,
+            image_height,
+        ) {
+            Ok(png_data) => png_data,
+            Err(e) => {
+                error!("Failed to render image for data URI: {}", e);
+                status_bar.set_status(&format!("Error copying data URI: {}", e));
+                return;
+            }
+        };
 
-        let mut surface = ImageSurface::create(Format::ARgb32, image_width, image_height)
-            .map_err(|e| anyhow!("Failed to create surface: {}", e))?;
+        let data_uri = format!("data:image/png;base64,{}", BASE64.encode(&png_data));
+        let size_kb = data_uri.len() / 1024;
+        match clipboard::copy_text(&data_uri) {
+            Ok(_) if data_uri.len() > DATA_URI_WARN_LENGTH => {
+                warn!("Copied a {} KB data URI", size_kb);
+                status_bar.set_status(&format!(
+                    "Copied data URI, but it is {} KB; some editors may reject it",
+                    size_kb
+                ));
+            }
+            Ok(_) => {
+                info!("Copied a {} KB data URI", size_kb);
+                status_bar.set_status(&format!("Copied data URI ({} KB)", size_kb));
+            }
+            Err(e) => {
+                error!("Failed to copy data URI to clipboard: {}", e);
+                status_bar.set_status(&format!("Error copying data URI: {}", e));
+            }
+        }
+    }
 
-        let ctx = Context::new(&surface).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+    fn handle_upload_action(
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
+        tools: &Rc<RefCell<AnnotationTools>>,
+        status_bar: &StatusBar,
+        image_width: i32,
+        image_height: i32,
+    ) {
+        let uploader = match HttpUploader::from_preferences(&preferences::get().upload) {
+            Some(uploader) => uploader,
+            None => {
+                status_bar.set_status("Set an upload URL in flint.toml to enable uploading");
+                return;
+            }
+        };
 
-        // Draw screenshot
-        if let Some(ref screenshot) = *screenshot_surface.borrow() {
-            info!("Drawing screenshot to surface");
-            ctx.set_source_surface(screenshot, 0.0, 0.0)
-                .map_err(|e| anyhow!("Failed to set source surface: {}", e))?;
-            ctx.paint()
-                .map_err(|e| anyhow!("Failed to paint surface: {}", e))?;
-        } else {
-            warn!("No screenshot surface available for saving");
-        }
+        let png_data = match Self::render_png_static(
+            screenshot_surface,
+            full_resolution,
+            tools,
+            image_width,
+            image_height,
+        ) {
+            Ok(png_data) => png_data,
+            Err(e) => {
+                error!("Failed to render image for upload: {}", e);
+                status_bar.set_status(&format!("Error preparing upload: {}", e));
+                return;
+            }
+        };
 
-        // Draw annotations
-        info!("Drawing annotations to surface");
-        tools.borrow().draw_all(&ctx);
+        status_bar.set_status("Uploading...");
 
-        // Finish all drawing operations
-        drop(ctx);
+        // Upload off the main thread so the editor stays responsive
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(uploader.upload(&png_data));
+        });
 
-        // Convert to image data using a safer approach without exclusive access
-        info!("Converting surface to image data");
-        let image_data = {
-            surface.flush();
-            let stride = surface.stride();
-            let width = surface.width();
-            let height = surface.height();
-
-            // Create a new vector to hold the converted data
-            let mut rgba_data = Vec::new();
-
-            // Process the surface data in chunks to avoid exclusive access issues
-            unsafe {
-                let data_ptr = surface.data().unwrap().as_ptr();
-                for y in 0..height {
-                    for x in 0..width {
-                        let pixel_offset = (y * stride + x * 4) as isize;
-                        let pixel_ptr = data_ptr.offset(pixel_offset);
-
-                        // Cairo ARGB format is BGRA on little-endian
-                        let b = *pixel_ptr;
-                        let g = *pixel_ptr.offset(1);
-                        let r = *pixel_ptr.offset(2);
-                        let a = *pixel_ptr.offset(3);
-
-                        rgba_data.extend_from_slice(&[r, g, b, a]);
+        let status_bar = status_bar.clone();
+        glib::timeout_add_local(
+            std::time::Duration::from_millis(100),
+            move || match receiver.try_recv() {
+                Ok(Ok(url)) => {
+                    match clipboard::copy_text(&url) {
+                        Ok(_) => status_bar.set_status(&format!("Uploaded, URL copied: {}", url)),
+                        Err(e) => {
+                            error!("Failed to copy upload URL to clipboard: {}", e);
+                            status_bar.set_status(&format!("Uploaded to {}", url));
+                        }
                     }
+                    glib::ControlFlow::Break
                 }
+                Ok(Err(e)) => {
+                    error!("Upload failed: {}", e);
+                    status_bar.set_status(&format!("Upload failed: {}", e));
+                    glib::ControlFlow::Break
+                }
+                Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+                Err(_) => {
+                    error!("Upload thread failed");
+                    status_bar.set_status("Upload failed unexpectedly");
+                    glib::ControlFlow::Break
+                }
+            },
+        );
+    }
+
+    // Render the export and read it back as RGBA, returning the surface as
+    // well for the capture history. Saving, copying and uploading all go
+    // through here so they produce the same pixels. The output is the
+    // options' scale times the image size, measured from the full-resolution
+    // original when there is one rather than from the working copy. The
+    // screenshot is painted first, unless there is none or only the
+    // annotations are wanted, in which case they sit on a fully transparent
+    // background; the watermark then goes over everything in output pixels,
+    // and a caption line adds a bar below the image to hold it.
+    fn composite_to_rgba(
+        screenshot: Option<&ImageSurface>,
+        full_resolution: Option<&FullResolution>,
+        tools: &Rc<RefCell<AnnotationTools>>,
+        image_width: i32,
+        image_height: i32,
+        options: &ExportOptions,
+    ) -> Result<(image::RgbaImage, ImageSurface)> {
+        let screenshot = screenshot.filter(|_| !options.annotations_only);
+        let caption_line = options.caption_line;
+        let full_scale = full_resolution.map_or(1.0, |full| full.scale);
+        let scale = options.scale / full_scale;
+        let width = (image_width as f64 * scale).round() as i32;
+        let height = (image_height as f64 * scale).round() as i32;
+        let bar_height = caption_line.map_or(0, |_| caption::bar_height());
+        info!("Compositing {}x{} image", width, height + bar_height);
+
+        let mut surface = ImageSurface::create(Format::ARgb32, width, height + bar_height)
+            .map_err(|e| anyhow!("Failed to create surface: {}", e))?;
+        {
+            let ctx =
+                Context::new(&surface).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+
+            // Render in image coordinates; the transform makes vector
+            // annotations redraw crisply at the target resolution
+            ctx.scale(scale, scale);
+
+            if let Some(screenshot) = screenshot {
+                let original = full_resolution.map(FullResolution::surface).transpose()?;
+                ctx.save()?;
+                if original.is_some() {
+                    ctx.scale(full_scale, full_scale);
+                }
+                ctx.set_source_surface(original.as_ref().unwrap_or(screenshot), 0.0, 0.0)
+                    .map_err(|e| anyhow!("Failed to set source surface: {}", e))?;
+                // Upscale the raster screenshot with a good filter
+                ctx.source().set_filter(cairo::Filter::Good);
+                ctx.paint()
+                    .map_err(|e| anyhow!("Failed to paint surface: {}", e))?;
+                ctx.restore()?;
             }
-            rgba_data
-        };
 
-        info!(
-            "Creating image from converted data: {}x{}",
-            image_width, image_height
-        );
-        let img = image::RgbaImage::from_raw(image_width as u32, image_height as u32, image_data)
-            .ok_or_else(|| anyhow!("Failed to create image from converted data"))?;
+            tools.borrow().draw_all(&ctx);
+
+            ctx.identity_matrix();
+            if options.add_watermark {
+                watermark::draw(&ctx, width as f64, height as f64)?;
+            }
+            if let Some(line) = caption_line {
+                caption::draw(&ctx, width as f64, height as f64, line)?;
+            }
+        }
+
+        let image = Self::read_rgba(&mut surface)?;
+        Ok((image, surface))
+    }
+
+    fn render_png_static(
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
+        tools: &Rc<RefCell<AnnotationTools>>,
+        image_width: i32,
+        image_height: i32,
+    ) -> Result<Vec<u8>> {
+        let (image, surface) = Self::composite_to_rgba(
+            screenshot_surface.borrow().as_ref(),
+            full_resolution.borrow().as_ref(),
+            tools,
+            image_width,
+            image_height,
+            &ExportOptions::default(),
+        )?;
+        history::remember_image(surface);
+        let mut png_data = Vec::new();
+        image
+            .write_to(
+                &mut std::io::Cursor::new(&mut png_data),
+                image::ImageOutputFormat::Png,
+            )
+            .map_err(|e| anyhow!("Failed to encode PNG: {}", e))?;
+
+        Ok(png_data)
+    }
+
+    fn render_to_file_static<P: AsRef<Path>>(
+        path: P,
+        screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
+        tools: &Rc<RefCell<AnnotationTools>>,
+        image_width: i32,
+        image_height: i32,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        let path_ref = path.as_ref();
+        let screenshot = screenshot_surface.borrow();
+        if options.annotations_only {
+            // JPEG would flatten the transparent background to black
+            if !Self::supports_transparency(path_ref) {
+                return Err(anyhow!(
+                    "Annotations-only export needs a PNG, WebP or AVIF file"
+                ));
+            }
+        } else if screenshot.is_none() {
+            warn!("No screenshot surface available for saving");
+        }
+        let (img, surface) = Self::composite_to_rgba(
+            screenshot.as_ref(),
+            full_resolution.borrow().as_ref(),
+            tools,
+            image_width,
+            image_height,
+            options,
+        )?;
 
         info!("Saving image to file: {}", path_ref.display());
-        img.save(path_ref)
+        Self::encode_to_file(&img, path_ref)
             .map_err(|e| anyhow!("Failed to save image to {}: {}", path_ref.display(), e))?;
 
+        // A transparent overlay is no use as a ghost for the next capture
+        if !options.annotations_only {
+            history::remember_image(surface);
+        }
+
         info!("File saved successfully to: {}", path_ref.display());
         Ok(())
     }
 
+    fn supports_transparency(path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .map_or(true, |extension| {
+                extension.eq_ignore_ascii_case("png")
+                    || extension.eq_ignore_ascii_case("webp")
+                    || extension.eq_ignore_ascii_case("avif")
+            })
+    }
+
+    // Encode in one of the export formats with the configured PNG
+    // compression and JPEG/WebP quality. Saving, the save action and copying
+    // in another format all go through here.
+    pub fn encode_image(img: &image::RgbaImage, format: ExportFormat) -> Result<Vec<u8>> {
+        let general = &preferences::get().general;
+        let mut data = Vec::new();
+
+        match format {
+            ExportFormat::Png => {
+                let compression = match general.png_compression {
+                    PngCompression::Fast => CompressionType::Fast,
+                    PngCompression::Default => CompressionType::Default,
+                    PngCompression::Best => CompressionType::Best,
+                };
+                PngEncoder::new_with_quality(&mut data, compression, PngFilterType::Adaptive)
+                    .write_image(
+                        img.as_raw(),
+                        img.width(),
+                        img.height(),
+                        image::ColorType::Rgba8,
+                    )?;
+            }
+            ExportFormat::Jpeg => {
+                // JPEG has no alpha channel
+                let rgb = image::DynamicImage::ImageRgba8(img.clone()).to_rgb8();
+                JpegEncoder::new_with_quality(&mut data, general.jpeg_quality.clamp(1, 100))
+                    .encode_image(&rgb)?;
+            }
+            ExportFormat::Webp => {
+                // WebP keeps the alpha channel, so encode the RGBA data as is
+                let quality = general.jpeg_quality.clamp(1, 100) as f32;
+                let encoded = webp::Encoder::from_rgba(img.as_raw(), img.width(), img.height())
+                    .encode(quality);
+                data.extend_from_slice(&encoded);
+            }
+        }
+
+        Ok(data)
+    }
+
+    // Encode explicitly so the configured PNG compression and JPEG/WebP/AVIF
+    // quality apply
+    fn encode_to_file(img: &image::RgbaImage, path: &Path) -> Result<()> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        // No extension saves a PNG
+        let format = extension
+            .as_deref()
+            .map_or(Some(ExportFormat::Png), ExportFormat::from_extension);
+        if let Some(format) = format {
+            std::fs::write(path, Self::encode_image(img, format)?)?;
+            return Ok(());
+        }
+
+        match extension.as_deref() {
+            #[cfg(feature = "avif")]
+            Some("avif") => {
+                // AVIF keeps the alpha channel too. Speed 6 of 1-10 is far
+                // quicker than the slowest setting for little size difference
+                let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+                image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                    writer,
+                    6,
+                    preferences::get().general.jpeg_quality.clamp(1, 100),
+                )
+                .write_image(
+                    img.as_raw(),
+                    img.width(),
+                    img.height(),
+                    image::ColorType::Rgba8,
+                )?;
+            }
+            #[cfg(not(feature = "avif"))]
+            Some("avif") => {
+                return Err(anyhow!(
+                    "AVIF export is not available in this build of Flint (avif feature)"
+                ));
+            }
+            _ => img.save(path)?,
+        }
+
+        Ok(())
+    }
+
     fn copy_to_clipboard_static(
         screenshot_surface: &Rc<RefCell<Option<ImageSurface>>>,
+        full_resolution: &Rc<RefCell<Option<FullResolution>>>,
         tools: &Rc<RefCell<AnnotationTools>>,
         image_width: i32,
         image_height: i32,
     ) -> Result<()> {
-        // Create a surface for the final image
-        let mut surface = ImageSurface::create(Format::ARgb32, image_width, image_height)?;
+        let (image, surface) = Self::composite_to_rgba(
+            screenshot_surface.borrow().as_ref(),
+            full_resolution.borrow().as_ref(),
+            tools,
+            image_width,
+            image_height,
+            &ExportOptions {
+                add_watermark: preferences::get().watermark.on_copy && watermark::is_configured(),
+                ..ExportOptions::default()
+            },
+        )?;
+
+        // Other formats only where the clipboard can take them; PNG otherwise
+        let copy_format = preferences::get().general.copy_format;
+        let copied_encoded = copy_format != ExportFormat::Png
+            && Self::encode_image(&image, copy_format)
+                .and_then(|data| clipboard::copy_encoded_image(&data, copy_format.mime_type()))
+                .map_err(|e| warn!("{}, copying as PNG instead", e))
+                .is_ok();
+        if !copied_encoded {
+            clipboard::copy_image(image.width(), image.height(), image.as_raw())?;
+        }
+        if let Err(e) = clipboard::remember_image(image.width(), image.height(), image.as_raw()) {
+            warn!("Failed to add copy to clipboard history: {}", e);
+        }
 
-        let ctx = Context::new(&surface)?;
+        history::remember_image(surface);
+        Ok(())
+    }
+}
 
-        // Draw screenshot
-        if let Some(ref screenshot) = *screenshot_surface.borrow() {
-            ctx.set_source_surface(screenshot, 0.0, 0.0)?;
-            ctx.paint()?;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::DrawingStroke;
 
-        // Draw annotations
-        tools.borrow().draw_all(&ctx);
+    // An image with a different value in every channel of every pixel
+    fn synthetic_rgba(width: u32, height: u32) -> image::RgbaImage {
+        image::RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([x as u8, y as u8, (x + y) as u8, (x * 7 + y * 3) as u8])
+        })
+    }
 
-        // Finish all drawing operations
-        drop(ctx);
+    // The per-pixel conversion surface_from_rgba used before it went row by row
+    fn per_pixel_bgra(rgba_image: &image::RgbaImage, stride: i32) -> Vec<u8> {
+        let (width, height) = rgba_image.dimensions();
+        let mut surface_data = vec![0u8; (stride * height as i32) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let src_pixel = rgba_image.get_pixel(x, y);
+                let dst_idx = (y as i32 * stride + x as i32 * 4) as usize;
+                surface_data[dst_idx] = src_pixel[2];
+                surface_data[dst_idx + 1] = src_pixel[1];
+                surface_data[dst_idx + 2] = src_pixel[0];
+                surface_data[dst_idx + 3] = src_pixel[3];
+            }
+        }
+        surface_data
+    }
 
-        // Convert surface to PNG image data
-        let image_data = {
-            surface.flush();
-            let stride = surface.stride();
-            let width = surface.width();
-            let height = surface.height();
+    #[test]
+    fn surface_bytes_match_the_per_pixel_conversion() {
+        // An odd width leaves stride padding at the end of every row
+        let rgba_image = synthetic_rgba(37, 23);
+        let mut surface = AnnotationEditor::surface_from_rgba(&rgba_image).unwrap();
+        let stride = surface.stride();
 
-            let mut rgba_data = Vec::new();
+        let expected = per_pixel_bgra(&rgba_image, stride);
+        assert_eq!(&surface.data().unwrap()[..], &expected[..]);
+    }
 
-            unsafe {
-                let data_ptr = surface.data().unwrap().as_ptr();
-                for y in 0..height {
-                    for x in 0..width {
-                        let pixel_offset = (y * stride + x * 4) as isize;
-                        let pixel_ptr = data_ptr.offset(pixel_offset);
+    // The dot grid as draw_base_layer painted it before it was tiled
+    fn per_dot_texture(ctx: &Context, width: i32, height: i32) {
+        ctx.save().unwrap();
+        ctx.set_source_rgba(1.0, 1.0, 1.0, 0.01);
+        for x in (0..width).step_by(20) {
+            for y in (0..height).step_by(20) {
+                ctx.arc(x as f64, y as f64, 0.5, 0.0, 2.0 * std::f64::consts::PI);
+                ctx.fill().unwrap();
+            }
+        }
+        ctx.restore().unwrap();
+    }
 
-                        let b = *pixel_ptr;
-                        let g = *pixel_ptr.offset(1);
-                        let r = *pixel_ptr.offset(2);
-                        let a = *pixel_ptr.offset(3);
+    // Prints what the background texture costs each way rather than
+    // asserting it, as timings vary between machines. Run with
+    // cargo test --release texture_pattern_timing -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn texture_pattern_timing() {
+        const RUNS: u32 = 30;
+
+        for (width, height) in [(1280, 720), (1920, 1080), (3840, 2160)] {
+            let surface = ImageSurface::create(Format::ARgb32, width, height).unwrap();
+            let ctx = Context::new(&surface).unwrap();
+
+            let started = std::time::Instant::now();
+            for _ in 0..RUNS {
+                per_dot_texture(&ctx, width, height);
+            }
+            let per_dot = started.elapsed() / RUNS;
 
-                        rgba_data.extend_from_slice(&[r, g, b, a]);
-                    }
-                }
+            let started = std::time::Instant::now();
+            for _ in 0..RUNS {
+                ctx.save().unwrap();
+                ctx.set_source(&AnnotationEditor::texture_pattern().unwrap())
+                    .unwrap();
+                ctx.paint().unwrap();
+                ctx.restore().unwrap();
             }
-            rgba_data
+            let tiled = started.elapsed() / RUNS;
+
+            println!(
+                "{}x{}: per-dot loop {:?}, tiled pattern {:?}",
+                width, height, per_dot, tiled
+            );
+        }
+    }
+
+    // A screenshot with one annotation on it, as the editor holds them
+    fn annotated_screenshot() -> (
+        Rc<RefCell<Option<ImageSurface>>>,
+        Rc<RefCell<Option<FullResolution>>>,
+        Rc<RefCell<AnnotationTools>>,
+    ) {
+        let screenshot = AnnotationEditor::surface_from_rgba(&synthetic_rgba(64, 48)).unwrap();
+        let mut stroke =
+            DrawingStroke::new(ToolType::Line, gdk4::RGBA::new(1.0, 0.0, 0.0, 1.0), 3.0);
+        stroke.add_point(Point::new(5.0, 5.0));
+        stroke.add_point(Point::new(50.0, 40.0));
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![stroke]);
+
+        (
+            Rc::new(RefCell::new(Some(screenshot))),
+            Rc::new(RefCell::new(None)),
+            Rc::new(RefCell::new(tools)),
+        )
+    }
+
+    #[test]
+    fn save_and_upload_paths_produce_identical_pixels() {
+        let (screenshot, full_resolution, tools) = annotated_screenshot();
+        let (composited, _) = AnnotationEditor::composite_to_rgba(
+            screenshot.borrow().as_ref(),
+            None,
+            &tools,
+            64,
+            48,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+
+        let png =
+            AnnotationEditor::render_png_static(&screenshot, &full_resolution, &tools, 64, 48)
+                .unwrap();
+        let uploaded = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        let path = std::env::temp_dir().join(format!("flint-test-save-{}.png", std::process::id()));
+        AnnotationEditor::render_to_file_static(
+            &path,
+            &screenshot,
+            &full_resolution,
+            &tools,
+            64,
+            48,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+        let saved = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(uploaded.as_raw(), composited.as_raw());
+        assert_eq!(saved.as_raw(), composited.as_raw());
+    }
+
+    #[test]
+    fn annotations_only_export_is_transparent_outside_strokes() {
+        let (screenshot, _, tools) = annotated_screenshot();
+        let (overlay, _) = AnnotationEditor::composite_to_rgba(
+            screenshot.borrow().as_ref(),
+            None,
+            &tools,
+            64,
+            48,
+            &ExportOptions {
+                annotations_only: true,
+                ..ExportOptions::default()
+            },
+        )
+        .unwrap();
+
+        // Away from the line from (5, 5) to (50, 40)
+        for (x, y) in [(0, 0), (63, 0), (0, 47), (63, 47), (40, 10)] {
+            assert_eq!(overlay.get_pixel(x, y)[3], 0, "({}, {}) is not clear", x, y);
+        }
+        // On it
+        assert_eq!(overlay.get_pixel(27, 22)[3], 255);
+    }
+
+    // A solid blue PNG, loaded the way a capture is, with a short red line
+    // through `at` in the editor's image coordinates. The line is entered as
+    // pointer positions on a 900x700 canvas, so it goes through the same
+    // widget-to-image transform as drawing does.
+    fn stroke_through(
+        working: &image::RgbaImage,
+        at: (f64, f64),
+    ) -> (ImageSurface, Rc<RefCell<AnnotationTools>>) {
+        let surface = AnnotationEditor::surface_from_rgba(working).unwrap();
+        let (scale, offset_x, offset_y) =
+            AnnotationEditor::image_transform(900, 700, surface.width(), surface.height());
+        let to_widget = |x: f64, y: f64| (offset_x + x * scale, offset_y + y * scale);
+
+        let mut stroke =
+            DrawingStroke::new(ToolType::Line, gdk4::RGBA::new(1.0, 0.0, 0.0, 1.0), 1.0);
+        for x in [at.0 - 3.0, at.0 + 3.0] {
+            let (widget_x, widget_y) = to_widget(x, at.1);
+            let (x, y) = AnnotationEditor::area_to_image(900, 700, &surface, widget_x, widget_y);
+            stroke.add_point(Point::new(x, y));
+        }
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![stroke]);
+        (surface, Rc::new(RefCell::new(tools)))
+    }
+
+    fn solid_blue(width: u32, height: u32) -> image::RgbaImage {
+        image::RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 255, 255]))
+    }
+
+    #[test]
+    fn stroke_lands_on_the_same_pixel_in_the_export() {
+        let (surface, tools) = stroke_through(&solid_blue(200, 100), (120.5, 40.5));
+        let (export, _) = AnnotationEditor::composite_to_rgba(
+            Some(&surface),
+            None,
+            &tools,
+            200,
+            100,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(export.dimensions(), (200, 100));
+        assert_eq!(export.get_pixel(120, 40).0, [255, 0, 0, 255]);
+        for (x, y) in [(120, 39), (120, 41), (110, 40), (130, 40)] {
+            assert_eq!(export.get_pixel(x, y).0, [0, 0, 255, 255], "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn stroke_on_a_downscaled_copy_lands_on_the_original_pixel() {
+        // The editor works on a half-size copy of a 200x100 capture
+        let mut png = Vec::new();
+        solid_blue(200, 100)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let full_resolution = FullResolution { png, scale: 0.5 };
+        let (surface, tools) = stroke_through(&solid_blue(100, 50), (60.25, 20.25));
+
+        let (export, _) = AnnotationEditor::composite_to_rgba(
+            Some(&surface),
+            Some(&full_resolution),
+            &tools,
+            100,
+            50,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(export.dimensions(), (200, 100));
+        assert_eq!(export.get_pixel(120, 40).0, [255, 0, 0, 255]);
+        for (x, y) in [(120, 38), (120, 42), (110, 40), (130, 40)] {
+            assert_eq!(export.get_pixel(x, y).0, [0, 0, 255, 255], "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn snapped_1px_rectangle_exports_single_pixel_edges() {
+        let mut stroke = DrawingStroke::new(
+            ToolType::Rectangle,
+            gdk4::RGBA::new(0.0, 0.0, 1.0, 1.0),
+            1.0,
+        );
+        stroke.pixel_snap = true;
+        stroke.add_point(Point::new(10.2, 10.7));
+        stroke.add_point(Point::new(40.4, 30.1));
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![stroke]);
+        let tools = Rc::new(RefCell::new(tools));
+
+        let (export, _) = AnnotationEditor::composite_to_rgba(
+            None,
+            None,
+            &tools,
+            64,
+            48,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+
+        // Edges snap to pixel columns 10 and 40 and rows 10 and 30
+        let on_edge = |x: u32, y: u32| {
+            ((x == 10 || x == 40) && (10..=30).contains(&y))
+                || ((y == 10 || y == 30) && (10..=40).contains(&x))
         };
+        for (x, y, pixel) in export.enumerate_pixels() {
+            let expected = if on_edge(x, y) { 255 } else { 0 };
+            assert_eq!(pixel[3], expected, "alpha at ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn png_export_decodes_to_the_same_pixels() {
+        let rgba_image = synthetic_rgba(37, 23);
+        let data = AnnotationEditor::encode_image(&rgba_image, ExportFormat::Png).unwrap();
+
+        let decoded = image::load_from_memory_with_format(&data, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(decoded, rgba_image);
+    }
 
-        // Copy to clipboard using arboard
-        let mut clipboard =
-            Clipboard::new().map_err(|e| anyhow!("Failed to access clipboard: {}", e))?;
+    #[test]
+    fn jpeg_export_decodes_to_the_same_size() {
+        let rgba_image = synthetic_rgba(37, 23);
+        let data = AnnotationEditor::encode_image(&rgba_image, ExportFormat::Jpeg).unwrap();
 
-        let img_data = arboard::ImageData {
-            width: image_width as usize,
-            height: image_height as usize,
-            bytes: std::borrow::Cow::Borrowed(&image_data),
+        let decoded = image::load_from_memory_with_format(&data, image::ImageFormat::Jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (37, 23));
+    }
+
+    #[test]
+    fn webp_export_decodes_to_the_same_size() {
+        let rgba_image = synthetic_rgba(37, 23);
+        let data = AnnotationEditor::encode_image(&rgba_image, ExportFormat::Webp).unwrap();
+
+        let decoded = webp::Decoder::new(&data).decode().unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (37, 23));
+    }
+
+    // image only decodes AVIF with the dav1d system library, so the size is
+    // read from the image spatial extents ('ispe') property instead
+    #[cfg(feature = "avif")]
+    #[test]
+    fn avif_export_has_the_original_size() {
+        let rgba_image = synthetic_rgba(37, 23);
+        let path = std::env::temp_dir().join(format!("flint-test-{}.avif", std::process::id()));
+        AnnotationEditor::encode_to_file(&rgba_image, &path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(&data[4..8], b"ftyp");
+        let ispe = data
+            .windows(4)
+            .position(|window| window == b"ispe")
+            .expect("no ispe property");
+        // Box type, then version and flags, then width and height
+        let field = |offset: usize| {
+            u32::from_be_bytes(data[ispe + offset..ispe + offset + 4].try_into().unwrap())
         };
+        assert_eq!((field(8), field(12)), (37, 23));
+    }
 
-        clipboard
-            .set_image(img_data)
-            .map_err(|e| anyhow!("Failed to set clipboard image: {}", e))?;
+    #[test]
+    fn saved_file_is_a_decodable_image() {
+        let rgba_image = synthetic_rgba(37, 23);
+        let path = std::env::temp_dir().join(format!("flint-test-{}.png", std::process::id()));
+        AnnotationEditor::encode_to_file(&rgba_image, &path).unwrap();
 
-        info!("Successfully copied image to clipboard using arboard");
-        Ok(())
+        let decoded = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(decoded, rgba_image);
     }
 }
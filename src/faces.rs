@@ -0,0 +1,40 @@
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+use log::info;
+
+// Find the faces in an image and return their rectangles as (x, y, width,
+// height) in image pixels. Detection runs the SeetaFace frontal model at
+// `model_path` through rustface, which is only built with the "faces" Cargo
+// feature.
+#[cfg(feature = "faces")]
+pub fn detect_faces(image: &RgbaImage, model_path: &str) -> Result<Vec<(i32, i32, u32, u32)>> {
+    let mut detector = rustface::create_detector(model_path)
+        .map_err(|e| anyhow!("Failed to load face model {}: {}", model_path, e))?;
+    detector.set_min_face_size(20);
+    detector.set_score_thresh(2.0);
+    detector.set_pyramid_scale_factor(0.8);
+    detector.set_slide_window_step(4, 4);
+
+    let gray = image::DynamicImage::ImageRgba8(image.clone()).to_luma8();
+    let (width, height) = gray.dimensions();
+    let mut data = rustface::ImageData::new(gray.as_raw(), width, height);
+    let faces: Vec<_> = detector
+        .detect(&mut data)
+        .iter()
+        .map(|face| {
+            let bbox = face.bbox();
+            (bbox.x(), bbox.y(), bbox.width(), bbox.height())
+        })
+        .collect();
+
+    info!("Detected {} face(s)", faces.len());
+    Ok(faces)
+}
+
+#[cfg(not(feature = "faces"))]
+pub fn detect_faces(_image: &RgbaImage, _model_path: &str) -> Result<Vec<(i32, i32, u32, u32)>> {
+    info!("Face detection requested without the faces feature");
+    Err(anyhow!(
+        "Face detection is not available in this build of Flint (faces feature)"
+    ))
+}
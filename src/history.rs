@@ -0,0 +1,62 @@
+use cairo::ImageSurface;
+use std::cell::RefCell;
+
+use crate::tools::DrawingStroke;
+
+// The last capture, kept so the next region selection can show it as a ghost
+// for lining up before/after shots. GTK runs on one thread, so thread-local
+// storage is enough.
+thread_local! {
+    static PREVIOUS_CAPTURE: RefCell<PreviousCapture> = RefCell::new(PreviousCapture::default());
+
+    // Annotations of the last editor that carried them over, with the image
+    // size they were drawn on. Kept apart from the capture, which every new
+    // full-screen capture clears.
+    static CARRIED_STROKES: RefCell<Option<((i32, i32), Vec<DrawingStroke>)>> =
+        RefCell::new(None);
+}
+
+#[derive(Clone, Default)]
+pub struct PreviousCapture {
+    // Latest composited image: the capture itself, then its annotated export
+    pub image: Option<ImageSurface>,
+
+    // Where the capture was selected, in overlay coordinates
+    pub region: Option<(f64, f64, f64, f64)>,
+}
+
+pub fn remember_region(x: f64, y: f64, width: f64, height: f64) {
+    PREVIOUS_CAPTURE.with(|previous| previous.borrow_mut().region = Some((x, y, width, height)));
+}
+
+pub fn remember_image(image: ImageSurface) {
+    PREVIOUS_CAPTURE.with(|previous| previous.borrow_mut().image = Some(image));
+}
+
+// Captures without a selected region have nothing to line up against
+pub fn clear() {
+    PREVIOUS_CAPTURE.with(|previous| *previous.borrow_mut() = PreviousCapture::default());
+}
+
+pub fn previous() -> PreviousCapture {
+    PREVIOUS_CAPTURE.with(|previous| previous.borrow().clone())
+}
+
+pub fn remember_strokes(width: i32, height: i32, strokes: Vec<DrawingStroke>) {
+    CARRIED_STROKES.with(|carried| *carried.borrow_mut() = Some(((width, height), strokes)));
+}
+
+pub fn forget_strokes() {
+    CARRIED_STROKES.with(|carried| *carried.borrow_mut() = None);
+}
+
+// The carried annotations, if they were drawn on an image of this size;
+// on any other size they would land in the wrong places
+pub fn carried_strokes(width: i32, height: i32) -> Option<Vec<DrawingStroke>> {
+    CARRIED_STROKES.with(|carried| match &*carried.borrow() {
+        Some((size, strokes)) if *size == (width, height) && !strokes.is_empty() => {
+            Some(strokes.clone())
+        }
+        _ => None,
+    })
+}
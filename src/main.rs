@@ -8,33 +8,156 @@ use gtk4::{
     Orientation, PolicyType, ScrolledWindow, SelectionMode,
 };
 use image::GenericImageView;
-use log::{error, info};
-use std::cell::RefCell;
+use log::{debug, error, info};
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::mpsc;
+use std::sync::{mpsc, OnceLock};
 use std::thread;
 
+mod caption;
 mod capture;
+mod cli;
+mod clipboard;
+mod contact_sheet;
 mod editor;
+mod faces;
+mod history;
+mod open_with;
+mod pin;
+mod preferences;
+mod project;
+mod recovery;
+mod stamps;
+mod style;
 mod tools;
 mod ui;
+mod upload;
+mod watermark;
 mod window_manager;
 
-use capture::ScreenshotCapture;
+use capture::{CaptureError, ScreenshotCapture};
+use cli::CliOptions;
 use editor::AnnotationEditor;
+use preferences::{AfterCapture, ExportFormat, RegionSelector};
+use window_manager::WindowError;
 
 const APP_ID: &str = "com.flint.Screenshot";
+const MM_PER_INCH: f64 = 25.4;
+
+// Fixed selection sizes offered on the region overlay
+const REGION_PRESETS: &[(i32, i32)] = &[
+    (1920, 1080),
+    (1600, 900),
+    (1280, 720),
+    (1024, 768),
+    (800, 600),
+    (640, 480),
+];
+
+// How often to check whether Flint's window has gone, and how long to leave
+// the compositor afterwards to repaint what was underneath it
+const HIDE_POLL_INTERVAL_MS: u64 = 10;
+const HIDE_SETTLE_MS: u64 = 50;
+
+// How long a released selection stays inverted before it is captured
+const SELECTION_FLASH_MS: u64 = 200;
+
+// Set from --action, which takes precedence over the after_capture preference
+static AFTER_CAPTURE_OVERRIDE: OnceLock<AfterCapture> = OnceLock::new();
+
+// Set while a CaptureClaim is held
+thread_local! {
+    static CAPTURE_BUSY: Cell<bool> = Cell::new(false);
+}
+
+// Held by a capture from the moment it is started, through any overlay or
+// dialog it shows, until the screenshot has been handed on, has failed or
+// was cancelled. Captures run one at a time, so a shortcut and a button
+// click close together can't open two overlays or editors. Dropping the
+// claim lets the next capture start.
+#[derive(Debug)]
+struct CaptureClaim(());
+
+impl CaptureClaim {
+    // None while another capture still holds it
+    fn take() -> Option<Self> {
+        if CAPTURE_BUSY.with(|busy| busy.replace(true)) {
+            info!("A capture is already running, ignoring the new request");
+            return None;
+        }
+        Some(Self(()))
+    }
+
+    // Keep the claim for as long as the window exists, e.g. a selection
+    // overlay that can be cancelled in several ways. Whatever carries the
+    // capture on takes the claim out of the slot before closing the window.
+    fn hold_until_destroyed(self, window: &ApplicationWindow) -> Rc<RefCell<Option<Self>>> {
+        let slot = Rc::new(RefCell::new(Some(self)));
+        let held = slot.clone();
+        window.connect_destroy(move |_| {
+            held.borrow_mut().take();
+        });
+        slot
+    }
+}
+
+impl Drop for CaptureClaim {
+    fn drop(&mut self) {
+        CAPTURE_BUSY.with(|busy| busy.set(false));
+    }
+}
 
 fn main() -> Result<()> {
     env_logger::init();
 
+    let options = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if options.show_help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+
+    if options.is_headless() {
+        std::process::exit(run_headless_capture(&options));
+    }
+
+    if let Some(action) = options.action {
+        let _ = AFTER_CAPTURE_OVERRIDE.set(action);
+    }
+
     let app = Application::builder().application_id(APP_ID).build();
 
-    app.connect_activate(build_capture_ui);
+    app.connect_startup(|_| style::load_fallback_css());
+
+    // A failed command line capture opens no window, so GTK itself would
+    // exit cleanly; its exit code is kept here instead
+    let cli_exit_code = Rc::new(Cell::new(0));
+    let cli_exit_code_activate = cli_exit_code.clone();
+    app.connect_activate(move |app| {
+        if options.has_capture() {
+            cli_exit_code_activate.set(run_cli_capture(app, &options));
+        } else {
+            build_capture_ui(app);
+        }
+    });
 
-    let exit_code = app.run();
+    // Our own flags were handled above, so don't let GTK parse them
+    let program = std::env::args()
+        .next()
+        .unwrap_or_else(|| "flint".to_string());
+    let exit_code: i32 = app.run_with_args(&[program]).into();
 
-    std::process::exit(exit_code.into());
+    std::process::exit(match cli_exit_code.get() {
+        0 => exit_code,
+        code => code,
+    });
 }
 
 fn build_capture_ui(app: &Application) {
@@ -62,7 +185,16 @@ fn build_capture_ui(app: &Application) {
 
     // Description label
     let desc_label = Label::new(Some("Capture and annotate screenshots"));
-    desc_label.set_margin_bottom(10);
+
+    // Which display server and capture backend are in use, so it is clear
+    // why some captures are unavailable
+    let backend_label = Label::new(Some(&format!(
+        "Backend: {}",
+        ScreenshotCapture::new().backend_name()
+    )));
+    backend_label.add_css_class("dim-label");
+    backend_label.add_css_class("caption");
+    backend_label.set_margin_bottom(10);
 
     // Capture buttons container
     let button_box = Box::new(Orientation::Vertical, 10);
@@ -79,6 +211,55 @@ fn build_capture_ui(app: &Application) {
     let window_button = Button::with_label("Window");
     window_button.set_size_request(200, 50);
 
+    // Capture whichever window is clicked next, without the list
+    let click_button = Button::with_label("Window Under Click");
+    click_button.set_size_request(200, 50);
+
+    // Capture the window that had the focus before Flint, without the list
+    let active_button = Button::with_label("Active Window");
+    active_button.set_size_request(200, 50);
+    active_button.set_tooltip_text(Some("Capture the window that was focused before Flint"));
+
+    // Capture every window of one application together
+    let application_button = Button::with_label("Application");
+    application_button.set_size_request(200, 50);
+
+    // Count down without taking focus, so menus and tooltips stay open
+    let delayed_button = Button::with_label("Delayed Screen");
+    delayed_button.set_size_request(200, 50);
+    delayed_button.set_tooltip_text(Some(
+        "Capture the screen after a countdown, leaving time to open a menu or tooltip",
+    ));
+    let delayed_rect_button = Button::with_label("Delayed Selection");
+    delayed_rect_button.set_size_request(200, 50);
+    delayed_rect_button.set_tooltip_text(Some(
+        "Freeze the screen after a countdown, then select a region of it",
+    ));
+
+    // Capture one region repeatedly into a grid of frames
+    let sheet_button = Button::with_label("Contact Sheet");
+    sheet_button.set_size_request(200, 50);
+
+    // Select a region and keep it on screen as a reference, without editing
+    let pin_button = Button::with_label("Pin Selection");
+    pin_button.set_size_request(200, 50);
+    pin_button.set_tooltip_text(Some(
+        "Select a region and pin it above other windows (P). Escape closes the pin.",
+    ));
+
+    // Window captures would only fail on click, so explain up front instead
+    if let Some(reason) = window_manager::window_capture_unavailable_reason() {
+        for button in [
+            &window_button,
+            &active_button,
+            &click_button,
+            &application_button,
+        ] {
+            button.set_sensitive(false);
+            button.set_tooltip_text(Some(reason));
+        }
+    }
+
     // Clone app for the callbacks
     let app_clone = app.clone();
     let window_clone = window.clone();
@@ -86,6 +267,20 @@ fn build_capture_ui(app: &Application) {
     let window_clone2 = window.clone();
     let app_clone3 = app.clone();
     let window_clone3 = window.clone();
+    let app_clone4 = app.clone();
+    let window_clone4 = window.clone();
+    let app_clone5 = app.clone();
+    let window_clone5 = window.clone();
+    let app_clone6 = app.clone();
+    let window_clone6 = window.clone();
+    let app_clone7 = app.clone();
+    let window_clone7 = window.clone();
+    let app_clone8 = app.clone();
+    let window_clone8 = window.clone();
+    let app_clone9 = app.clone();
+    let window_clone9 = window.clone();
+    let app_clone10 = app.clone();
+    let window_clone10 = window.clone();
 
     // Full screenshot button callback
     capture_button.connect_clicked(move |_| {
@@ -105,14 +300,75 @@ fn build_capture_ui(app: &Application) {
         start_window_selection_capture(app_clone3.clone(), window_clone3.clone());
     });
 
+    // Active window button callback
+    active_button.connect_clicked(move |_| {
+        info!("Active window button clicked");
+        start_active_window_capture(app_clone9.clone(), window_clone9.clone());
+    });
+
+    // Capture after click button callback
+    click_button.connect_clicked(move |_| {
+        info!("Capture after click button clicked");
+        start_click_window_capture(app_clone4.clone(), window_clone4.clone());
+    });
+
+    // Application button callback
+    application_button.connect_clicked(move |_| {
+        info!("Application capture button clicked");
+        start_application_capture(app_clone6.clone(), window_clone6.clone());
+    });
+
+    // Delayed capture button callbacks
+    delayed_button.connect_clicked(move |_| {
+        info!("Delayed screenshot button clicked");
+        start_delayed_capture(app_clone7.clone(), window_clone7.clone(), false);
+    });
+
+    delayed_rect_button.connect_clicked(move |_| {
+        info!("Delayed selection button clicked");
+        start_delayed_capture(app_clone8.clone(), window_clone8.clone(), true);
+    });
+
+    // Contact sheet button callback
+    sheet_button.connect_clicked(move |_| {
+        info!("Contact sheet button clicked");
+        if let Some(claim) = CaptureClaim::take() {
+            show_rectangle_selection(
+                app_clone5.clone(),
+                window_clone5.clone(),
+                RegionAction::ContactSheet,
+                None,
+                claim,
+            );
+        }
+    });
+
+    // Pin selection button callback
+    pin_button.connect_clicked(move |_| {
+        info!("Pin selection button clicked");
+        if let Some(claim) = CaptureClaim::take() {
+            show_rectangle_selection(
+                app_clone10.clone(),
+                window_clone10.clone(),
+                RegionAction::Pin,
+                None,
+                claim,
+            );
+        }
+    });
+
     // Keyboard shortcuts
     let key_controller = gtk4::EventControllerKey::new();
-    key_controller.connect_key_pressed(glib::clone!(@weak window => @default-return glib::Propagation::Proceed, move |_, key, _, _| {
+    key_controller.connect_key_pressed(glib::clone!(@weak window, @weak pin_button => @default-return glib::Propagation::Proceed, move |_, key, _, _| {
         match key {
             gdk4::Key::Escape => {
                 window.close();
                 glib::Propagation::Stop
             }
+            gdk4::Key::p | gdk4::Key::P => {
+                pin_button.emit_clicked();
+                glib::Propagation::Stop
+            }
             gdk4::Key::Return | gdk4::Key::space => {
                 // Trigger capture on Enter or Space
                 if let Some(capture_btn) = window.child()
@@ -145,10 +401,18 @@ fn build_capture_ui(app: &Application) {
     button_box.append(&capture_button);
     button_box.append(&rect_button);
     button_box.append(&window_button);
+    button_box.append(&active_button);
+    button_box.append(&click_button);
+    button_box.append(&application_button);
+    button_box.append(&delayed_button);
+    button_box.append(&delayed_rect_button);
+    button_box.append(&sheet_button);
+    button_box.append(&pin_button);
 
     // Add widgets to container
     main_box.append(&title_label);
     main_box.append(&desc_label);
+    main_box.append(&backend_label);
     main_box.append(&button_box);
 
     window.set_child(Some(&main_box));
@@ -156,32 +420,352 @@ fn build_capture_ui(app: &Application) {
     // Show the window
     window.present();
 
+    if recovery::pending().is_some() {
+        offer_capture_recovery(app, &window);
+    }
+
     info!("Capture interface ready");
 }
 
+// A capture was left behind by an editor that failed to open or went down;
+// offer to open it again before it is lost for good
+fn offer_capture_recovery(app: &Application, window: &ApplicationWindow) {
+    let dialog = gtk4::MessageDialog::builder()
+        .transient_for(window)
+        .modal(true)
+        .text("Recover Last Screenshot?")
+        .secondary_text(
+            "The editor closed unexpectedly before the last screenshot was saved. \
+             Open it in the editor again?",
+        )
+        .buttons(gtk4::ButtonsType::None)
+        .build();
+    dialog.add_buttons(&[
+        ("Discard", gtk4::ResponseType::Reject),
+        ("Recover", gtk4::ResponseType::Accept),
+    ]);
+    dialog.set_default_response(gtk4::ResponseType::Accept);
+
+    let app = app.clone();
+    let window = window.clone();
+    dialog.connect_response(move |dialog, response| {
+        dialog.close();
+        match response {
+            gtk4::ResponseType::Accept => {
+                info!("Recovering last capture");
+                let result = recovery::load().and_then(|png_data| {
+                    run_after_capture(&app, AfterCapture::Editor, png_data, None, None, None)
+                });
+                if let Err(e) = result {
+                    error!("Failed to recover last capture: {}", e);
+                    show_error_dialog(
+                        &window,
+                        &format!("Failed to recover the last screenshot: {}", e),
+                    );
+                }
+            }
+            gtk4::ResponseType::Reject => recovery::discard(),
+            // Dismissed without choosing: ask again next time
+            _ => {}
+        }
+    });
+
+    dialog.present();
+}
+
 fn start_screenshot_capture(app: Application, window: ApplicationWindow, is_rectangle: bool) {
+    let Some(claim) = CaptureClaim::take() else {
+        return;
+    };
+
     // Hide the capture window
     window.set_visible(false);
 
-    if is_rectangle {
+    let region_selector = preferences::get().capture.region_selector;
+    if is_rectangle && region_selector != RegionSelector::Flint {
+        // Let the compositor or slurp draw the selection
+        let hidden_window = window.clone();
+        when_window_hidden(&hidden_window, move || {
+            select_region_externally(app, window, region_selector, claim)
+        });
+    } else if is_rectangle {
         // Show rectangle selection overlay
-        show_rectangle_selection(app, window);
+        show_rectangle_selection(app, window, RegionAction::Capture, None, claim);
     } else {
-        // Proceed with full screenshot
-        proceed_with_screenshot(app, window, None);
+        // Proceed with full screenshot once the capture window is gone
+        let hidden_window = window.clone();
+        when_window_hidden(&hidden_window, move || {
+            proceed_with_screenshot(app, window, None, None, None, claim)
+        });
     }
 }
 
-fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow) {
+// Count down with a small indicator, then capture without ever focusing a
+// Flint window, so a menu or tooltip opened meanwhile stays on screen. The
+// indicator is hidden before the capture and nothing is presented until the
+// screen has been grabbed: a region selection freezes the screen first and
+// only then shows its overlay.
+fn start_delayed_capture(app: Application, window: ApplicationWindow, is_rectangle: bool) {
+    let Some(claim) = CaptureClaim::take() else {
+        return;
+    };
+
+    let delay = preferences::get().capture.capture_delay;
+    info!(
+        "Arming {} capture in {}s",
+        if is_rectangle {
+            "region"
+        } else {
+            "full screen"
+        },
+        delay
+    );
+    window.set_visible(false);
+
+    // Shown now, while Flint has focus anyway; never presented again
+    let countdown_window = ApplicationWindow::builder()
+        .application(&app)
+        .title("Flint Countdown")
+        .decorated(false)
+        .resizable(false)
+        .can_focus(false)
+        .build();
+    let countdown_label = Label::new(None);
+    countdown_label.add_css_class("title-1");
+    let hint_label = Label::new(Some("Open the menu or tooltip to capture now"));
+    hint_label.add_css_class("dim-label");
+
+    let countdown_box = Box::new(Orientation::Vertical, 6);
+    countdown_box.set_margin_start(20);
+    countdown_box.set_margin_end(20);
+    countdown_box.set_margin_top(12);
+    countdown_box.set_margin_bottom(12);
+    countdown_box.set_can_target(false);
+    countdown_box.append(&countdown_label);
+    countdown_box.append(&hint_label);
+    countdown_window.set_child(Some(&countdown_box));
+    countdown_window.present();
+
+    let remaining = Cell::new(delay);
+    countdown_label.set_text(&format!("Capturing in {}…", remaining.get()));
+    let mut claim = Some(claim);
+    glib::timeout_add_local(std::time::Duration::from_secs(1), move || {
+        remaining.set(remaining.get().saturating_sub(1));
+        if remaining.get() > 0 {
+            countdown_label.set_text(&format!("Capturing in {}…", remaining.get()));
+            return glib::ControlFlow::Continue;
+        }
+        let Some(claim) = claim.take() else {
+            return glib::ControlFlow::Break;
+        };
+
+        countdown_window.set_visible(false);
+        let app = app.clone();
+        let window = window.clone();
+        let hidden_countdown = countdown_window.clone();
+        when_window_hidden(&countdown_window, move || {
+            hidden_countdown.destroy();
+            // The compositor's portal selector would take focus before
+            // anything is captured, so the frozen overlay is always used
+            if is_rectangle {
+                show_rectangle_selection(app, window, RegionAction::Capture, None, claim);
+            } else {
+                proceed_with_screenshot(app, window, None, None, None, claim);
+            }
+        });
+        glib::ControlFlow::Break
+    });
+}
+
+// Hiding a window is asynchronous, and on slow compositors it can still be on
+// screen well after set_visible(false). Rather than sleeping for a fixed time,
+// poll until the window is unmapped (by GTK, and by the X server on X11), then
+// give the compositor a moment to repaint. Gives up after hide_timeout_ms.
+fn when_window_hidden<F: FnOnce() + 'static>(window: &ApplicationWindow, then: F) {
+    let window = window.clone();
+    let window_id = window_manager::x11_window_id(&window);
+    let window_manager = window_id.and_then(|_| window_manager::WindowManager::new().ok());
+    let timeout = std::time::Duration::from_millis(preferences::get().capture.hide_timeout_ms);
+    let started = std::time::Instant::now();
+    let mut then = Some(then);
+
+    glib::timeout_add_local(
+        std::time::Duration::from_millis(HIDE_POLL_INTERVAL_MS),
+        move || {
+            let server_mapped = match (window_id, &window_manager) {
+                (Some(window_id), Some(manager)) => {
+                    manager.is_window_mapped(window_id).unwrap_or(false)
+                }
+                _ => false,
+            };
+            let hidden = !window.is_mapped() && !server_mapped;
+
+            if !hidden && started.elapsed() < timeout {
+                return glib::ControlFlow::Continue;
+            }
+
+            if hidden {
+                info!("Window hidden after {:?}", started.elapsed());
+            } else {
+                log::warn!(
+                    "Window still mapped after {:?}, capturing anyway",
+                    started.elapsed()
+                );
+            }
+
+            if let Some(then) = then.take() {
+                glib::timeout_add_local_once(
+                    std::time::Duration::from_millis(HIDE_SETTLE_MS),
+                    then,
+                );
+            }
+            glib::ControlFlow::Break
+        },
+    );
+}
+
+// Region capture through the portal's interactive selector. The portal call
+// blocks until the user is done, so it runs on a worker thread. Anything but
+// a cancel falls back to Flint's own overlay.
+fn select_region_externally(
+    app: Application,
+    window: ApplicationWindow,
+    region_selector: RegionSelector,
+    claim: CaptureClaim,
+) {
+    history::clear();
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let capture = ScreenshotCapture::new();
+        let _ = sender.send(match region_selector {
+            RegionSelector::Slurp => capture.take_screenshot_slurp_blocking(),
+            _ => capture.take_screenshot_portal_interactive(),
+        });
+    });
+
+    let mut claim = Some(claim);
+    glib::timeout_add_local(
+        std::time::Duration::from_millis(100),
+        move || match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            Ok(Ok(png_data)) => {
+                dismiss_capture_window(&window);
+                // The selector's region is not known here
+                show_capture_feedback(&app, None);
+                if let Err(e) = handle_capture(&app, Some(&window), png_data, None) {
+                    error!("Failed to handle screenshot: {}", e);
+                    show_error_dialog(&window, &format!("Failed to handle screenshot: {}", e));
+                }
+                glib::ControlFlow::Break
+            }
+            Ok(Err(CaptureError::Cancelled)) => {
+                info!("{:?} region selection cancelled", region_selector);
+                window.set_visible(true);
+                glib::ControlFlow::Break
+            }
+            Ok(Err(e)) => {
+                log::warn!(
+                    "{:?} region selection failed: {}, using Flint's overlay",
+                    region_selector,
+                    e
+                );
+                if let Some(claim) = claim.take() {
+                    show_rectangle_selection(
+                        app.clone(),
+                        window.clone(),
+                        RegionAction::Capture,
+                        None,
+                        claim,
+                    );
+                }
+                glib::ControlFlow::Break
+            }
+            Err(mpsc::TryRecvError::Disconnected) => {
+                error!("{:?} capture thread failed", region_selector);
+                if let Some(claim) = claim.take() {
+                    show_rectangle_selection(
+                        app.clone(),
+                        window.clone(),
+                        RegionAction::Capture,
+                        None,
+                        claim,
+                    );
+                }
+                glib::ControlFlow::Break
+            }
+        },
+    );
+}
+
+// What a selected region is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionAction {
+    // Captured once and handed on like any other capture
+    Capture,
+    // The region every frame of a contact sheet is taken from
+    ContactSheet,
+    // Shown in a window that stays on top, without the editor
+    Pin,
+}
+
+// What to do with a region once its selection is confirmed
+#[derive(Debug, Clone)]
+struct SelectionOptions {
+    // Copy the region to the clipboard instead of opening the editor
+    copy_only: bool,
+    action: RegionAction,
+    // Whether the region is picked from an earlier capture, not the screen
+    recropping: bool,
+    // The overlay's capture claim, which a contact sheet carries on with
+    claim: Rc<RefCell<Option<CaptureClaim>>>,
+}
+
+// `action` decides what the selected region is used for. With a `source`,
+// the region is chosen from that earlier full capture rather than the screen
+// as it is now; the editor uses this to re-crop, and is the parent window
+// then.
+fn show_rectangle_selection(
+    app: Application,
+    parent_window: ApplicationWindow,
+    action: RegionAction,
+    source: Option<Rc<image::DynamicImage>>,
+    claim: CaptureClaim,
+) {
     // Hide parent window first and ensure it's completely hidden
     parent_window.set_visible(false);
 
-    // Additional delay to ensure the capture window is fully hidden before preview capture
-    glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+    // Only take the preview once the capture window is really off screen
+    let hidden_window = parent_window.clone();
+    when_window_hidden(&hidden_window, move || {
         // Now capture the actual current screen state for preview (without the capture UI)
         let screen_info = get_screen_info_without_capture();
-        let (preview_surface, original_png_data) =
-            capture_current_screen_for_preview_with_data(screen_info.0, screen_info.1);
+        let scale_factor = get_screen_scale_factor();
+        let recropping = source.is_some();
+        let (width, height) = (screen_info.0 * scale_factor, screen_info.1 * scale_factor);
+
+        // The overlay freezes the screen: it shows one snapshot and the
+        // selection is always cropped from that same snapshot, never from a
+        // fresh capture, so video or menus changing underneath meanwhile
+        // don't change what ends up captured
+        let snapshot = match source {
+            Some(image) => {
+                preview_surface_from_image(&image, width, height).map(|surface| (surface, image))
+            }
+            None => match capture_current_screen_for_preview_with_data(width, height) {
+                (surface, Some(image)) => Some((surface, image)),
+                (_, None) => None,
+            },
+        };
+        let Some((preview_surface, original_image)) = snapshot else {
+            error!("No screen snapshot to select a region from");
+            parent_window.set_visible(true);
+            show_error_dialog(
+                &parent_window,
+                "Failed to take the screen snapshot to select a region from. Please try again.",
+            );
+            return;
+        };
 
         // Create fullscreen overlay window for rectangle selection
         let overlay_window = ApplicationWindow::builder()
@@ -199,6 +783,9 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
 
         overlay_window.fullscreen();
 
+        // However the overlay goes away, the capture is over with it
+        let claim = claim.hold_until_destroyed(&overlay_window);
+
         let drawing_area = DrawingArea::new();
         drawing_area.set_hexpand(true);
         drawing_area.set_vexpand(true);
@@ -206,26 +793,69 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
         let selection_start = Rc::new(RefCell::new(None::<(f64, f64)>));
         let selection_end = Rc::new(RefCell::new(None::<(f64, f64)>));
         let is_selecting = Rc::new(RefCell::new(false));
+        let keyboard_cursor = Rc::new(RefCell::new(None::<(f64, f64)>));
+        let overlay_hint = Rc::new(RefCell::new(None::<String>));
+        // Size of the chosen preset; the selection then follows the pointer
+        let preset_size = Rc::new(RefCell::new(None::<(f64, f64)>));
+        let pointer_position = Rc::new(RefCell::new(None::<(f64, f64)>));
+        // Set while the released selection is flashed before capturing
+        let flashing = Rc::new(Cell::new(false));
 
         let selection_start_draw = selection_start.clone();
         let selection_end_draw = selection_end.clone();
+        let keyboard_cursor_draw = keyboard_cursor.clone();
+        let overlay_hint_draw = overlay_hint.clone();
+        let pointer_position_draw = pointer_position.clone();
+        let flashing_draw = flashing.clone();
+        let crisp_selection_border = preferences::get().capture.crisp_selection_border;
+        let high_contrast = preferences::get().capture.high_contrast_selection;
+        // Bright desktops get heavier dimming and a yellow selection
+        let (dim_alpha, accent) = if high_contrast {
+            (0.55, (1.0, 0.85, 0.0))
+        } else {
+            (0.2, (0.2, 0.6, 1.0))
+        };
+        let screen_dpi = if preferences::get().capture.show_physical_size {
+            get_screen_dpi()
+        } else {
+            None
+        };
+        let previous_capture = if preferences::get().capture.onion_skin {
+            history::previous()
+        } else {
+            history::PreviousCapture::default()
+        };
 
         drawing_area.set_draw_func(move |_, ctx, width, height| {
             // Draw the preview pattern as background
             ctx.save().unwrap();
             ctx.scale(
-                width as f64 / screen_info.0 as f64,
-                height as f64 / screen_info.1 as f64,
+                width as f64 / preview_surface.width() as f64,
+                height as f64 / preview_surface.height() as f64,
             );
             ctx.set_source_surface(&preview_surface, 0.0, 0.0).unwrap();
+            ctx.source().set_filter(cairo::Filter::Good);
             ctx.paint().unwrap();
             ctx.restore().unwrap();
 
-            // Add a subtle dark overlay to indicate selection mode
-            ctx.set_source_rgba(0.0, 0.0, 0.0, 0.2);
+            // Add a dark overlay to indicate selection mode
+            ctx.set_source_rgba(0.0, 0.0, 0.0, dim_alpha);
             ctx.rectangle(0.0, 0.0, width as f64, height as f64);
             ctx.fill().unwrap();
 
+            // Ghost of the previous capture where it was taken, display only
+            if let (Some(ref image), Some((x, y, w, h))) =
+                (&previous_capture.image, previous_capture.region)
+            {
+                ctx.save().unwrap();
+                ctx.translate(x, y);
+                ctx.scale(w / image.width() as f64, h / image.height() as f64);
+                ctx.set_source_surface(image, 0.0, 0.0).unwrap();
+                ctx.source().set_filter(cairo::Filter::Good);
+                ctx.paint_with_alpha(0.35).unwrap();
+                ctx.restore().unwrap();
+            }
+
             // Add subtle grid to help with positioning
             ctx.set_source_rgba(0.3, 0.3, 0.3, 0.3);
             ctx.set_line_width(1.0);
@@ -247,7 +877,7 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
             ctx.stroke().unwrap();
 
             // Draw instruction text with background for visibility
-            let instruction_text = "Current desktop view - Click and drag to select rectangle area • Press Escape to cancel";
+            let instruction_text = "Current desktop view - Click and drag, or use arrows and Space, to select rectangle area • Hold Ctrl to copy without editing • Press Escape to cancel";
             ctx.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
             ctx.set_font_size(16.0);
 
@@ -269,10 +899,9 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
             if let (Some(start), Some(end)) =
                 (*selection_start_draw.borrow(), *selection_end_draw.borrow())
             {
-                let x = start.0.min(end.0);
-                let y = start.1.min(end.1);
-                let w = (end.0 - start.0).abs();
-                let h = (end.1 - start.1).abs();
+                // Show exactly the integer rectangle that will be cropped
+                let (x, y, w, h) = selection_rect(start, end);
+                let (x, y, w, h) = (x as f64, y as f64, w as f64, h as f64);
 
                 // Clear the selected area to show a brighter preview
                 ctx.save().unwrap();
@@ -281,28 +910,67 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
 
                 // Redraw the preview pattern at full brightness for selected area
                 ctx.scale(
-                    width as f64 / screen_info.0 as f64,
-                    height as f64 / screen_info.1 as f64,
+                    width as f64 / preview_surface.width() as f64,
+                    height as f64 / preview_surface.height() as f64,
                 );
                 ctx.set_source_surface(&preview_surface, 0.0, 0.0).unwrap();
+                ctx.source().set_filter(cairo::Filter::Good);
                 ctx.paint().unwrap();
                 ctx.restore().unwrap();
 
-                // Draw thick selection border with animated effect
-                ctx.set_source_rgb(0.2, 0.6, 1.0); // Blue selection color
-                ctx.set_line_width(3.0);
-                ctx.rectangle(x, y, w, h);
-                ctx.stroke().unwrap();
+                if flashing_draw.get() {
+                    ctx.save().unwrap();
+                    ctx.set_operator(cairo::Operator::Difference);
+                    ctx.set_source_rgb(1.0, 1.0, 1.0);
+                    ctx.rectangle(x, y, w, h);
+                    ctx.fill().unwrap();
+                    ctx.restore().unwrap();
+                }
 
-                // Add inner white border for better visibility
-                ctx.set_source_rgb(1.0, 1.0, 1.0);
-                ctx.set_line_width(1.0);
-                ctx.rectangle(x + 1.5, y + 1.5, w - 3.0, h - 3.0);
-                ctx.stroke().unwrap();
+                if crisp_selection_border {
+                    // 1px hairline through the centers of the outermost
+                    // selected pixels, so every lit pixel is part of the crop
+                    ctx.save().unwrap();
+                    ctx.set_antialias(cairo::Antialias::None);
+                    ctx.set_line_width(1.0);
+                    if high_contrast {
+                        // Dark outline on the pixels just outside the crop
+                        ctx.set_source_rgb(0.0, 0.0, 0.0);
+                        ctx.rectangle(x - 0.5, y - 0.5, w + 1.0, h + 1.0);
+                        ctx.stroke().unwrap();
+                    }
+                    ctx.set_source_rgb(accent.0, accent.1, accent.2);
+                    ctx.rectangle(x + 0.5, y + 0.5, (w - 1.0).max(0.0), (h - 1.0).max(0.0));
+                    ctx.stroke().unwrap();
+                    ctx.restore().unwrap();
+                } else if high_contrast {
+                    // Thick bright border over a wider black outline
+                    ctx.set_source_rgb(0.0, 0.0, 0.0);
+                    ctx.set_line_width(7.0);
+                    ctx.rectangle(x, y, w, h);
+                    ctx.stroke().unwrap();
+
+                    ctx.set_source_rgb(accent.0, accent.1, accent.2);
+                    ctx.set_line_width(4.0);
+                    ctx.rectangle(x, y, w, h);
+                    ctx.stroke().unwrap();
+                } else {
+                    // Draw thick selection border with animated effect
+                    ctx.set_source_rgb(0.2, 0.6, 1.0); // Blue selection color
+                    ctx.set_line_width(3.0);
+                    ctx.rectangle(x, y, w, h);
+                    ctx.stroke().unwrap();
+
+                    // Add inner white border for better visibility
+                    ctx.set_source_rgb(1.0, 1.0, 1.0);
+                    ctx.set_line_width(1.0);
+                    ctx.rectangle(x + 1.5, y + 1.5, w - 3.0, h - 3.0);
+                    ctx.stroke().unwrap();
+                }
 
                 // Draw corner handles to indicate interactive selection
-                let handle_size = 8.0;
-                ctx.set_source_rgb(0.2, 0.6, 1.0);
+                let handle_size = if high_contrast { 12.0 } else { 8.0 };
+                ctx.set_source_rgb(accent.0, accent.1, accent.2);
                 // Top-left corner
                 ctx.rectangle(
                     x - handle_size / 2.0,
@@ -337,7 +1005,16 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
                 ctx.fill().unwrap();
 
                 // Draw dimension text with background
-                let text = format!("{}×{}", w as i32, h as i32);
+                let text = match screen_dpi {
+                    Some(dpi) => format!(
+                        "{}×{} px ({:.0}×{:.0} mm)",
+                        w as i32,
+                        h as i32,
+                        w / dpi * MM_PER_INCH,
+                        h / dpi * MM_PER_INCH
+                    ),
+                    None => format!("{}×{}", w as i32, h as i32),
+                };
                 ctx.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
                 ctx.set_font_size(16.0);
 
@@ -360,6 +1037,56 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
                 ctx.move_to(text_x, text_y);
                 ctx.show_text(&text).unwrap();
             }
+
+            // Brief hint explaining why a selection was not accepted
+            if let Some(ref hint) = *overlay_hint_draw.borrow() {
+                ctx.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+                ctx.set_font_size(20.0);
+                let text_extents = ctx.text_extents(hint).unwrap();
+                let hint_x = (width as f64 - text_extents.width()) / 2.0;
+                let hint_y = height as f64 / 2.0;
+
+                ctx.set_source_rgba(0.0, 0.0, 0.0, 0.8);
+                ctx.rectangle(
+                    hint_x - 12.0,
+                    hint_y - text_extents.height() - 10.0,
+                    text_extents.width() + 24.0,
+                    text_extents.height() + 20.0,
+                );
+                ctx.fill().unwrap();
+
+                ctx.set_source_rgb(1.0, 1.0, 1.0);
+                ctx.move_to(hint_x, hint_y);
+                ctx.show_text(hint).unwrap();
+            }
+
+            // Full-screen crosshair following the pointer
+            if high_contrast {
+                if let Some((pointer_x, pointer_y)) = *pointer_position_draw.borrow() {
+                    let (pointer_x, pointer_y) = (pointer_x.floor() + 0.5, pointer_y.floor() + 0.5);
+                    ctx.move_to(0.0, pointer_y);
+                    ctx.line_to(width as f64, pointer_y);
+                    ctx.move_to(pointer_x, 0.0);
+                    ctx.line_to(pointer_x, height as f64);
+                    ctx.set_source_rgb(0.0, 0.0, 0.0);
+                    ctx.set_line_width(3.0);
+                    ctx.stroke_preserve().unwrap();
+                    ctx.set_source_rgb(accent.0, accent.1, accent.2);
+                    ctx.set_line_width(1.0);
+                    ctx.stroke().unwrap();
+                }
+            }
+
+            // Crosshair for keyboard-driven selection
+            if let Some((cursor_x, cursor_y)) = *keyboard_cursor_draw.borrow() {
+                ctx.set_source_rgb(accent.0, accent.1, accent.2);
+                ctx.set_line_width(1.0);
+                ctx.move_to(cursor_x - 12.0, cursor_y + 0.5);
+                ctx.line_to(cursor_x + 12.0, cursor_y + 0.5);
+                ctx.move_to(cursor_x + 0.5, cursor_y - 12.0);
+                ctx.line_to(cursor_x + 0.5, cursor_y + 12.0);
+                ctx.stroke().unwrap();
+            }
         });
 
         // Mouse event handling
@@ -367,11 +1094,28 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
         let selection_start_click = selection_start.clone();
         let selection_end_click = selection_end.clone();
         let is_selecting_click = is_selecting.clone();
+        let preset_size_click = preset_size.clone();
+        let flashing_click = flashing.clone();
         let drawing_area_click = drawing_area.clone();
 
         gesture_click.connect_pressed(move |_, _, x, y| {
-            *selection_start_click.borrow_mut() = Some((x, y));
-            *selection_end_click.borrow_mut() = Some((x, y));
+            // The flashed selection is already on its way to being captured
+            if flashing_click.get() {
+                return;
+            }
+            let (start, end) = match *preset_size_click.borrow() {
+                Some(size) => preset_selection(
+                    (x, y),
+                    size,
+                    (
+                        drawing_area_click.width() as f64,
+                        drawing_area_click.height() as f64,
+                    ),
+                ),
+                None => ((x, y), (x, y)),
+            };
+            *selection_start_click.borrow_mut() = Some(start);
+            *selection_end_click.borrow_mut() = Some(end);
             *is_selecting_click.borrow_mut() = true;
             drawing_area_click.queue_draw();
         });
@@ -381,12 +1125,25 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
         let is_selecting_release = is_selecting.clone();
         let overlay_window_release = overlay_window.clone();
         let app_release = app.clone();
+        let claim_release = claim.clone();
         let parent_window_release = parent_window.clone();
-        let original_png_data_release = original_png_data.clone();
-
-        gesture_click.connect_released(move |_, _, x, y| {
+        let original_image_release = original_image.clone();
+        let drawing_area_release = drawing_area.clone();
+        let overlay_hint_release = overlay_hint.clone();
+        let preset_size_release = preset_size.clone();
+        let flashing_release = flashing.clone();
+        let flash_selection = preferences::get().capture.flash_selection;
+
+        gesture_click.connect_released(move |gesture, _, x, y| {
             if *is_selecting_release.borrow() {
-                *selection_end_release.borrow_mut() = Some((x, y));
+                // Releasing with Ctrl held copies the region instead of editing
+                let copy_only = gesture
+                    .current_event_state()
+                    .contains(gdk4::ModifierType::CONTROL_MASK);
+                // A preset selection has already been placed by the pointer
+                if preset_size_release.borrow().is_none() {
+                    *selection_end_release.borrow_mut() = Some((x, y));
+                }
                 *is_selecting_release.borrow_mut() = false;
 
                 // Get selection bounds
@@ -394,49 +1151,67 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
                     *selection_start_release.borrow(),
                     *selection_end_release.borrow(),
                 ) {
-                    let x = start.0.min(end.0) as i32;
-                    let y = start.1.min(end.1) as i32;
-                    let w = (end.0 - start.0).abs() as i32;
-                    let h = (end.1 - start.1).abs() as i32;
-
-                    if w > 10 && h > 10 {
-                        // Minimum size check
-                        overlay_window_release.close();
-
-                        // Use the stored PNG data and crop it directly
-                        if let Some(ref png_data) = original_png_data_release {
-                            match crop_png_data_direct(png_data, x, y, w, h) {
-                                Ok(cropped_png) => {
-                                    proceed_with_cropped_screenshot(
-                                        app_release.clone(),
-                                        parent_window_release.clone(),
-                                        cropped_png,
-                                    );
-                                }
-                                Err(e) => {
-                                    error!("Failed to crop PNG data: {}", e);
-                                    // Fallback to taking a new screenshot
-                                    let rect = Some((x, y, w, h));
-                                    proceed_with_screenshot(
-                                        app_release.clone(),
-                                        parent_window_release.clone(),
-                                        rect,
-                                    );
+                    let (_, _, w, h) = selection_rect(start, end);
+                    let min_size = preferences::get().capture.min_selection_size as i32;
+                    if flash_selection && w >= min_size && h >= min_size {
+                        flashing_release.set(true);
+                        drawing_area_release.queue_draw();
+
+                        let app = app_release.clone();
+                        let parent_window = parent_window_release.clone();
+                        let overlay_window = overlay_window_release.clone();
+                        let original_image = original_image_release.clone();
+                        let flashing = flashing_release.clone();
+                        let claim = claim_release.clone();
+                        glib::timeout_add_local_once(
+                            std::time::Duration::from_millis(SELECTION_FLASH_MS),
+                            move || {
+                                flashing.set(false);
+                                // Escape during the flash cancels the capture
+                                if !overlay_window.is_visible() {
+                                    return;
                                 }
-                            }
-                        } else {
-                            error!("No PNG data available for cropping, falling back to new screenshot");
-                            // Fallback to taking a new screenshot
-                            let rect = Some((x, y, w, h));
-                            proceed_with_screenshot(
-                                app_release.clone(),
-                                parent_window_release.clone(),
-                                rect,
-                            );
-                        }
-                    } else {
-                        overlay_window_release.close();
-                        parent_window_release.set_visible(true);
+                                confirm_rectangle_selection(
+                                    &app,
+                                    &parent_window,
+                                    &overlay_window,
+                                    &original_image,
+                                    start,
+                                    end,
+                                    SelectionOptions {
+                                        copy_only,
+                                        action,
+                                        recropping,
+                                        claim,
+                                    },
+                                );
+                            },
+                        );
+                        return;
+                    }
+
+                    let accepted = confirm_rectangle_selection(
+                        &app_release,
+                        &parent_window_release,
+                        &overlay_window_release,
+                        &original_image_release,
+                        start,
+                        end,
+                        SelectionOptions {
+                            copy_only,
+                            action,
+                            recropping,
+                            claim: claim_release.clone(),
+                        },
+                    );
+                    if !accepted {
+                        *selection_start_release.borrow_mut() = None;
+                        *selection_end_release.borrow_mut() = None;
+                        show_overlay_hint(
+                            &drawing_area_release,
+                            &overlay_hint_release,
+                            "Selection too small",
+                        );
                     }
                 }
             }
@@ -444,30 +1219,182 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
 
         // Mouse motion for live selection
         let motion_controller = gtk4::EventControllerMotion::new();
+        let selection_start_motion = selection_start.clone();
         let selection_end_motion = selection_end.clone();
         let is_selecting_motion = is_selecting.clone();
+        let preset_size_motion = preset_size.clone();
+        let pointer_position_motion = pointer_position.clone();
         let drawing_area_motion = drawing_area.clone();
 
+        let high_contrast = preferences::get().capture.high_contrast_selection;
+
         motion_controller.connect_motion(move |_, x, y| {
-            if *is_selecting_motion.borrow() {
+            *pointer_position_motion.borrow_mut() = Some((x, y));
+            // The high-contrast crosshair tracks every movement
+            if high_contrast {
+                drawing_area_motion.queue_draw();
+            }
+
+            // A preset selection is carried around under the pointer
+            if let Some(size) = *preset_size_motion.borrow() {
+                let (start, end) = preset_selection(
+                    (x, y),
+                    size,
+                    (
+                        drawing_area_motion.width() as f64,
+                        drawing_area_motion.height() as f64,
+                    ),
+                );
+                *selection_start_motion.borrow_mut() = Some(start);
+                *selection_end_motion.borrow_mut() = Some(end);
+                drawing_area_motion.queue_draw();
+            } else if *is_selecting_motion.borrow() {
                 *selection_end_motion.borrow_mut() = Some((x, y));
                 drawing_area_motion.queue_draw();
             }
         });
 
-        // Keyboard handling (Escape to cancel)
+        // Keyboard handling: arrows move the cursor, Space sets the first
+        // corner and then confirms, Escape cancels
         let key_controller = gtk4::EventControllerKey::new();
         let overlay_window_key = overlay_window.clone();
         let parent_window_key = parent_window.clone();
-
-        key_controller.connect_key_pressed(move |_, key, _, _| {
-            if key == gdk4::Key::Escape {
-                overlay_window_key.close();
-                parent_window_key.set_visible(true);
-                glib::Propagation::Stop
+        let app_key = app.clone();
+        let original_image_key = original_image.clone();
+        let claim_key = claim.clone();
+        let selection_start_key = selection_start.clone();
+        let selection_end_key = selection_end.clone();
+        let is_selecting_key = is_selecting.clone();
+        let keyboard_cursor_key = keyboard_cursor.clone();
+        let overlay_hint_key = overlay_hint.clone();
+        let preset_size_key = preset_size.clone();
+        let pointer_position_key = pointer_position.clone();
+        let drawing_area_key = drawing_area.clone();
+
+        key_controller.connect_key_pressed(move |_, key, _, modifiers| {
+            let width = drawing_area_key.width() as f64;
+            let height = drawing_area_key.height() as f64;
+
+            // Shift moves by single pixels for fine adjustment
+            let step = if modifiers.contains(gdk4::ModifierType::SHIFT_MASK) {
+                1.0
             } else {
-                glib::Propagation::Proceed
+                10.0
+            };
+
+            let (dx, dy) = match key {
+                gdk4::Key::Escape => {
+                    overlay_window_key.close();
+                    parent_window_key.set_visible(true);
+                    return glib::Propagation::Stop;
+                }
+                gdk4::Key::Left => (-step, 0.0),
+                gdk4::Key::Right => (step, 0.0),
+                gdk4::Key::Up => (0.0, -step),
+                gdk4::Key::Down => (0.0, step),
+                gdk4::Key::space => (0.0, 0.0),
+                _ => return glib::Propagation::Proceed,
+            };
+
+            // A preset selection moves on from where it is shown, which the
+            // pointer may have carried it to. Otherwise the keyboard cursor
+            // starts at the pointer, or in the middle of the screen.
+            let preset = *preset_size_key.borrow();
+            let preset_center = preset.and_then(|_| {
+                let start = (*selection_start_key.borrow())?;
+                let end = (*selection_end_key.borrow())?;
+                Some(((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0))
+            });
+            let cursor = preset_center
+                .or(*keyboard_cursor_key.borrow())
+                .or(*pointer_position_key.borrow())
+                .unwrap_or((width / 2.0, height / 2.0));
+            let cursor = (
+                (cursor.0 + dx).clamp(0.0, width),
+                (cursor.1 + dy).clamp(0.0, height),
+            );
+            *keyboard_cursor_key.borrow_mut() = Some(cursor);
+
+            // With a preset the arrows move the whole selection and Space
+            // confirms it where it is
+            if let Some(size) = preset {
+                let (start, end) = preset_selection(cursor, size, (width, height));
+                *selection_start_key.borrow_mut() = Some(start);
+                *selection_end_key.borrow_mut() = Some(end);
+
+                if key == gdk4::Key::space {
+                    *is_selecting_key.borrow_mut() = false;
+                    let accepted = confirm_rectangle_selection(
+                        &app_key,
+                        &parent_window_key,
+                        &overlay_window_key,
+                        &original_image_key,
+                        start,
+                        end,
+                        SelectionOptions {
+                            copy_only: modifiers.contains(gdk4::ModifierType::CONTROL_MASK),
+                            action,
+                            recropping,
+                            claim: claim_key.clone(),
+                        },
+                    );
+                    if !accepted {
+                        show_overlay_hint(
+                            &drawing_area_key,
+                            &overlay_hint_key,
+                            "Selection too small",
+                        );
+                    }
+                    return glib::Propagation::Stop;
+                }
+
+                drawing_area_key.queue_draw();
+                return glib::Propagation::Stop;
+            }
+
+            let is_selecting = *is_selecting_key.borrow();
+            if key == gdk4::Key::space {
+                if is_selecting {
+                    *is_selecting_key.borrow_mut() = false;
+                    let start = *selection_start_key.borrow();
+                    if let Some(start) = start {
+                        let accepted = confirm_rectangle_selection(
+                            &app_key,
+                            &parent_window_key,
+                            &overlay_window_key,
+                            &original_image_key,
+                            start,
+                            cursor,
+                            SelectionOptions {
+                                copy_only: modifiers.contains(gdk4::ModifierType::CONTROL_MASK),
+                                action,
+                                recropping,
+                                claim: claim_key.clone(),
+                            },
+                        );
+                        if !accepted {
+                            *selection_start_key.borrow_mut() = None;
+                            *selection_end_key.borrow_mut() = None;
+                            show_overlay_hint(
+                                &drawing_area_key,
+                                &overlay_hint_key,
+                                "Selection too small",
+                            );
+                        }
+                    }
+                    return glib::Propagation::Stop;
+                }
+
+                *selection_start_key.borrow_mut() = Some(cursor);
+                *is_selecting_key.borrow_mut() = true;
+            }
+
+            if *is_selecting_key.borrow() {
+                *selection_end_key.borrow_mut() = Some(cursor);
             }
+
+            drawing_area_key.queue_draw();
+            glib::Propagation::Stop
         });
 
         drawing_area.add_controller(gesture_click);
@@ -475,77 +1402,651 @@ fn show_rectangle_selection(app: Application, parent_window: ApplicationWindow)
         drawing_area.add_controller(key_controller);
         drawing_area.set_can_focus(true);
 
-        overlay_window.set_child(Some(&drawing_area));
+        // Choosing a preset drops a selection of that size under the pointer
+        let selection_start_preset = selection_start.clone();
+        let selection_end_preset = selection_end.clone();
+        let is_selecting_preset = is_selecting.clone();
+        let drawing_area_preset = drawing_area.clone();
+        let preset_picker = create_region_preset_picker(preset_size.clone(), move |size| {
+            *is_selecting_preset.borrow_mut() = false;
+            let placed = size.map(|size| {
+                let bounds = (
+                    drawing_area_preset.width() as f64,
+                    drawing_area_preset.height() as f64,
+                );
+                let center = pointer_position
+                    .borrow()
+                    .unwrap_or((bounds.0 / 2.0, bounds.1 / 2.0));
+                preset_selection(center, size, bounds)
+            });
+            *selection_start_preset.borrow_mut() = placed.map(|(start, _)| start);
+            *selection_end_preset.borrow_mut() = placed.map(|(_, end)| end);
+            drawing_area_preset.queue_draw();
+            drawing_area_preset.grab_focus();
+        });
+
+        let overlay = gtk4::Overlay::new();
+        overlay.set_child(Some(&drawing_area));
+        overlay.add_overlay(&preset_picker);
+        overlay_window.set_child(Some(&overlay));
 
         overlay_window.present();
         gtk4::prelude::GtkWindowExt::set_focus(&overlay_window, Some(&drawing_area));
-
-        glib::ControlFlow::Break
     });
 }
 
-fn proceed_with_screenshot(
-    app: Application,
-    window: ApplicationWindow,
-    rect: Option<(i32, i32, i32, i32)>,
-) {
-    // Create a channel for communication between threads
-    let (sender, receiver) = mpsc::channel();
+// Dropdown of fixed selection sizes, plus width and height fields for a
+// custom size. `on_changed` gets the new size, or None for a free selection.
+fn create_region_preset_picker(
+    preset_size: Rc<RefCell<Option<(f64, f64)>>>,
+    on_changed: impl Fn(Option<(f64, f64)>) + 'static,
+) -> Box {
+    let combo = gtk4::ComboBoxText::new();
+    combo.append(Some("free"), "Free selection");
+    for &(width, height) in REGION_PRESETS {
+        combo.append(
+            Some(&format!("{}x{}", width, height)),
+            &format!("{}×{}", width, height),
+        );
+    }
+    combo.append(Some("custom"), "Custom size");
+    combo.set_active_id(Some("free"));
+
+    let width_field = gtk4::SpinButton::with_range(1.0, 16384.0, 1.0);
+    width_field.set_value(1024.0);
+    let height_field = gtk4::SpinButton::with_range(1.0, 16384.0, 1.0);
+    height_field.set_value(768.0);
+
+    let custom_fields = Box::new(Orientation::Horizontal, 4);
+    custom_fields.append(&width_field);
+    custom_fields.append(&Label::new(Some("×")));
+    custom_fields.append(&height_field);
+    custom_fields.set_visible(false);
+
+    let picker = Box::new(Orientation::Horizontal, 6);
+    picker.set_halign(gtk4::Align::End);
+    picker.set_valign(gtk4::Align::Start);
+    picker.set_margin_top(10);
+    picker.set_margin_end(10);
+    picker.add_css_class("osd");
+    picker.append(&combo);
+    picker.append(&custom_fields);
+
+    let update: Rc<dyn Fn()> = {
+        let combo = combo.clone();
+        let width_field = width_field.clone();
+        let height_field = height_field.clone();
+        let custom_fields = custom_fields.clone();
+        Rc::new(move || {
+            let size = match combo.active_id().as_deref() {
+                Some("custom") => Some((width_field.value(), height_field.value())),
+                Some(id) => id
+                    .split_once('x')
+                    .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?))),
+                None => None,
+            };
+            custom_fields.set_visible(combo.active_id().as_deref() == Some("custom"));
+            *preset_size.borrow_mut() = size;
+            on_changed(size);
+        })
+    };
 
-    // Spawn a thread for screenshot capture
-    thread::spawn(move || {
-        info!("Screenshot capture thread started");
+    let update_combo = update.clone();
+    combo.connect_changed(move |_| update_combo());
+    for field in [&width_field, &height_field] {
+        let combo = combo.clone();
+        let update = update.clone();
+        field.connect_value_changed(move |_| {
+            if combo.active_id().as_deref() == Some("custom") {
+                update();
+            }
+        });
+    }
 
-        // Add delay to ensure window is hidden
-        thread::sleep(std::time::Duration::from_millis(500));
-        info!("Starting screenshot capture after delay");
+    picker
+}
 
-        let result = take_screenshot_sync(rect);
-        match &result {
-            Ok(_) => info!("Screenshot capture completed successfully"),
-            Err(e) => error!("Screenshot capture failed: {}", e),
-        }
+// A fixed-size selection centered on a point and kept inside the overlay.
+// Presets larger than the overlay shrink to fit it.
+fn preset_selection(
+    center: (f64, f64),
+    size: (f64, f64),
+    bounds: (f64, f64),
+) -> ((f64, f64), (f64, f64)) {
+    let width = size.0.min(bounds.0);
+    let height = size.1.min(bounds.1);
+    let left = (center.0 - width / 2.0)
+        .round()
+        .clamp(0.0, bounds.0 - width);
+    let top = (center.1 - height / 2.0)
+        .round()
+        .clamp(0.0, bounds.1 - height);
+    ((left, top), (left + width, top + height))
+}
 
-        if let Err(e) = sender.send(result) {
-            error!("Failed to send screenshot result: {}", e);
+// Show a message on the selection overlay for a moment
+fn show_overlay_hint(drawing_area: &DrawingArea, hint: &Rc<RefCell<Option<String>>>, text: &str) {
+    *hint.borrow_mut() = Some(text.to_string());
+    drawing_area.queue_draw();
+
+    let hint = hint.clone();
+    let drawing_area = drawing_area.clone();
+    let text = text.to_string();
+    glib::timeout_add_local_once(std::time::Duration::from_millis(1500), move || {
+        // Leave a newer hint in place
+        if hint.borrow().as_deref() == Some(text.as_str()) {
+            *hint.borrow_mut() = None;
+            drawing_area.queue_draw();
         }
     });
+}
 
-    // Use glib timeout to check for completion
-    glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
-        match receiver.try_recv() {
-            Ok(result) => {
-                match result {
-                    Ok(image_data) => {
-                        info!(
-                            "Screenshot captured successfully ({} bytes), opening editor",
-                            image_data.len()
-                        );
+// Integer rectangle covered by a selection between two pointer positions.
+// Corners fall on pixel boundaries: a corner at (10.7, 4.2) lies on the edge
+// between pixels 9|10 and 3|4, so the rectangle covers pixels [x, x + w) and
+// [y, y + h). The overlay draws and the crop uses this same rectangle.
+fn selection_rect(start: (f64, f64), end: (f64, f64)) -> (i32, i32, i32, i32) {
+    let left = start.0.min(end.0).floor();
+    let top = start.1.min(end.1).floor();
+    let right = start.0.max(end.0).floor();
+    let bottom = start.1.max(end.1).floor();
+
+    (
+        left as i32,
+        top as i32,
+        (right - left) as i32,
+        (bottom - top) as i32,
+    )
+}
 
-                        // Close the capture window
-                        window.close();
+// Map a selection from overlay pixels onto the snapshot shown stretched
+// across the overlay. Both edges of each axis are scaled, so with a whole
+// scale factor every overlay pixel covers exactly scale x scale image pixels.
+fn selection_in_image(
+    (x, y, w, h): (i32, i32, i32, i32),
+    (overlay_width, overlay_height): (i32, i32),
+    (image_width, image_height): (i32, i32),
+) -> (i32, i32, i32, i32) {
+    let scale_x = image_width as f64 / overlay_width.max(1) as f64;
+    let scale_y = image_height as f64 / overlay_height.max(1) as f64;
+    let left = (x as f64 * scale_x).round() as i32;
+    let top = (y as f64 * scale_y).round() as i32;
+    let right = ((x + w) as f64 * scale_x).round() as i32;
+    let bottom = ((y + h) as f64 * scale_y).round() as i32;
+
+    (left, top, right - left, bottom - top)
+}
 
-                        // Create and show the annotation editor
-                        match AnnotationEditor::new(&app, image_data) {
-                            Ok(editor) => {
-                                info!("Editor created successfully");
-                                editor.show();
-                            }
-                            Err(e) => {
-                                error!("Failed to create editor: {}", e);
-                                show_error_dialog(
-                                    &window,
-                                    &format!("Failed to open editor: {}", e),
-                                );
-                            }
-                        }
+// Shared by the mouse and keyboard paths once both corners are known. Returns
+// false, leaving the overlay open, when the selection is too small to use.
+fn confirm_rectangle_selection(
+    app: &Application,
+    parent_window: &ApplicationWindow,
+    overlay_window: &ApplicationWindow,
+    original_image: &Rc<image::DynamicImage>,
+    start: (f64, f64),
+    end: (f64, f64),
+    options: SelectionOptions,
+) -> bool {
+    let SelectionOptions {
+        copy_only,
+        action,
+        recropping,
+        claim,
+    } = options;
+    let (x, y, w, h) = selection_rect(start, end);
+
+    // A plain click without any drag is a deliberate cancel
+    if w == 0 && h == 0 {
+        overlay_window.close();
+        parent_window.set_visible(true);
+        return true;
+    }
+
+    // Tiny accidental drags keep the overlay open so the user can retry
+    let min_size = preferences::get().capture.min_selection_size as i32;
+    if w < min_size || h < min_size {
+        info!("Selection {}x{} is below the {}px minimum", w, h, min_size);
+        return false;
+    }
+
+    // The selection is in overlay (logical) pixels, while the snapshot it is
+    // cropped from has one pixel per device pixel, or is a stretched earlier
+    // capture when re-cropping
+    let (image_width, image_height) = original_image.dimensions();
+    let crop = selection_in_image(
+        (x, y, w, h),
+        (overlay_window.width(), overlay_window.height()),
+        (image_width as i32, image_height as i32),
+    );
+    let (crop_x, crop_y, crop_w, crop_h) = crop;
+
+    // The editor opens on the monitor the region was selected on
+    let monitor_index = monitor_index_of(overlay_window);
+    // Closing the overlay releases its claim, which a contact sheet still needs
+    let sheet_claim = claim.borrow_mut().take();
+    overlay_window.close();
+    history::remember_region(x as f64, y as f64, w as f64, h as f64);
+
+    // The selection becomes the region every contact sheet frame is taken
+    // from; what is on screen right now is the first frame
+    if action == RegionAction::ContactSheet {
+        let first_frame = crop_image_direct(original_image, crop_x, crop_y, crop_w, crop_h)
+            .map_err(|e| error!("Failed to crop first contact sheet frame: {}", e))
+            .ok();
+        if let Some(claim) = sheet_claim {
+            start_contact_sheet(app.clone(), parent_window.clone(), crop, first_frame, claim);
+        }
+        return true;
+    }
+
+    // A re-crop opens in an editor of its own, and the editor it was started
+    // from comes back as it was, annotations and all
+    if recropping {
+        parent_window.set_visible(true);
+        let result = crop_image_direct(original_image, crop_x, crop_y, crop_w, crop_h).and_then(
+            |cropped_png| {
+                run_after_capture(
+                    app,
+                    AfterCapture::Editor,
+                    cropped_png,
+                    monitor_index,
+                    None,
+                    Some(original_image.clone()),
+                )
+            },
+        );
+        if let Err(e) = result {
+            error!("Failed to re-crop capture: {}", e);
+            show_error_dialog(parent_window, &format!("Failed to re-crop: {}", e));
+        }
+        return true;
+    }
+
+    // A pinned region skips the editor altogether
+    if action == RegionAction::Pin {
+        let result = crop_image_direct(original_image, crop_x, crop_y, crop_w, crop_h)
+            .and_then(|cropped_png| pin::show(app, &cropped_png, get_screen_scale_factor()));
+        match result {
+            Ok(_) => {
+                dismiss_capture_window(parent_window);
+                if preferences::get().general.keep_open {
+                    parent_window.present();
+                }
+            }
+            Err(e) => {
+                error!("Failed to pin region: {}", e);
+                parent_window.set_visible(true);
+                show_error_dialog(parent_window, &format!("Failed to pin region: {}", e));
+            }
+        }
+        return true;
+    }
+
+    if copy_only {
+        match crop_image(original_image, crop_x, crop_y, crop_w, crop_h) {
+            Ok(cropped) => {
+                dismiss_capture_window(parent_window);
+                copy_image_and_quit(app, cropped.to_rgba8());
+                if preferences::get().general.keep_open {
+                    parent_window.present();
+                }
+            }
+            Err(e) => {
+                error!("Failed to crop captured image: {}", e);
+                parent_window.set_visible(true);
+                show_error_dialog(parent_window, &format!("Failed to copy region: {}", e));
+            }
+        }
+        return true;
+    }
+
+    // Crop the frozen snapshot the selection was made on
+    match crop_image_direct(original_image, crop_x, crop_y, crop_w, crop_h) {
+        Ok(cropped_png) => {
+            proceed_with_cropped_screenshot(
+                app.clone(),
+                parent_window.clone(),
+                cropped_png,
+                monitor_index,
+                original_image.clone(),
+            );
+        }
+        Err(e) => {
+            error!("Failed to crop captured image: {}", e);
+            parent_window.set_visible(true);
+            show_error_dialog(parent_window, &format!("Failed to crop region: {}", e));
+        }
+    }
+
+    true
+}
+
+// Contact sheet session for one region. Each press of Space (or the Capture
+// Frame button) hides this window and captures the region again; Finish lays
+// all frames out in a labelled grid and hands it on like any other capture.
+fn start_contact_sheet(
+    app: Application,
+    parent_window: ApplicationWindow,
+    rect: (i32, i32, i32, i32),
+    first_frame: Option<Vec<u8>>,
+    claim: CaptureClaim,
+) {
+    let frames = Rc::new(RefCell::new(first_frame.into_iter().collect::<Vec<_>>()));
+
+    let window = ApplicationWindow::builder()
+        .application(&app)
+        .title("Contact Sheet")
+        .default_width(360)
+        .resizable(false)
+        .build();
+    // No other capture starts while frames are being taken
+    claim.hold_until_destroyed(&window);
+
+    let main_box = Box::new(Orientation::Vertical, 12);
+    main_box.set_margin_start(20);
+    main_box.set_margin_end(20);
+    main_box.set_margin_top(20);
+    main_box.set_margin_bottom(20);
+
+    let count_label = Label::new(None);
+    count_label.add_css_class("title-3");
+
+    let hint_label = Label::new(Some(
+        "Set up the next state, then press Space to capture the region again. \
+         Press Enter to build the contact sheet.",
+    ));
+    hint_label.set_wrap(true);
+    hint_label.set_max_width_chars(40);
+
+    let button_box = Box::new(Orientation::Horizontal, 10);
+    button_box.set_halign(gtk4::Align::End);
+    let cancel_button = Button::with_label("Cancel");
+    let capture_button = Button::with_label("Capture Frame");
+    let finish_button = Button::with_label("Finish");
+    finish_button.add_css_class("suggested-action");
+    button_box.append(&cancel_button);
+    button_box.append(&capture_button);
+    button_box.append(&finish_button);
+
+    main_box.append(&count_label);
+    main_box.append(&hint_label);
+    main_box.append(&button_box);
+    window.set_child(Some(&main_box));
+
+    let update_count: Rc<dyn Fn()> = {
+        let frames = frames.clone();
+        let finish_button = finish_button.clone();
+        Rc::new(move || {
+            let count = frames.borrow().len();
+            count_label.set_text(&format!(
+                "{} frame{} captured",
+                count,
+                if count == 1 { "" } else { "s" }
+            ));
+            finish_button.set_sensitive(count > 0);
+        })
+    };
+    update_count();
+
+    // Capture off the main thread once this window is out of the way
+    let capture_frame: Rc<dyn Fn()> = {
+        let window = window.clone();
+        let frames = frames.clone();
+        let update_count = update_count.clone();
+        Rc::new(move || {
+            window.set_visible(false);
+
+            let window = window.clone();
+            let frames = frames.clone();
+            let update_count = update_count.clone();
+            let hidden_window = window.clone();
+            when_window_hidden(&hidden_window, move || {
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let _ = sender.send(take_screenshot_sync(Some(rect)));
+                });
+
+                glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+                    match receiver.try_recv() {
+                        Ok(Ok(png_data)) => {
+                            frames.borrow_mut().push(png_data);
+                            info!("Captured contact sheet frame {}", frames.borrow().len());
+                            update_count();
+                            window.present();
+                        }
+                        Ok(Err(e)) => {
+                            error!("Failed to capture contact sheet frame: {}", e);
+                            window.present();
+                            show_error_dialog(&window, &capture_error_message(&e));
+                        }
+                        Err(mpsc::TryRecvError::Empty) => return glib::ControlFlow::Continue,
+                        Err(_) => {
+                            error!("Contact sheet capture thread failed");
+                            window.present();
+                        }
+                    }
+                    glib::ControlFlow::Break
+                });
+            });
+        })
+    };
+
+    // Roughly square grids read best
+    let finish: Rc<dyn Fn()> = {
+        let app = app.clone();
+        let parent_window = parent_window.clone();
+        let window = window.clone();
+        let frames = frames.clone();
+        Rc::new(move || {
+            let count = frames.borrow().len();
+            if count == 0 {
+                return;
+            }
+            let cols = (count as f64).sqrt().ceil() as usize;
+
+            let sheet = contact_sheet::compose_grid(&frames.borrow(), cols);
+            match sheet {
+                Ok(png_data) => {
+                    // Destroy rather than close, which would bring the
+                    // capture window back
+                    window.destroy();
+                    dismiss_capture_window(&parent_window);
+                    if let Err(e) = handle_capture(&app, Some(&parent_window), png_data, None) {
+                        error!("Failed to handle contact sheet: {}", e);
+                        parent_window.set_visible(true);
+                        show_error_dialog(
+                            &parent_window,
+                            &format!("Failed to handle contact sheet: {}", e),
+                        );
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to build contact sheet: {}", e);
+                    show_error_dialog(&window, &format!("Failed to build contact sheet: {}", e));
+                }
+            }
+        })
+    };
+
+    let window_cancel = window.clone();
+    cancel_button.connect_clicked(move |_| window_cancel.close());
+
+    let capture_frame_button = capture_frame.clone();
+    capture_button.connect_clicked(move |_| capture_frame_button());
+
+    let finish_button_clicked = finish.clone();
+    finish_button.connect_clicked(move |_| finish_button_clicked());
+
+    // Capture phase, so Space never activates whichever button has focus
+    let key_controller = gtk4::EventControllerKey::new();
+    key_controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    let window_key = window.clone();
+    key_controller.connect_key_pressed(move |_, key, _, _| match key {
+        gdk4::Key::space => {
+            capture_frame();
+            glib::Propagation::Stop
+        }
+        gdk4::Key::Return | gdk4::Key::KP_Enter => {
+            finish();
+            glib::Propagation::Stop
+        }
+        gdk4::Key::Escape => {
+            window_key.close();
+            glib::Propagation::Stop
+        }
+        _ => glib::Propagation::Proceed,
+    });
+    window.add_controller(key_controller);
+
+    // Cancelling brings the capture window back
+    let parent_close = parent_window.clone();
+    window.connect_close_request(move |_| {
+        parent_close.set_visible(true);
+        glib::Propagation::Proceed
+    });
+
+    window.present();
+}
+
+// Copy the image straight to the clipboard once every window has closed. The
+// app stays alive in the background until another application takes the
+// clipboard over, since the clipboard contents are served by this process.
+fn copy_image_and_quit(app: &Application, image: image::RgbaImage) {
+    let (width, height) = image.dimensions();
+    let hold_guard = app.hold();
+
+    let notification = gtk4::gio::Notification::new("Screenshot copied");
+    notification.set_body(Some(&format!(
+        "{}×{} screenshot copied to the clipboard",
+        width, height
+    )));
+    app.send_notification(None, &notification);
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(clipboard::copy_image_and_wait(
+            width,
+            height,
+            image.as_raw(),
+        ));
+    });
+
+    let app = app.clone();
+    glib::timeout_add_local(
+        std::time::Duration::from_millis(200),
+        move || match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => glib::ControlFlow::Continue,
+            result => {
+                if let Ok(Err(e)) = result {
+                    error!("Failed to copy region to clipboard: {}", e);
+                    // No window is left, so report the failure as a notification
+                    let notification = gtk4::gio::Notification::new("Copy failed");
+                    notification.set_body(Some(&e.to_string()));
+                    app.send_notification(None, &notification);
+                }
+                info!("Clipboard handed over, releasing the application");
+                let _ = &hold_guard;
+                glib::ControlFlow::Break
+            }
+        },
+    );
+}
+
+fn proceed_with_screenshot(
+    app: Application,
+    window: ApplicationWindow,
+    rect: Option<(i32, i32, i32, i32)>,
+    monitor_index: Option<u32>,
+    // Area to blur out of the capture, such as a window kept private
+    redact: Option<(i32, i32, u32, u32)>,
+    claim: CaptureClaim,
+) {
+    // Shown as busy should it come back before the capture is done
+    window.set_sensitive(false);
+
+    if rect.is_none() {
+        history::clear();
+    }
+
+    // Create a channel for communication between threads
+    let (sender, receiver) = mpsc::channel();
+
+    // Spawn a thread for screenshot capture
+    thread::spawn(move || {
+        info!("Screenshot capture thread started");
+
+        let result = take_screenshot_sync(rect).and_then(|png| match redact {
+            Some((x, y, width, height)) => capture::blur_region_png(&png, x, y, width, height),
+            None => Ok(png),
+        });
+        match &result {
+            Ok(_) => info!("Screenshot capture completed successfully"),
+            Err(e) => error!("Screenshot capture failed: {}", e),
+        }
+
+        // The receiver is gone if the window closed while capturing; the
+        // result is no longer wanted then
+        if sender.send(result).is_err() {
+            debug!("Capture window closed before the screenshot finished, dropping it");
+        }
+    });
+
+    // Stop polling once the window has been destroyed
+    let window_destroyed = Rc::new(Cell::new(false));
+    let destroyed = window_destroyed.clone();
+    let destroy_handler = window.connect_destroy(move |_| destroyed.set(true));
+    let mut destroy_handler = Some(destroy_handler);
+    let mut claim = Some(claim);
+
+    // Use glib timeout to check for completion
+    glib::timeout_add_local(std::time::Duration::from_millis(100), move || {
+        if window_destroyed.get() {
+            info!("Capture window was destroyed, no longer waiting for the screenshot");
+            claim.take();
+            return glib::ControlFlow::Break;
+        }
+
+        let result = receiver.try_recv();
+        if !matches!(result, Err(mpsc::TryRecvError::Empty)) {
+            if let Some(handler) = destroy_handler.take() {
+                window.disconnect(handler);
+            }
+            claim.take();
+            window.set_sensitive(true);
+        }
+
+        match result {
+            Ok(result) => {
+                match result {
+                    Ok(image_data) => {
+                        info!(
+                            "Screenshot captured successfully ({} bytes)",
+                            image_data.len()
+                        );
+
+                        dismiss_capture_window(&window);
+
+                        // Feedback only runs now that the capture is done, so
+                        // it never shows up in the screenshot itself
+                        show_capture_feedback(&app, rect);
+
+                        if let Err(e) =
+                            handle_capture(&app, Some(&window), image_data, monitor_index)
+                        {
+                            error!("Failed to handle screenshot: {}", e);
+                            show_error_dialog(
+                                &window,
+                                &format!("Failed to handle screenshot: {}", e),
+                            );
+                        }
                     }
                     Err(e) => {
                         error!("Failed to capture screenshot: {}", e);
 
                         // Show the window again and display error
                         window.set_visible(true);
-                        show_error_dialog(&window, &format!("Failed to capture screenshot: {}", e));
+                        show_error_dialog(&window, &capture_error_message(&e));
                     }
                 }
                 glib::ControlFlow::Break
@@ -564,7 +2065,182 @@ fn proceed_with_screenshot(
     });
 }
 
-fn take_screenshot_sync(rect: Option<(i32, i32, i32, i32)>) -> Result<Vec<u8>> {
+// `area` is the captured region in screen device pixels, if the capture
+// wasn't the whole screen
+fn show_capture_feedback(app: &Application, area: Option<(i32, i32, i32, i32)>) {
+    let capture_preferences = &preferences::get().capture;
+
+    if capture_preferences.shutter_sound {
+        // Use the desktop sound theme's shutter sound when available
+        if let Err(e) = std::process::Command::new("canberra-gtk-play")
+            .args([
+                "--id",
+                "camera-shutter",
+                "--description",
+                "Screenshot taken",
+            ])
+            .spawn()
+        {
+            log::warn!("Failed to play shutter sound: {}", e);
+        }
+    }
+
+    if !capture_preferences.flash_on_capture {
+        return;
+    }
+
+    // Borderless white overlay that fades out over ~150ms
+    let flash_window = ApplicationWindow::builder()
+        .application(app)
+        .title("Flint Capture Flash")
+        .decorated(false)
+        .can_focus(false)
+        .build();
+
+    let flash_area = DrawingArea::new();
+    flash_area.set_draw_func(|_, ctx, width, height| {
+        ctx.set_source_rgb(1.0, 1.0, 1.0);
+        ctx.rectangle(0.0, 0.0, width as f64, height as f64);
+        ctx.fill().unwrap();
+    });
+    flash_window.set_child(Some(&flash_area));
+    flash_window.set_opacity(0.8);
+
+    // GTK 4 cannot position windows, so only on X11, where Flint moves the
+    // window itself, can the flash cover just the captured area. Elsewhere it
+    // covers the screen.
+    flash_window.realize();
+    match (area, window_manager::x11_window_id(&flash_window)) {
+        (Some((x, y, width, height)), Some(window_id)) => {
+            let scale_factor = get_screen_scale_factor();
+            flash_window.set_resizable(false);
+            flash_window.set_default_size(width / scale_factor, height / scale_factor);
+            flash_window.connect_map(move |_| {
+                if let Err(e) = window_manager::WindowManager::new()
+                    .and_then(|manager| manager.move_window(window_id, x, y))
+                {
+                    log::warn!("Failed to place capture flash: {}", e);
+                }
+            });
+        }
+        _ => flash_window.fullscreen(),
+    }
+    flash_window.present();
+
+    let step = Rc::new(RefCell::new(0));
+    glib::timeout_add_local(std::time::Duration::from_millis(15), move || {
+        *step.borrow_mut() += 1;
+        let progress = *step.borrow() as f64 / 10.0;
+
+        if progress >= 1.0 {
+            flash_window.close();
+            glib::ControlFlow::Break
+        } else {
+            flash_window.set_opacity(0.8 * (1.0 - progress));
+            glib::ControlFlow::Continue
+        }
+    });
+}
+
+// Capture as the command line asked and hand the result to the after-capture
+// action, returning the process exit code
+fn run_cli_capture(app: &Application, options: &CliOptions) -> i32 {
+    match (&options.monitor, options.region) {
+        _ if options.active_window => {
+            info!("Capturing the active window from command line");
+            history::clear();
+        }
+        (Some(connector), _) => {
+            info!("Capturing monitor {} from command line", connector);
+            history::clear();
+        }
+        (None, Some((x, y, width, height))) => {
+            info!(
+                "Capturing region from command line: {}x{} at ({}, {})",
+                width, height, x, y
+            );
+            history::remember_region(x as f64, y as f64, width as f64, height as f64);
+        }
+        (None, None) => {
+            info!("Capturing full screen from command line");
+            history::clear();
+        }
+    }
+
+    // No window is shown yet, so capturing on the main thread is fine here
+    match capture_from_options(options)
+        .and_then(|image_data| handle_capture(app, None, image_data, None))
+    {
+        Ok(_) => 0,
+        Err(e) => {
+            error!("Command line capture failed: {}", e);
+            eprintln!("flint: {}", e);
+            1
+        }
+    }
+}
+
+// Write the capture to stdout or the --output file for scripting and return
+// the process exit code. GTK is initialised for display queries only; no
+// window is ever presented.
+fn run_headless_capture(options: &CliOptions) -> i32 {
+    use std::io::Write;
+
+    let result = gtk4::init()
+        .map_err(|e| anyhow::anyhow!("Failed to initialize GTK: {}", e))
+        .and_then(|_| capture_from_options(options))
+        .and_then(|image_data| match options.output {
+            Some(ref path) => std::fs::write(path, &image_data)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e)),
+            None => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(&image_data)?;
+                stdout.flush()?;
+                Ok(())
+            }
+        });
+
+    match result {
+        Ok(_) => 0,
+        Err(e) => {
+            error!("Headless capture failed: {}", e);
+            eprintln!("flint: {}", e);
+            1
+        }
+    }
+}
+
+fn capture_from_options(options: &CliOptions) -> Result<Vec<u8>> {
+    if options.active_window {
+        let window_manager = window_manager::WindowManager::new()?;
+        let window_info = window_manager.active_window()?;
+        return Ok(window_manager.capture_window(window_info.frame_id)?);
+    }
+
+    if let Some(ref connector) = options.monitor {
+        let (x, y, width, height) = monitor_geometry(connector)?;
+        return capture_region(x, y, width, height);
+    }
+
+    match options.region {
+        Some((x, y, width, height)) => capture_region(x, y, width, height),
+        None => Ok(ScreenshotCapture::new().take_screenshot_blocking()?),
+    }
+}
+
+// Capture a region given in screen coordinates without going through the
+// selection overlay. Sizes must be positive; regions are clamped to the screen.
+fn capture_region(x: i32, y: i32, width: i32, height: i32) -> Result<Vec<u8>> {
+    let (screen_width, screen_height) = get_screen_info_without_capture();
+    let (x, y, width, height) =
+        capture::clamp_region(x, y, width, height, screen_width, screen_height)?;
+
+    Ok(ScreenshotCapture::new().take_screenshot_region_blocking(x, y, width, height)?)
+}
+
+fn take_screenshot_sync(
+    rect: Option<(i32, i32, i32, i32)>,
+) -> std::result::Result<Vec<u8>, CaptureError> {
     info!("Initializing screenshot capture");
     let capture = ScreenshotCapture::new();
 
@@ -584,6 +2260,52 @@ fn take_screenshot_sync(rect: Option<(i32, i32, i32, i32)>) -> Result<Vec<u8>> {
     result
 }
 
+// Error text plus a hint at what usually fixes that kind of failure
+fn capture_error_message(error: &CaptureError) -> String {
+    let hint = match error {
+        CaptureError::NoDisplay(_) => {
+            "Make sure Flint is running in a graphical session with a display."
+        }
+        CaptureError::CaptureFailed(_)
+            if std::env::var_os("WAYLAND_DISPLAY").is_some() && !capture::has_grim() =>
+        {
+            "On Wayland, screen capture goes through XWayland, which only sees X11 windows on wlroots compositors. Install grim (and slurp for region selection), or make sure XWayland is running and the compositor allows screen capture."
+        }
+        CaptureError::CaptureFailed(_) if std::env::var_os("WAYLAND_DISPLAY").is_some() => {
+            "Make sure the compositor supports wlr-screencopy for grim, or that XWayland is running and allows screen capture."
+        }
+        CaptureError::CaptureFailed(_) => "Make sure Flint is allowed to read the screen.",
+        CaptureError::InvalidRegionSize { .. } | CaptureError::RegionOutOfBounds { .. } => {
+            "Select a region that lies on the screen."
+        }
+        CaptureError::Cancelled => return error.to_string(),
+        CaptureError::PortalUnavailable(_) => {
+            "Install xdg-desktop-portal and a backend for your desktop, or switch region_selector back to \"flint\"."
+        }
+        CaptureError::InvalidImage(_) | CaptureError::Decode(_) | CaptureError::Encode(_) => {
+            return format!("Failed to capture screenshot: {}", error)
+        }
+    };
+
+    format!("Failed to capture screenshot: {}\n\n{}", error, hint)
+}
+
+fn window_error_message(action: &str, error: &WindowError) -> String {
+    let hint = match error {
+        WindowError::Unsupported(_) => {
+            "This feature requires X11. On Wayland, please use Screen or Selection capture instead."
+        }
+        WindowError::NoConnection(_) => {
+            "Window selection needs an X11 display. Check that Flint can reach the X server."
+        }
+        WindowError::NotVisible => "Tip: Make sure the target window is visible and not minimized.",
+        WindowError::Changed => "Please try again once the window has settled.",
+        _ => return format!("{}: {}", action, error),
+    };
+
+    format!("{}: {}\n\n{}", action, error, hint)
+}
+
 fn show_error_dialog(parent: &ApplicationWindow, message: &str) {
     let dialog = gtk4::MessageDialog::builder()
         .transient_for(parent)
@@ -603,12 +2325,9 @@ fn show_error_dialog(parent: &ApplicationWindow, message: &str) {
 fn capture_current_screen_for_preview_with_data(
     width: i32,
     height: i32,
-) -> (cairo::ImageSurface, Option<Vec<u8>>) {
+) -> (cairo::ImageSurface, Option<Rc<image::DynamicImage>>) {
     info!("Attempting to capture current screen state for preview with original data");
 
-    // Longer delay to ensure capture UI window is completely hidden
-    std::thread::sleep(std::time::Duration::from_millis(500));
-
     let capture = ScreenshotCapture::new();
 
     match capture.take_screenshot_blocking() {
@@ -617,40 +2336,8 @@ fn capture_current_screen_for_preview_with_data(
             // Load PNG data into an image
             match image::load_from_memory(&png_data) {
                 Ok(img) => {
-                    // Convert to RGBA format
-                    let rgba_img = img.to_rgba8();
-                    let (img_width, img_height) = rgba_img.dimensions();
-                    let pixels = rgba_img.into_raw();
-
-                    // Convert RGBA to BGRA for Cairo (Cairo expects BGRA on little-endian systems)
-                    let mut bgra_pixels = Vec::with_capacity(pixels.len());
-                    for chunk in pixels.chunks(4) {
-                        if chunk.len() == 4 {
-                            bgra_pixels.push(chunk[2]); // B
-                            bgra_pixels.push(chunk[1]); // G
-                            bgra_pixels.push(chunk[0]); // R
-                            bgra_pixels.push(chunk[3]); // A
-                        }
-                    }
-
-                    // Create Cairo ImageSurface with actual screen capture
-                    match cairo::ImageSurface::create_for_data(
-                        bgra_pixels,
-                        cairo::Format::ARgb32,
-                        img_width as i32,
-                        img_height as i32,
-                        img_width as i32 * 4,
-                    ) {
-                        Ok(surface) => {
-                            info!(
-                                "Created Cairo surface from screen capture: {}x{}",
-                                img_width, img_height
-                            );
-                            return (surface, Some(png_data));
-                        }
-                        Err(e) => {
-                            log::warn!("Failed to create Cairo surface from capture: {}", e);
-                        }
+                    if let Some(surface) = preview_surface_from_image(&img, width, height) {
+                        return (surface, Some(Rc::new(img)));
                     }
                 }
                 Err(e) => {
@@ -668,6 +2355,51 @@ fn capture_current_screen_for_preview_with_data(
     (create_screen_preview_pattern(width, height), None)
 }
 
+// Downscale a copy of a full capture to the overlay's device-pixel size with
+// a high quality filter; the full image is kept for cropping
+fn preview_surface_from_image(
+    img: &image::DynamicImage,
+    width: i32,
+    height: i32,
+) -> Option<cairo::ImageSurface> {
+    let preview = capture::downscale_to_fit(img.clone(), width as u32, height as u32);
+    let rgba_img = preview.to_rgba8();
+    let (img_width, img_height) = rgba_img.dimensions();
+    let pixels = rgba_img.into_raw();
+
+    // Convert RGBA to BGRA for Cairo (Cairo expects BGRA on little-endian systems)
+    let mut bgra_pixels = Vec::with_capacity(pixels.len());
+    for chunk in pixels.chunks(4) {
+        if chunk.len() == 4 {
+            bgra_pixels.push(chunk[2]); // B
+            bgra_pixels.push(chunk[1]); // G
+            bgra_pixels.push(chunk[0]); // R
+            bgra_pixels.push(chunk[3]); // A
+        }
+    }
+
+    // Create Cairo ImageSurface with actual screen capture
+    match cairo::ImageSurface::create_for_data(
+        bgra_pixels,
+        cairo::Format::ARgb32,
+        img_width as i32,
+        img_height as i32,
+        img_width as i32 * 4,
+    ) {
+        Ok(surface) => {
+            info!(
+                "Created Cairo surface from screen capture: {}x{}",
+                img_width, img_height
+            );
+            Some(surface)
+        }
+        Err(e) => {
+            log::warn!("Failed to create Cairo surface from capture: {}", e);
+            None
+        }
+    }
+}
+
 fn get_screen_info_without_capture() -> (i32, i32) {
     // Get screen dimensions using GDK without actually capturing
     let display = gdk4::Display::default().expect("Failed to get default display");
@@ -687,6 +2419,112 @@ fn get_screen_info_without_capture() -> (i32, i32) {
     }
 }
 
+// Logical pixels per inch, from the reference DPI preference or else the
+// primary monitor's reported physical size. None if neither is known.
+fn get_screen_dpi() -> Option<f64> {
+    let reference_dpi = preferences::get().capture.reference_dpi;
+    if reference_dpi > 0.0 {
+        return Some(reference_dpi);
+    }
+
+    let display = gdk4::Display::default()?;
+    let monitor = display
+        .monitors()
+        .item(0)
+        .and_then(|item| item.downcast::<gdk4::Monitor>().ok())?;
+
+    // Some displays report 0 or nonsense sizes; treat those as unknown
+    let width_mm = monitor.width_mm();
+    if width_mm <= 0 {
+        return None;
+    }
+
+    let dpi = monitor.geometry().width() as f64 / (width_mm as f64 / MM_PER_INCH);
+    (20.0..=1000.0).contains(&dpi).then_some(dpi)
+}
+
+// Index of the monitor containing the point, in logical desktop coordinates
+fn monitor_index_at(x: i32, y: i32) -> Option<u32> {
+    let monitors = gdk4::Display::default()?.monitors();
+
+    (0..monitors.n_items()).find(|&index| {
+        monitors
+            .item(index)
+            .and_then(|item| item.downcast::<gdk4::Monitor>().ok())
+            .map_or(false, |monitor| {
+                let geometry = monitor.geometry();
+                x >= geometry.x()
+                    && x < geometry.x() + geometry.width()
+                    && y >= geometry.y()
+                    && y < geometry.y() + geometry.height()
+            })
+    })
+}
+
+// Geometry of the monitor on the named connector, such as "DP-1". Naming a
+// connector that isn't present lists the ones that are.
+fn monitor_geometry(connector: &str) -> Result<(i32, i32, i32, i32)> {
+    let display =
+        gdk4::Display::default().ok_or_else(|| anyhow::anyhow!("No display available"))?;
+    let monitors = display.monitors();
+    let monitors: Vec<gdk4::Monitor> = (0..monitors.n_items())
+        .filter_map(|index| monitors.item(index)?.downcast::<gdk4::Monitor>().ok())
+        .collect();
+
+    if let Some(monitor) = monitors
+        .iter()
+        .find(|monitor| monitor.connector().as_deref() == Some(connector))
+    {
+        let geometry = monitor.geometry();
+        return Ok((
+            geometry.x(),
+            geometry.y(),
+            geometry.width(),
+            geometry.height(),
+        ));
+    }
+
+    let available: Vec<String> = monitors
+        .iter()
+        .filter_map(|monitor| monitor.connector().map(|name| name.to_string()))
+        .collect();
+    Err(anyhow::anyhow!(
+        "No monitor on connector '{}' (available: {})",
+        connector,
+        if available.is_empty() {
+            "none".to_string()
+        } else {
+            available.join(", ")
+        }
+    ))
+}
+
+// Index of the monitor a realized window is shown on
+fn monitor_index_of(window: &ApplicationWindow) -> Option<u32> {
+    let surface = window.surface()?;
+    let display = surface.display();
+    let monitor = display.monitor_at_surface(&surface)?;
+    let monitors = display.monitors();
+
+    (0..monitors.n_items()).find(|&index| {
+        monitors
+            .item(index)
+            .map_or(false, |item| item == *monitor.upcast_ref::<glib::Object>())
+    })
+}
+
+fn get_screen_scale_factor() -> i32 {
+    // Device pixels per logical pixel of the primary monitor
+    let display = gdk4::Display::default().expect("Failed to get default display");
+    let monitors = display.monitors();
+
+    monitors
+        .item(0)
+        .and_then(|item| item.downcast::<gdk4::Monitor>().ok())
+        .map(|monitor| monitor.scale_factor().max(1))
+        .unwrap_or(1)
+}
+
 fn create_screen_preview_pattern(width: i32, height: i32) -> cairo::ImageSurface {
     // Create a visual pattern that represents the desktop without actually capturing it
     let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)
@@ -765,92 +2603,658 @@ fn create_screen_preview_pattern(width: i32, height: i32) -> cairo::ImageSurface
     surface
 }
 
-fn crop_png_data_direct(
-    png_data: &[u8],
+fn crop_image_direct(
+    image: &image::DynamicImage,
     x: i32,
     y: i32,
     width: i32,
     height: i32,
 ) -> Result<Vec<u8>> {
+    let cropped = crop_image(image, x, y, width, height)?;
+
+    // Convert back to PNG bytes
+    let mut buffer = Vec::new();
+    cropped
+        .write_to(
+            &mut std::io::Cursor::new(&mut buffer),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to convert cropped image to PNG: {}", e))?;
+
+    info!("Cropped image converted to PNG, {} bytes", buffer.len());
+    Ok(buffer)
+}
+
+fn crop_image(
+    image: &image::DynamicImage,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) -> Result<image::DynamicImage> {
     info!(
-        "Cropping PNG data directly: {}x{} at ({}, {})",
+        "Cropping captured image directly: {}x{} at ({}, {})",
         width, height, x, y
     );
 
-    // Load the image from PNG bytes
-    let image = image::load_from_memory(png_data)
-        .map_err(|e| anyhow::anyhow!("Failed to load image for cropping: {}", e))?;
+    let (img_width, img_height) = image.dimensions();
+    info!("Original image dimensions: {}x{}", img_width, img_height);
+
+    // Validate crop bounds
+    let crop_x = x.max(0) as u32;
+    let crop_y = y.max(0) as u32;
+    let crop_width = width.min(img_width as i32 - x).max(1) as u32;
+    let crop_height = height.min(img_height as i32 - y).max(1) as u32;
+
+    if crop_x >= img_width || crop_y >= img_height {
+        return Err(anyhow::anyhow!("Crop region is outside image bounds"));
+    }
+
+    info!(
+        "Adjusted crop region: {}x{} at ({}, {})",
+        crop_width, crop_height, crop_x, crop_y
+    );
+
+    // Crop the image
+    Ok(image.crop_imm(crop_x, crop_y, crop_width, crop_height))
+}
+
+// `source` is the full capture the region was cropped from, kept so the
+// editor can re-crop it
+fn proceed_with_cropped_screenshot(
+    app: Application,
+    window: ApplicationWindow,
+    png_data: Vec<u8>,
+    monitor_index: Option<u32>,
+    source: Rc<image::DynamicImage>,
+) {
+    info!("Handling cropped screenshot ({} bytes)", png_data.len());
+
+    dismiss_capture_window(&window);
+
+    if let Err(e) = handle_capture_as(
+        &app,
+        Some(&window),
+        png_data,
+        monitor_index,
+        None,
+        Some(source),
+    ) {
+        error!("Failed to handle screenshot: {}", e);
+        show_error_dialog(&window, &format!("Failed to handle screenshot: {}", e));
+    }
+}
+
+// The capture window goes away once a capture is taken. With keep_open it is
+// only hidden, and comes back when the capture has been dealt with.
+fn dismiss_capture_window(window: &ApplicationWindow) {
+    if preferences::get().general.keep_open {
+        window.set_visible(false);
+    } else {
+        window.close();
+    }
+}
+
+// Run the post-capture action for a freshly captured PNG. Every capture path
+// ends here, so the editor is only one of the possible outcomes.
+fn handle_capture(
+    app: &Application,
+    capture_window: Option<&ApplicationWindow>,
+    png_data: Vec<u8>,
+    monitor_index: Option<u32>,
+) -> Result<()> {
+    handle_capture_as(app, capture_window, png_data, monitor_index, None, None)
+}
+
+// Like handle_capture, with an action chosen by the capture path in place of
+// the after_capture preference. --action still takes precedence. A region
+// capture passes the full capture it was cut from as `recrop_source`.
+fn handle_capture_as(
+    app: &Application,
+    capture_window: Option<&ApplicationWindow>,
+    png_data: Vec<u8>,
+    monitor_index: Option<u32>,
+    action: Option<AfterCapture>,
+    recrop_source: Option<Rc<image::DynamicImage>>,
+) -> Result<()> {
+    let action = AFTER_CAPTURE_OVERRIDE
+        .get()
+        .copied()
+        .or(action)
+        .unwrap_or(preferences::get().general.after_capture);
+    let return_to = capture_window
+        .filter(|_| preferences::get().general.keep_open)
+        .cloned();
+
+    let result = run_after_capture(
+        app,
+        action,
+        png_data,
+        monitor_index,
+        return_to.clone(),
+        recrop_source,
+    );
+    if let (Err(_), Some(window)) = (&result, return_to) {
+        window.present();
+    }
+    result
+}
+
+// `return_to` is the hidden capture window to bring back once the action is
+// finished, when the keep_open preference is on. With a `recrop_source` the
+// editor offers to choose a different region of it.
+fn run_after_capture(
+    app: &Application,
+    action: AfterCapture,
+    png_data: Vec<u8>,
+    monitor_index: Option<u32>,
+    return_to: Option<ApplicationWindow>,
+    recrop_source: Option<Rc<image::DynamicImage>>,
+) -> Result<()> {
+    info!("Running post-capture action {:?}", action);
+
+    match action {
+        AfterCapture::Editor => {
+            // Kept until the editor is done with it, in case it fails
+            let recovery_capture = recovery::store(&png_data);
+            if AnnotationEditor::is_over_pixel_budget(&png_data) {
+                offer_downscale(
+                    app,
+                    png_data,
+                    monitor_index,
+                    return_to,
+                    recrop_source,
+                    recovery_capture,
+                );
+            } else {
+                open_editor(
+                    app,
+                    png_data,
+                    monitor_index,
+                    return_to,
+                    recrop_source,
+                    false,
+                    recovery_capture,
+                )?;
+            }
+        }
+        AfterCapture::Copy => {
+            let image = image::load_from_memory(&png_data)?.to_rgba8();
+            copy_image_and_quit(app, image);
+            if let Some(window) = return_to {
+                window.present();
+            }
+        }
+        AfterCapture::Save => {
+            let path = save_capture(&png_data)?;
+
+            let notification = gtk4::gio::Notification::new("Screenshot saved");
+            notification.set_body(Some(&path.display().to_string()));
+            app.send_notification(None, &notification);
+            if let Some(window) = return_to {
+                window.present();
+            }
+        }
+        AfterCapture::Ask => {
+            show_after_capture_chooser(app, png_data, monitor_index, return_to, recrop_source)
+        }
+    }
+
+    Ok(())
+}
+
+fn open_editor(
+    app: &Application,
+    png_data: Vec<u8>,
+    monitor_index: Option<u32>,
+    return_to: Option<ApplicationWindow>,
+    recrop_source: Option<Rc<image::DynamicImage>>,
+    downscale: bool,
+    recovery_capture: u64,
+) -> Result<()> {
+    let editor = AnnotationEditor::new(app, png_data, monitor_index, downscale)?;
+    info!("Editor created successfully");
+    editor.set_recovery_capture(recovery_capture);
+    editor.connect_closed(move || recovery::release(recovery_capture));
+    if let Some(source) = recrop_source {
+        let app = app.clone();
+        editor.connect_recrop(move |editor_window| {
+            if let Some(claim) = CaptureClaim::take() {
+                show_rectangle_selection(
+                    app.clone(),
+                    editor_window.clone(),
+                    RegionAction::Capture,
+                    Some(source.clone()),
+                    claim,
+                );
+            }
+        });
+    }
+    if let Some(window) = return_to {
+        editor.connect_closed(move || window.present());
+    }
+    editor.show();
+    Ok(())
+}
+
+// A capture over max_editor_megapixels, e.g. from a wall of monitors, may
+// not fit in memory at full size; ask before editing a smaller copy of it
+fn offer_downscale(
+    app: &Application,
+    png_data: Vec<u8>,
+    monitor_index: Option<u32>,
+    return_to: Option<ApplicationWindow>,
+    recrop_source: Option<Rc<image::DynamicImage>>,
+    recovery_capture: u64,
+) {
+    let dialog = gtk4::MessageDialog::builder()
+        .modal(true)
+        .text("Edit a Smaller Copy?")
+        .secondary_text(
+            "This screenshot is very large and may make the editor slow or run \
+             out of memory. Edit a downscaled copy instead? Saving and copying \
+             still use the full resolution.",
+        )
+        .buttons(gtk4::ButtonsType::None)
+        .build();
+    if let Some(parent) = return_to.as_ref() {
+        dialog.set_transient_for(Some(parent));
+    }
+    dialog.add_buttons(&[
+        ("Edit Full Size", gtk4::ResponseType::Reject),
+        ("Downscale", gtk4::ResponseType::Accept),
+    ]);
+    dialog.set_default_response(gtk4::ResponseType::Accept);
+
+    let app = app.clone();
+    let png_data = RefCell::new(Some(png_data));
+    let recrop_source = RefCell::new(recrop_source);
+    dialog.connect_response(move |dialog, response| {
+        dialog.close();
+        let Some(png_data) = png_data.borrow_mut().take() else {
+            return;
+        };
+        // Dismissing the dialog is the safe choice
+        let downscale = response != gtk4::ResponseType::Reject;
+        info!(
+            "Opening large capture {}",
+            if downscale {
+                "downscaled"
+            } else {
+                "at full size"
+            }
+        );
+        let result = open_editor(
+            &app,
+            png_data,
+            monitor_index,
+            return_to.clone(),
+            recrop_source.borrow_mut().take(),
+            downscale,
+            recovery_capture,
+        );
+        if let Err(e) = result {
+            error!("Failed to open editor: {}", e);
+            if let Some(ref window) = return_to {
+                window.present();
+                show_error_dialog(window, &format!("Failed to open the editor: {}", e));
+            }
+        }
+    });
+
+    dialog.present();
+}
+
+fn save_directory() -> PathBuf {
+    let configured = &preferences::get().general.save_directory;
+    if !configured.is_empty() {
+        return match configured.strip_prefix("~/") {
+            Some(relative) => glib::home_dir().join(relative),
+            None => PathBuf::from(configured),
+        };
+    }
+
+    glib::user_special_dir(glib::UserDirectory::Pictures)
+        .unwrap_or_else(glib::home_dir)
+        .join("Screenshots")
+}
+
+// Write the capture to the save folder under a timestamped name
+fn save_capture(png_data: &[u8]) -> Result<PathBuf> {
+    let directory = save_directory();
+    std::fs::create_dir_all(&directory)?;
+
+    let format = preferences::get().general.format;
+    let extension = format.extension();
+    let timestamp = glib::DateTime::now_local()?.format("%Y-%m-%d-%H%M%S")?;
+    let mut path = directory.join(format!("flint-{}.{}", timestamp, extension));
+    let mut counter = 1;
+    while path.exists() {
+        counter += 1;
+        path = directory.join(format!("flint-{}-{}.{}", timestamp, counter, extension));
+    }
+
+    // The capture is already a PNG, anything else is encoded from it
+    if format == ExportFormat::Png {
+        std::fs::write(&path, png_data)?;
+    } else {
+        let image = image::load_from_memory(png_data)?.to_rgba8();
+        std::fs::write(&path, AnnotationEditor::encode_image(&image, format)?)?;
+    }
+    info!("Saved screenshot to {}", path.display());
+    Ok(path)
+}
+
+// Small window offering the other actions, for the "ask" setting
+fn show_after_capture_chooser(
+    app: &Application,
+    png_data: Vec<u8>,
+    monitor_index: Option<u32>,
+    return_to: Option<ApplicationWindow>,
+    recrop_source: Option<Rc<image::DynamicImage>>,
+) {
+    let chooser = ApplicationWindow::builder()
+        .application(app)
+        .title("Screenshot Captured")
+        .resizable(false)
+        .build();
+
+    let container = Box::new(Orientation::Vertical, 12);
+    container.set_margin_top(16);
+    container.set_margin_bottom(16);
+    container.set_margin_start(16);
+    container.set_margin_end(16);
+    container.append(&Label::new(Some(
+        "What would you like to do with the screenshot?",
+    )));
+
+    let buttons = Box::new(Orientation::Horizontal, 8);
+    buttons.set_halign(gtk4::Align::Center);
+    container.append(&buttons);
+
+    let png_data = Rc::new(RefCell::new(Some(png_data)));
+    for (label, action) in [
+        ("Edit", AfterCapture::Editor),
+        ("Copy", AfterCapture::Copy),
+        ("Save", AfterCapture::Save),
+    ] {
+        let button = Button::with_label(label);
+        let app = app.clone();
+        let chooser_clone = chooser.clone();
+        let png_data = png_data.clone();
+        let return_to = return_to.clone();
+        let recrop_source = recrop_source.clone();
+        button.connect_clicked(move |_| {
+            let Some(data) = png_data.borrow().clone() else {
+                return;
+            };
+
+            if let Err(e) = run_after_capture(
+                &app,
+                action,
+                data,
+                monitor_index,
+                return_to.clone(),
+                recrop_source.clone(),
+            ) {
+                error!("Failed to handle screenshot: {}", e);
+                show_error_dialog(
+                    &chooser_clone,
+                    &format!("Failed to handle screenshot: {}", e),
+                );
+                return;
+            }
+            // Handled, so closing the chooser no longer means discarding it
+            png_data.borrow_mut().take();
+            chooser_clone.close();
+        });
+        buttons.append(&button);
+    }
+
+    // Closed without picking an action: the screenshot is discarded
+    chooser.connect_close_request(move |_| {
+        if png_data.borrow().is_some() {
+            if let Some(window) = &return_to {
+                window.present();
+            }
+        }
+        glib::Propagation::Proceed
+    });
+
+    // Escape discards the screenshot
+    let key_controller = gtk4::EventControllerKey::new();
+    let chooser_clone = chooser.clone();
+    key_controller.connect_key_pressed(move |_, key, _, _| {
+        if key == gdk4::Key::Escape {
+            chooser_clone.close();
+            glib::Propagation::Stop
+        } else {
+            glib::Propagation::Proceed
+        }
+    });
+    chooser.add_controller(key_controller);
+
+    chooser.set_child(Some(&container));
+    chooser.present();
+}
+
+// Capture the focused window straight away. Flint has the focus while its
+// button is clicked, so the window manager is asked only once the capture
+// window is gone and the focus has gone back to the previous window.
+fn start_active_window_capture(app: Application, parent_window: ApplicationWindow) {
+    let Some(claim) = CaptureClaim::take() else {
+        return;
+    };
+    info!("Capturing the active window");
+    let own_window = window_manager::x11_window_id(&parent_window);
+    parent_window.set_visible(false);
+
+    let hidden_window = parent_window.clone();
+    when_window_hidden(&hidden_window, move || {
+        // Released once the window has been captured or has failed
+        let _claim = claim;
+        let result = window_manager::WindowManager::new().and_then(|window_manager| {
+            let window_info = window_manager.active_window()?;
+            Ok((window_manager, window_info))
+        });
+
+        match result {
+            Ok((_, window_info)) if Some(window_info.id) == own_window => {
+                info!("Flint itself is still the active window");
+                parent_window.set_visible(true);
+                show_error_dialog(
+                    &parent_window,
+                    "No other window has the focus. Focus the window to capture and try again, \
+                     e.g. with --active-window from a keyboard shortcut.",
+                );
+            }
+            Ok((window_manager, window_info)) => proceed_with_window_capture(
+                app,
+                parent_window,
+                &window_info,
+                &window_manager,
+                true,
+                None,
+            ),
+            Err(e) => {
+                error!("Failed to find the active window: {}", e);
+                parent_window.set_visible(true);
+                show_error_dialog(
+                    &parent_window,
+                    &window_error_message("Failed to find the active window", &e),
+                );
+            }
+        }
+    });
+}
+
+// Arm "capture after click": a fullscreen view of the desktop waits for one
+// click, and the window under it is captured straight away
+fn start_click_window_capture(app: Application, parent_window: ApplicationWindow) {
+    let Some(claim) = CaptureClaim::take() else {
+        return;
+    };
+    info!("Arming capture after click");
+    parent_window.set_visible(false);
 
-    let (img_width, img_height) = image.dimensions();
-    info!("Original image dimensions: {}x{}", img_width, img_height);
+    let window_manager = match window_manager::WindowManager::new() {
+        Ok(manager) => Rc::new(manager),
+        Err(e) => {
+            error!("Failed to create window manager: {}", e);
+            show_error_dialog(
+                &parent_window,
+                &window_error_message("Capture after click not available", &e),
+            );
+            parent_window.set_visible(true);
+            return;
+        }
+    };
 
-    // Validate crop bounds
-    let crop_x = x.max(0) as u32;
-    let crop_y = y.max(0) as u32;
-    let crop_width = width.min(img_width as i32 - x).max(1) as u32;
-    let crop_height = height.min(img_height as i32 - y).max(1) as u32;
+    let hidden_window = parent_window.clone();
+    when_window_hidden(&hidden_window, move || {
+        let screen_info = get_screen_info_without_capture();
+        let scale_factor = get_screen_scale_factor();
+        let (preview_surface, _) = capture_current_screen_for_preview_with_data(
+            screen_info.0 * scale_factor,
+            screen_info.1 * scale_factor,
+        );
 
-    if crop_x >= img_width || crop_y >= img_height {
-        return Err(anyhow::anyhow!("Crop region is outside image bounds"));
-    }
+        let overlay_window = ApplicationWindow::builder()
+            .application(&app)
+            .title("Click a Window to Capture")
+            .default_width(screen_info.0)
+            .default_height(screen_info.1)
+            .decorated(false)
+            .build();
+        overlay_window.set_modal(true);
+        overlay_window.set_resizable(false);
+        overlay_window.set_deletable(false);
+        overlay_window.fullscreen();
+        let claim = claim.hold_until_destroyed(&overlay_window);
 
-    info!(
-        "Adjusted crop region: {}x{} at ({}, {})",
-        crop_width, crop_height, crop_x, crop_y
-    );
+        let drawing_area = DrawingArea::new();
+        drawing_area.set_hexpand(true);
+        drawing_area.set_vexpand(true);
+        drawing_area.set_cursor_from_name(Some("crosshair"));
 
-    // Crop the image
-    let cropped = image.crop_imm(crop_x, crop_y, crop_width, crop_height);
+        drawing_area.set_draw_func(move |_, ctx, width, height| {
+            ctx.save().unwrap();
+            ctx.scale(
+                width as f64 / preview_surface.width() as f64,
+                height as f64 / preview_surface.height() as f64,
+            );
+            ctx.set_source_surface(&preview_surface, 0.0, 0.0).unwrap();
+            ctx.source().set_filter(cairo::Filter::Good);
+            ctx.paint().unwrap();
+            ctx.restore().unwrap();
 
-    // Convert back to PNG bytes
-    let mut buffer = Vec::new();
-    cropped
-        .write_to(
-            &mut std::io::Cursor::new(&mut buffer),
-            image::ImageOutputFormat::Png,
-        )
-        .map_err(|e| anyhow::anyhow!("Failed to convert cropped image to PNG: {}", e))?;
+            ctx.set_source_rgba(0.0, 0.0, 0.0, 0.2);
+            ctx.rectangle(0.0, 0.0, width as f64, height as f64);
+            ctx.fill().unwrap();
 
-    info!("Cropped image converted to PNG, {} bytes", buffer.len());
-    Ok(buffer)
-}
+            let instruction_text = "Click a window to capture it • Press Escape to cancel";
+            ctx.select_font_face("Sans", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+            ctx.set_font_size(16.0);
+            let text_extents = ctx.text_extents(instruction_text).unwrap();
 
-fn proceed_with_cropped_screenshot(app: Application, window: ApplicationWindow, png_data: Vec<u8>) {
-    info!(
-        "Opening editor with cropped screenshot ({} bytes)",
-        png_data.len()
-    );
+            ctx.set_source_rgba(0.0, 0.0, 0.0, 0.8);
+            ctx.rectangle(
+                10.0,
+                10.0,
+                text_extents.width() + 20.0,
+                text_extents.height() + 15.0,
+            );
+            ctx.fill().unwrap();
 
-    // Close the capture window
-    window.close();
+            ctx.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+            ctx.move_to(20.0, 30.0);
+            ctx.show_text(instruction_text).unwrap();
+        });
 
-    // Create and show the annotation editor directly
-    match AnnotationEditor::new(&app, png_data) {
-        Ok(editor) => {
-            info!("Editor created successfully");
-            editor.show();
-        }
-        Err(e) => {
-            error!("Failed to create editor: {}", e);
-            show_error_dialog(&window, &format!("Failed to open editor: {}", e));
-        }
-    }
-}
+        // The click only picks a point; the capture runs once the overlay is
+        // gone so it shows the real window, not the preview
+        let gesture_click = gtk4::GestureClick::new();
+        let overlay_click = overlay_window.clone();
+        let parent_click = parent_window.clone();
+        gesture_click.connect_pressed(move |_, _, x, y| {
+            // Taken before the overlay closes, and only by the first click
+            let Some(claim) = claim.borrow_mut().take() else {
+                return;
+            };
+            let point = (
+                (x * scale_factor as f64) as i32,
+                (y * scale_factor as f64) as i32,
+            );
+            info!("Capturing window under click at {:?}", point);
+            overlay_click.close();
+
+            let app = app.clone();
+            let parent_window = parent_click.clone();
+            let window_manager = window_manager.clone();
+            when_window_hidden(&overlay_click, move || {
+                let _claim = claim;
+                match window_manager.window_at_point(point.0, point.1) {
+                    Ok(window_info) => proceed_with_window_capture(
+                        app,
+                        parent_window,
+                        &window_info,
+                        &window_manager,
+                        true,
+                        None,
+                    ),
+                    Err(e) => {
+                        error!("Failed to find the clicked window: {}", e);
+                        parent_window.set_visible(true);
+                        show_error_dialog(
+                            &parent_window,
+                            &window_error_message("Failed to find the clicked window", &e),
+                        );
+                    }
+                }
+            });
+        });
+        drawing_area.add_controller(gesture_click);
 
-fn start_window_selection_capture(app: Application, parent_window: ApplicationWindow) {
-    info!("Starting window selection capture");
+        // Escape disarms and brings the capture window back
+        let key_controller = gtk4::EventControllerKey::new();
+        let overlay_key = overlay_window.clone();
+        let parent_key = parent_window.clone();
+        key_controller.connect_key_pressed(move |_, key, _, _| {
+            if key == gdk4::Key::Escape {
+                info!("Capture after click cancelled");
+                overlay_key.close();
+                parent_key.set_visible(true);
+                glib::Propagation::Stop
+            } else {
+                glib::Propagation::Proceed
+            }
+        });
+        overlay_window.add_controller(key_controller);
 
-    // Hide the parent window
-    parent_window.set_visible(false);
+        overlay_window.set_child(Some(&drawing_area));
+        overlay_window.present();
+    });
+}
 
+// Open the window manager and list the windows to pick from. Errors are shown
+// over the capture window, which is brought back.
+fn list_windows_for_selection(
+    parent_window: &ApplicationWindow,
+) -> Option<(
+    window_manager::WindowManager,
+    Vec<window_manager::WindowInfo>,
+)> {
     // Create window manager
     let window_manager = match window_manager::WindowManager::new() {
         Ok(manager) => manager,
         Err(e) => {
             error!("Failed to create window manager: {}", e);
-            show_error_dialog(&parent_window, &format!("Window selection not available: {}\n\nThis feature requires X11. On Wayland, please use Screen or Selection capture instead.", e));
+            show_error_dialog(
+                parent_window,
+                &window_error_message("Window selection not available", &e),
+            );
             parent_window.set_visible(true);
-            return;
+            return None;
         }
     };
 
@@ -859,19 +3263,234 @@ fn start_window_selection_capture(app: Application, parent_window: ApplicationWi
         Ok(windows) => windows,
         Err(e) => {
             error!("Failed to list windows: {}", e);
-            show_error_dialog(&parent_window, &format!("Failed to enumerate windows: {}\n\nThis feature requires X11. On Wayland, please use Screen or Selection capture instead.", e));
+            show_error_dialog(
+                parent_window,
+                &window_error_message("Failed to enumerate windows", &e),
+            );
             parent_window.set_visible(true);
-            return;
+            return None;
         }
     };
 
     if windows.is_empty() {
-        show_error_dialog(&parent_window, "No capturable windows found. Please ensure you have open windows that are not minimized.");
+        show_error_dialog(parent_window, "No capturable windows found. Please ensure you have open windows that are not minimized.");
+        parent_window.set_visible(true);
+        return None;
+    }
+
+    Some((window_manager, windows))
+}
+
+fn start_window_selection_capture(app: Application, parent_window: ApplicationWindow) {
+    let Some(claim) = CaptureClaim::take() else {
+        return;
+    };
+    info!("Starting window selection capture");
+
+    // Hide the parent window
+    parent_window.set_visible(false);
+
+    if let Some((window_manager, windows)) = list_windows_for_selection(&parent_window) {
+        show_window_selection_dialog(app, parent_window, windows, window_manager, claim);
+    }
+}
+
+fn start_application_capture(app: Application, parent_window: ApplicationWindow) {
+    let Some(claim) = CaptureClaim::take() else {
+        return;
+    };
+    info!("Starting application capture");
+
+    parent_window.set_visible(false);
+
+    let Some((_, windows)) = list_windows_for_selection(&parent_window) else {
+        return;
+    };
+    let applications = window_manager::group_by_class(&windows);
+    if applications.is_empty() {
+        show_error_dialog(&parent_window, "No visible application windows found.");
         parent_window.set_visible(true);
         return;
     }
 
-    show_window_selection_dialog(app, parent_window, windows, window_manager);
+    show_application_selection_dialog(app, parent_window, applications, claim);
+}
+
+// One row per application; capturing takes the screen area covering all of
+// that application's visible windows, e.g. a main window and its inspector
+fn show_application_selection_dialog(
+    app: Application,
+    parent_window: ApplicationWindow,
+    applications: Vec<(String, Vec<window_manager::WindowInfo>)>,
+    claim: CaptureClaim,
+) {
+    info!(
+        "Showing application selection dialog with {} applications",
+        applications.len()
+    );
+
+    let dialog = ApplicationWindow::builder()
+        .application(&app)
+        .title("Select Application to Capture")
+        .default_width(500)
+        .default_height(400)
+        .resizable(true)
+        .modal(true)
+        .build();
+    let claim = claim.hold_until_destroyed(&dialog);
+
+    let main_box = Box::new(Orientation::Vertical, 10);
+    main_box.set_margin_start(20);
+    main_box.set_margin_end(20);
+    main_box.set_margin_top(20);
+    main_box.set_margin_bottom(20);
+
+    let title_label = Label::new(Some("Select an application to capture:"));
+    title_label.add_css_class("title-2");
+    title_label.set_margin_bottom(10);
+
+    let scrolled = ScrolledWindow::new();
+    scrolled.set_policy(PolicyType::Never, PolicyType::Automatic);
+    scrolled.set_vexpand(true);
+
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(SelectionMode::Single);
+    list_box.add_css_class("boxed-list");
+
+    for (class, windows) in &applications {
+        let list_row = ListBoxRow::new();
+        list_row.set_child(Some(&create_application_list_row(class, windows)));
+        list_box.append(&list_row);
+    }
+    scrolled.set_child(Some(&list_box));
+
+    let button_box = Box::new(Orientation::Horizontal, 10);
+    button_box.set_halign(gtk4::Align::End);
+    button_box.set_margin_top(10);
+
+    let cancel_button = Button::with_label("Cancel");
+    let dialog_cancel = dialog.clone();
+    cancel_button.connect_clicked(move |_| dialog_cancel.close());
+
+    let capture_button = Button::with_label("Capture Application");
+    capture_button.add_css_class("suggested-action");
+    capture_button.set_sensitive(false);
+
+    let capture_button_selected = capture_button.clone();
+    list_box.connect_row_selected(move |_, row| {
+        capture_button_selected.set_sensitive(row.is_some());
+    });
+
+    // Double-clicking a row and the capture button both capture it
+    let capture_row: Rc<dyn Fn(usize)> = {
+        let dialog = dialog.clone();
+        let app = app.clone();
+        let parent_window = parent_window.clone();
+        Rc::new(move |index| {
+            let Some((class, windows)) = applications.get(index) else {
+                error!("Failed to get application for index: {}", index);
+                return;
+            };
+            let Some(rect) = window_manager::bounding_box(windows) else {
+                error!("Windows of {} have no area to capture", class);
+                return;
+            };
+            let Some(claim) = claim.borrow_mut().take() else {
+                return;
+            };
+            info!(
+                "Capturing {} window(s) of {} in {}x{} at ({}, {})",
+                windows.len(),
+                class,
+                rect.2,
+                rect.3,
+                rect.0,
+                rect.1
+            );
+
+            // X11 reports device pixels, monitor geometry is logical
+            let scale_factor = get_screen_scale_factor();
+            let monitor_index = monitor_index_at(
+                (rect.0 + rect.2 / 2) / scale_factor,
+                (rect.1 + rect.3 / 2) / scale_factor,
+            );
+
+            // Hide rather than close the dialog, since closing brings the
+            // capture window back; it is destroyed once the screen is taken
+            dialog.set_visible(false);
+            let app = app.clone();
+            let parent_window = parent_window.clone();
+            let hidden_dialog = dialog.clone();
+            when_window_hidden(&dialog, move || {
+                hidden_dialog.destroy();
+                proceed_with_screenshot(app, parent_window, Some(rect), monitor_index, None, claim);
+            });
+        })
+    };
+
+    let capture_row_activate = capture_row.clone();
+    list_box.connect_row_activated(move |_, row| capture_row_activate(row.index() as usize));
+
+    let list_box_capture = list_box.clone();
+    capture_button.connect_clicked(move |_| {
+        if let Some(row) = list_box_capture.selected_row() {
+            capture_row(row.index() as usize);
+        }
+    });
+
+    button_box.append(&cancel_button);
+    button_box.append(&capture_button);
+
+    main_box.append(&title_label);
+    main_box.append(&scrolled);
+    main_box.append(&button_box);
+    dialog.set_child(Some(&main_box));
+
+    let parent_close = parent_window.clone();
+    dialog.connect_close_request(move |_| {
+        parent_close.set_visible(true);
+        glib::Propagation::Proceed
+    });
+
+    dialog.present();
+}
+
+fn create_application_list_row(class: &str, windows: &[window_manager::WindowInfo]) -> Box {
+    let row_box = Box::new(Orientation::Vertical, 4);
+    row_box.set_margin_start(12);
+    row_box.set_margin_end(12);
+    row_box.set_margin_top(8);
+    row_box.set_margin_bottom(8);
+
+    let name = if class.is_empty() || class == "Unknown" {
+        "Unknown application"
+    } else {
+        class
+    };
+    let name_label = Label::new(Some(name));
+    name_label.set_halign(gtk4::Align::Start);
+    name_label.add_css_class("heading");
+
+    // The window titles tell apart applications that share a class name
+    let titles = windows
+        .iter()
+        .map(|window| window.title.replace('\0', ""))
+        .collect::<Vec<_>>()
+        .join(" • ");
+    let details_label = Label::new(Some(&format!(
+        "{} window{} • {}",
+        windows.len(),
+        if windows.len() == 1 { "" } else { "s" },
+        titles
+    )));
+    details_label.set_halign(gtk4::Align::Start);
+    details_label.add_css_class("dim-label");
+    details_label.add_css_class("caption");
+    details_label.set_ellipsize(pango::EllipsizeMode::End);
+
+    row_box.append(&name_label);
+    row_box.append(&details_label);
+    row_box
 }
 
 fn show_window_selection_dialog(
@@ -879,6 +3498,7 @@ fn show_window_selection_dialog(
     parent_window: ApplicationWindow,
     windows: Vec<window_manager::WindowInfo>,
     window_manager: window_manager::WindowManager,
+    claim: CaptureClaim,
 ) {
     info!(
         "Showing window selection dialog with {} windows",
@@ -894,6 +3514,7 @@ fn show_window_selection_dialog(
         .resizable(true)
         .modal(true)
         .build();
+    let claim = claim.hold_until_destroyed(&dialog);
 
     // Create main container
     let main_box = Box::new(Orientation::Vertical, 10);
@@ -917,6 +3538,9 @@ fn show_window_selection_dialog(
     list_box.set_selection_mode(SelectionMode::Single);
     list_box.add_css_class("boxed-list");
 
+    // A single click only selects; double-click or Enter activates
+    list_box.set_activate_on_single_click(false);
+
     // Populate list with windows
     for window_info in &windows {
         let row_widget = create_window_list_row(window_info);
@@ -929,6 +3553,10 @@ fn show_window_selection_dialog(
 
     scrolled.set_child(Some(&list_box));
 
+    // Frame with title bar and borders, or just the application's content
+    let decorations_check = gtk4::CheckButton::with_label("Include window decorations");
+    decorations_check.set_active(true);
+
     // Button container
     let button_box = Box::new(Orientation::Horizontal, 10);
     button_box.set_halign(gtk4::Align::End);
@@ -961,12 +3589,14 @@ fn show_window_selection_dialog(
         }
     });
 
-    // Add double-click activation for immediate capture
+    // Double-click or Enter captures right away, with the activate action
     let dialog_clone_activate = dialog.clone();
     let parent_clone_activate = parent_window.clone();
     let app_clone_activate = app.clone();
     let windows_clone_activate = windows.clone();
     let window_manager_activate = window_manager_clone.clone();
+    let decorations_check_activate = decorations_check.clone();
+    let claim_activate = claim.clone();
 
     list_box.connect_row_activated(move |_, activated_row| {
         let window_index = activated_row.index() as usize;
@@ -975,6 +3605,10 @@ fn show_window_selection_dialog(
                 "Window row activated (double-clicked): {} (ID: {})",
                 window_info.title, window_info.id
             );
+            // Held through the capture, past closing the dialog
+            let Some(_claim) = claim_activate.borrow_mut().take() else {
+                return;
+            };
 
             // Close the dialog
             dialog_clone_activate.close();
@@ -983,8 +3617,10 @@ fn show_window_selection_dialog(
             proceed_with_window_capture(
                 app_clone_activate.clone(),
                 parent_clone_activate.clone(),
-                window_info.id,
+                window_info,
                 window_manager_activate.as_ref(),
+                decorations_check_activate.is_active(),
+                preferences::get().capture.window_activate_action,
             );
         }
     });
@@ -996,6 +3632,8 @@ fn show_window_selection_dialog(
     let list_box_clone = list_box.clone();
     let windows_clone = windows.clone();
     let window_manager_capture = window_manager_clone.clone();
+    let decorations_check_capture = decorations_check.clone();
+    let claim_capture = claim.clone();
 
     capture_button.connect_clicked(move |_| {
         if let Some(selected_row) = list_box_clone.selected_row() {
@@ -1005,6 +3643,9 @@ fn show_window_selection_dialog(
                     "Capturing window: {} (ID: {})",
                     window_info.title, window_info.id
                 );
+                let Some(_claim) = claim_capture.borrow_mut().take() else {
+                    return;
+                };
 
                 // Close the dialog
                 dialog_clone.close();
@@ -1013,8 +3654,10 @@ fn show_window_selection_dialog(
                 proceed_with_window_capture(
                     app_clone.clone(),
                     parent_clone.clone(),
-                    window_info.id,
+                    window_info,
                     window_manager_capture.as_ref(),
+                    decorations_check_capture.is_active(),
+                    None,
                 );
             } else {
                 error!("Failed to get window info for index: {}", window_index);
@@ -1024,13 +3667,71 @@ fn show_window_selection_dialog(
         }
     });
 
+    // Capture the whole screen with the selected window blurred out
+    let redact_button = Button::with_label("Screen Without Window");
+    redact_button.set_tooltip_text(Some(
+        "Capture the whole screen with this window's area blurred",
+    ));
+    redact_button.set_sensitive(false);
+
+    let redact_button_selected = redact_button.clone();
+    list_box.connect_row_selected(move |_, row| {
+        redact_button_selected.set_sensitive(row.is_some());
+    });
+
+    let dialog_redact = dialog.clone();
+    let parent_redact = parent_window.clone();
+    let app_redact = app.clone();
+    let list_box_redact = list_box.clone();
+    let windows_redact = windows.clone();
+    let claim_redact = claim.clone();
+
+    redact_button.connect_clicked(move |_| {
+        let Some(window_info) = list_box_redact
+            .selected_row()
+            .and_then(|row| windows_redact.get(row.index() as usize))
+        else {
+            error!("No window selected");
+            return;
+        };
+        let Some(claim) = claim_redact.borrow_mut().take() else {
+            return;
+        };
+        info!(
+            "Capturing screen without window: {} (ID: {})",
+            window_info.title, window_info.id
+        );
+
+        // Window geometry is already in root coordinates, in device pixels
+        // like the full-screen capture
+        let rect = (
+            window_info.x,
+            window_info.y,
+            window_info.width,
+            window_info.height,
+        );
+
+        // Hide rather than close the dialog, since closing brings the capture
+        // window back; it is destroyed once the screen has been captured
+        dialog_redact.set_visible(false);
+        let app = app_redact.clone();
+        let parent_window = parent_redact.clone();
+        let dialog = dialog_redact.clone();
+        when_window_hidden(&dialog_redact, move || {
+            dialog.destroy();
+            proceed_with_screenshot(app, parent_window, None, None, Some(rect), claim);
+        });
+    });
+
     // Add buttons to container
     button_box.append(&cancel_button);
+    button_box.append(&redact_button);
     button_box.append(&capture_button);
 
     // Add all elements to main container
     main_box.append(&title_label);
     main_box.append(&scrolled);
+    main_box.append(&decorations_check);
     main_box.append(&button_box);
 
     dialog.set_child(Some(&main_box));
@@ -1105,12 +3806,27 @@ fn create_window_list_row(window_info: &window_manager::WindowInfo) -> Box {
 fn proceed_with_window_capture(
     app: Application,
     parent_window: ApplicationWindow,
-    window_id: u64,
+    window_info: &window_manager::WindowInfo,
     window_manager: &window_manager::WindowManager,
+    include_decorations: bool,
+    // Post-capture action in place of after_capture, if any
+    action: Option<AfterCapture>,
 ) {
+    // The frame holds the title bar and borders around the client window
+    let window_id = if include_decorations {
+        window_info.frame_id
+    } else {
+        window_info.id
+    };
+    history::clear();
     info!(
-        "Proceeding with window capture for window ID: {}",
-        window_id
+        "Proceeding with window capture for window ID: {} ({} decorations)",
+        window_id,
+        if include_decorations {
+            "with"
+        } else {
+            "without"
+        }
     );
 
     // Capture the window immediately - no need for async delay
@@ -1118,26 +3834,73 @@ fn proceed_with_window_capture(
         Ok(png_data) => {
             info!("Window captured successfully, {} bytes", png_data.len());
 
-            // Close the parent window
-            parent_window.close();
+            dismiss_capture_window(&parent_window);
+            show_capture_feedback(
+                &app,
+                Some((
+                    window_info.x,
+                    window_info.y,
+                    window_info.width as i32,
+                    window_info.height as i32,
+                )),
+            );
 
-            // Open the editor with the captured window
-            match AnnotationEditor::new(&app, png_data) {
-                Ok(editor) => {
-                    info!("Editor created successfully for window capture");
-                    editor.show();
-                }
-                Err(e) => {
-                    error!("Failed to create editor: {}", e);
-                    show_error_dialog(&parent_window, &format!("Failed to open editor: {}", e));
-                    parent_window.set_visible(true);
-                }
+            // Open the editor on the monitor showing the captured window.
+            // X11 reports device pixels, monitor geometry is logical.
+            let scale_factor = get_screen_scale_factor();
+            let monitor_index = monitor_index_at(
+                (window_info.x + window_info.width as i32 / 2) / scale_factor,
+                (window_info.y + window_info.height as i32 / 2) / scale_factor,
+            );
+
+            if let Err(e) = handle_capture_as(
+                &app,
+                Some(&parent_window),
+                png_data,
+                monitor_index,
+                action,
+                None,
+            ) {
+                error!("Failed to handle window capture: {}", e);
+                show_error_dialog(
+                    &parent_window,
+                    &format!("Failed to handle screenshot: {}", e),
+                );
+                parent_window.set_visible(true);
             }
         }
         Err(e) => {
             error!("Failed to capture window: {}", e);
-            show_error_dialog(&parent_window, &format!("Failed to capture window: {}\n\nTip: Make sure the target window is visible and not minimized.", e));
+            show_error_dialog(
+                &parent_window,
+                &window_error_message("Failed to capture window", &e),
+            );
             parent_window.set_visible(true);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_claim_is_held_until_dropped() {
+        let claim = CaptureClaim::take().expect("nothing holds the claim yet");
+
+        // A second capture is refused while the first is running
+        assert!(CaptureClaim::take().is_none());
+        assert!(CaptureClaim::take().is_none());
+
+        drop(claim);
+        let next = CaptureClaim::take();
+        assert!(next.is_some());
+
+        // Handing the claim on through a slot keeps it held
+        let slot = Rc::new(RefCell::new(next));
+        let handed_on = slot.borrow_mut().take();
+        assert!(CaptureClaim::take().is_none());
+        drop(handed_on);
+        assert!(CaptureClaim::take().is_some());
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::{anyhow, Result};
+use gtk4::gio;
+use gtk4::prelude::*;
+use log::{info, warn};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+// Exports handed to another application stay around for this long. Launching
+// through GIO doesn't tell us when the application is done with the file, so
+// old ones are removed the next time something is opened instead.
+const KEEP_FOR: Duration = Duration::from_secs(60 * 60);
+
+fn export_dir() -> PathBuf {
+    glib::tmp_dir().join("flint-open-with")
+}
+
+// Installed applications that can open a PNG, in the order GIO lists them
+pub fn image_apps() -> Vec<gio::AppInfo> {
+    gio::AppInfo::all_for_type("image/png")
+        .into_iter()
+        .filter(|app| app.should_show())
+        .collect()
+}
+
+fn remove_stale_exports() {
+    let Ok(entries) = fs::read_dir(export_dir()) else {
+        return;
+    };
+
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > KEEP_FOR);
+        if stale {
+            if let Err(e) = fs::remove_file(entry.path()) {
+                warn!("Failed to remove {}: {}", entry.path().display(), e);
+            }
+        }
+    }
+}
+
+// Write the export to a temporary file and open it in `app`
+pub fn open(png_data: &[u8], app: &gio::AppInfo) -> Result<()> {
+    remove_stale_exports();
+
+    let directory = export_dir();
+    fs::create_dir_all(&directory)?;
+    let timestamp = glib::DateTime::now_local()?.format("%Y-%m-%d-%H%M%S")?;
+    let mut path = directory.join(format!("flint-{}.png", timestamp));
+    let mut counter = 1;
+    while path.exists() {
+        counter += 1;
+        path = directory.join(format!("flint-{}-{}.png", timestamp, counter));
+    }
+    fs::write(&path, png_data)?;
+
+    let context = gdk4::Display::default().map(|display| display.app_launch_context());
+    app.launch(&[gio::File::for_path(&path)], context.as_ref())
+        .map_err(|e| anyhow!("Failed to launch {}: {}", app.name(), e))?;
+
+    info!("Opened {} in {}", path.display(), app.name());
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use anyhow::{anyhow, Result};
+use gtk4::prelude::*;
+use gtk4::{Application, ApplicationWindow};
+use log::{info, warn};
+
+use crate::window_manager;
+
+// Show a capture in a small undecorated window that stays above the others,
+// to keep it in view as a reference. It is dragged by the image and closed
+// with Escape. `scale_factor` is the display's, so the image appears at the
+// size it had on screen.
+pub fn show(app: &Application, png_data: &[u8], scale_factor: i32) -> Result<()> {
+    let image = image::load_from_memory(png_data)
+        .map_err(|e| anyhow!("Failed to load image to pin: {}", e))?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    let texture = gdk4::MemoryTexture::new(
+        width as i32,
+        height as i32,
+        gdk4::MemoryFormat::R8g8b8a8,
+        &glib::Bytes::from_owned(image.into_raw()),
+        width as usize * 4,
+    );
+
+    let picture = gtk4::Picture::for_paintable(&texture);
+    picture.set_can_shrink(true);
+    let scale_factor = scale_factor.max(1);
+    picture.set_size_request(width as i32 / scale_factor, height as i32 / scale_factor);
+
+    // Dragging anywhere on the image moves the window
+    let handle = gtk4::WindowHandle::new();
+    handle.set_child(Some(&picture));
+
+    let window = ApplicationWindow::builder()
+        .application(app)
+        .title("Pinned Screenshot")
+        .decorated(false)
+        .resizable(false)
+        .child(&handle)
+        .build();
+    window.set_tooltip_text(Some("Drag to move, Escape to close"));
+
+    let key_controller = gtk4::EventControllerKey::new();
+    let window_key = window.clone();
+    key_controller.connect_key_pressed(move |_, key, _, _| {
+        if key == gdk4::Key::Escape {
+            window_key.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    });
+    window.add_controller(key_controller);
+
+    // GTK 4 has no keep-above of its own; on X11 the window manager is asked
+    // directly once the window exists
+    window.connect_map(|window| {
+        let Some(window_id) = window_manager::x11_window_id(window) else {
+            info!("Pinned window cannot be kept on top in this session");
+            return;
+        };
+        let result =
+            window_manager::WindowManager::new().and_then(|manager| manager.keep_above(window_id));
+        if let Err(e) = result {
+            warn!("Failed to keep pinned window on top: {}", e);
+        }
+    });
+
+    window.present();
+    info!("Pinned {}x{} capture", width, height);
+    Ok(())
+}
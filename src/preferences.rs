@@ -0,0 +1,463 @@
+use log::{info, warn};
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+static PREFERENCES: OnceLock<Preferences> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub general: GeneralPreferences,
+    pub capture: CapturePreferences,
+    pub ui: UiPreferences,
+    pub pencil: PencilPreferences,
+    pub highlighter: HighlighterPreferences,
+    pub upload: UploadPreferences,
+    pub watermark: WatermarkPreferences,
+    pub caption: CaptionPreferences,
+    pub keyboard: KeyboardPreferences,
+    pub privacy: PrivacyPreferences,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GeneralPreferences {
+    // Quality for JPEG, WebP and AVIF saves (1-100)
+    pub jpeg_quality: u8,
+
+    // Compression level for PNG saves
+    pub png_compression: PngCompression,
+
+    // Format offered first when saving, and used by the save action
+    pub format: ExportFormat,
+
+    // Format of copied images where the clipboard can take it
+    pub copy_format: ExportFormat,
+
+    // What to do with a screenshot once it has been captured
+    pub after_capture: AfterCapture,
+
+    // Folder for screenshots saved straight after capture; empty uses
+    // Pictures/Screenshots
+    pub save_directory: String,
+
+    // Bring the capture window back after each capture instead of quitting
+    pub keep_open: bool,
+
+    // Initial state of the editor's carry-over toggle, which starts the next
+    // capture of the same size with the annotations of this one
+    pub carry_over_annotations: bool,
+}
+
+impl Default for GeneralPreferences {
+    fn default() -> Self {
+        Self {
+            jpeg_quality: 90,
+            png_compression: PngCompression::Default,
+            format: ExportFormat::Png,
+            copy_format: ExportFormat::Png,
+            after_capture: AfterCapture::Editor,
+            save_directory: String::new(),
+            keep_open: false,
+            carry_over_annotations: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Png,
+    #[serde(alias = "jpg")]
+    Jpeg,
+    Webp,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "image/png",
+            ExportFormat::Jpeg => "image/jpeg",
+            ExportFormat::Webp => "image/webp",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(ExportFormat::Png),
+            "jpg" | "jpeg" => Some(ExportFormat::Jpeg),
+            "webp" => Some(ExportFormat::Webp),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterCapture {
+    #[default]
+    Editor,
+    Copy,
+    Save,
+    Ask,
+}
+
+impl std::str::FromStr for AfterCapture {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "editor" => Ok(Self::Editor),
+            "copy" => Ok(Self::Copy),
+            "save" => Ok(Self::Save),
+            "ask" => Ok(Self::Ask),
+            _ => Err(format!(
+                "Unknown action '{}': expected editor, copy, save or ask",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CapturePreferences {
+    // Briefly flash the captured area after a successful capture
+    pub flash_on_capture: bool,
+
+    // Play a shutter sound after a successful capture
+    pub shutter_sound: bool,
+
+    // Show a faint ghost of the previous capture while selecting a region
+    pub onion_skin: bool,
+
+    // Draw the region selection as a crisp 1px hairline on pixel centers
+    pub crisp_selection_border: bool,
+
+    // Heavier dimming, a bright outlined border and a full-screen crosshair
+    pub high_contrast_selection: bool,
+
+    // Invert the selected region for a moment before it is captured
+    pub flash_selection: bool,
+
+    // Smallest region width/height, in pixels, that a selection may have
+    pub min_selection_size: u32,
+
+    // Also show the selection's physical size in millimetres
+    pub show_physical_size: bool,
+
+    // DPI used for physical sizes; 0 uses the monitor's reported size
+    pub reference_dpi: f64,
+
+    // Longest time to wait for Flint's window to disappear before capturing
+    pub hide_timeout_ms: u64,
+
+    // Who draws the region selector: Flint's overlay or the compositor
+    pub region_selector: RegionSelector,
+
+    // Seconds the delayed captures wait, leaving time to open a menu
+    pub capture_delay: u32,
+
+    // Post-capture action for a window picked by double-click or Enter;
+    // unset follows after_capture
+    pub window_activate_action: Option<AfterCapture>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionSelector {
+    #[default]
+    Flint,
+    Portal,
+    // slurp draws the selection and grim captures it, for wlroots compositors
+    Slurp,
+}
+
+impl Default for CapturePreferences {
+    fn default() -> Self {
+        Self {
+            flash_on_capture: true,
+            shutter_sound: false,
+            onion_skin: false,
+            crisp_selection_border: false,
+            high_contrast_selection: false,
+            flash_selection: false,
+            min_selection_size: 10,
+            show_physical_size: false,
+            reference_dpi: 0.0,
+            hide_timeout_ms: 1000,
+            region_selector: RegionSelector::Flint,
+            capture_delay: 5,
+            window_activate_action: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UiPreferences {
+    // What the editor draws behind and around the screenshot
+    pub editor_background: EditorBackground,
+
+    // Color of the solid background, and of the area around the image with
+    // the checkerboard, as a hex string
+    pub editor_background_color: String,
+
+    // Largest capture, in megapixels, edited at full size; 0 disables the
+    // limit
+    pub max_editor_megapixels: f64,
+
+    // Colors offered in the toolbar, in order; empty uses the built-in ones
+    pub palette: Vec<PaletteColor>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaletteColor {
+    pub name: String,
+
+    // Any CSS color, e.g. "#e01b24"
+    pub color: String,
+}
+
+impl Default for UiPreferences {
+    fn default() -> Self {
+        Self {
+            editor_background: EditorBackground::Gradient,
+            editor_background_color: "#262d35".to_string(),
+            max_editor_megapixels: 64.0,
+            palette: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditorBackground {
+    #[default]
+    Gradient,
+    Solid,
+    // Solid around the image, checkerboard behind it so transparent
+    // pixels show as transparent
+    Checkerboard,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PencilPreferences {
+    // How far freehand strokes trail the pointer (0.0-0.9); 0 is off
+    pub stabilizer: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HighlighterPreferences {
+    // How highlighter strokes combine with what is underneath
+    pub blend_mode: HighlighterBlend,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlighterBlend {
+    // Darkens like ink, so text underneath stays readable
+    #[default]
+    Multiply,
+    // Plain alpha blending, which washes out dark text
+    Normal,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct UploadPreferences {
+    // Endpoint that accepts a multipart image upload; empty disables uploading
+    pub url: String,
+
+    // Optional header sent with the upload, e.g. "Authorization: Bearer <token>"
+    pub auth_header: String,
+
+    // Name of the multipart form field carrying the image
+    pub field_name: String,
+}
+
+impl Default for UploadPreferences {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            auth_header: String::new(),
+            field_name: "file".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WatermarkPreferences {
+    // Footer text; empty leaves it out
+    pub text: String,
+
+    // Logo image file; empty leaves it out
+    pub image: String,
+
+    // Corner or center of the export the watermark sits in
+    pub position: WatermarkPosition,
+
+    // 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f64,
+
+    // Text size in export pixels
+    pub font_size: f64,
+
+    // Initial state of the watermark toggle in the save dialog
+    pub enabled: bool,
+
+    // Also watermark images copied to the clipboard
+    pub on_copy: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl Default for WatermarkPreferences {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            image: String::new(),
+            position: WatermarkPosition::BottomRight,
+            opacity: 0.8,
+            font_size: 18.0,
+            enabled: true,
+            on_copy: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CaptionPreferences {
+    // Text written after the date and time; the save dialog starts with it
+    pub text: String,
+
+    // strftime-style format of the date and time
+    pub timestamp_format: String,
+
+    // Text size in export pixels; the bar is sized to fit it
+    pub font_size: f64,
+
+    // Bar and text colors, as CSS colors
+    pub background: String,
+    pub foreground: String,
+
+    // Initial state of the caption bar toggle in the save dialog
+    pub enabled: bool,
+}
+
+impl Default for CaptionPreferences {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            timestamp_format: "%Y-%m-%d %H:%M:%S".to_string(),
+            font_size: 16.0,
+            background: "#202020".to_string(),
+            foreground: "#ffffff".to_string(),
+            enabled: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KeyboardPreferences {
+    // GDK name of the key that starts a stroke at the pointer and commits
+    // it when pressed again; empty turns this off
+    pub stroke_key: String,
+}
+
+impl Default for KeyboardPreferences {
+    fn default() -> Self {
+        Self {
+            stroke_key: "Return".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PrivacyPreferences {
+    // SeetaFace frontal model used by "Blur Faces"; empty turns it off
+    pub face_model: String,
+}
+
+impl Preferences {
+    pub fn config_path() -> Option<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(config_dir.join("flint").join("flint.toml"))
+    }
+
+    pub fn load() -> Self {
+        let path = match Self::config_path() {
+            Some(path) => path,
+            None => {
+                warn!("Could not determine config directory, using default preferences");
+                return Self::default();
+            }
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                info!("No preferences at {}, using defaults", path.display());
+                return Self::default();
+            }
+        };
+
+        match toml::from_str(&contents) {
+            Ok(preferences) => {
+                info!("Loaded preferences from {}", path.display());
+                preferences
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to parse preferences at {}: {}, using defaults",
+                    path.display(),
+                    e
+                );
+                Self::default()
+            }
+        }
+    }
+}
+
+// Preferences are read once from disk and shared for the rest of the session
+pub fn get() -> &'static Preferences {
+    PREFERENCES.get_or_init(Preferences::load)
+}
@@ -0,0 +1,278 @@
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::tools::{DrawingStroke, ToolType};
+
+pub const PROJECT_EXTENSION: &str = "flint";
+
+// Bumped whenever the layout changes in a way older versions cannot read
+const PROJECT_VERSION: u32 = 1;
+
+// An editing session that can be reopened later: the unannotated screenshot
+// plus every finished stroke, stored as JSON
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    version: u32,
+    #[serde(with = "base64_bytes")]
+    pub image_png: Vec<u8>,
+    pub strokes: Vec<DrawingStroke>,
+}
+
+impl Project {
+    pub fn new(image_png: Vec<u8>, strokes: Vec<DrawingStroke>) -> Self {
+        Self {
+            version: PROJECT_VERSION,
+            image_png,
+            strokes,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json =
+            serde_json::to_vec(self).map_err(|e| anyhow!("Failed to serialize project: {}", e))?;
+        std::fs::write(path, json)?;
+
+        info!(
+            "Saved project with {} annotations to {}",
+            self.strokes.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read(path)?;
+        let project: Self =
+            serde_json::from_slice(&json).map_err(|e| anyhow!("Not a Flint project: {}", e))?;
+
+        if project.version > PROJECT_VERSION {
+            return Err(anyhow!(
+                "Project was saved by a newer version of Flint (format {})",
+                project.version
+            ));
+        }
+
+        info!(
+            "Loaded project with {} annotations from {}",
+            project.strokes.len(),
+            path.display()
+        );
+        Ok(project)
+    }
+}
+
+// One annotation in the JSON sidecar written next to an export. Unlike a
+// project this is flat and meant for other tools, e.g. test pipelines
+// checking that annotations landed where expected.
+#[derive(Serialize)]
+struct SidecarAnnotation<'a> {
+    tool: ToolType,
+    // [x, y] in pixels of the exported image
+    points: Vec<[f64; 2]>,
+    // [r, g, b, a], each 0.0 to 1.0
+    color: [f32; 4],
+    thickness: f64,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'a str>,
+}
+
+// Write the visible strokes to `<image path>.json`, with coordinates
+// multiplied by `scale` to match the exported image
+pub fn write_sidecar(image_path: &Path, strokes: &[DrawingStroke], scale: f64) -> Result<PathBuf> {
+    let annotations: Vec<_> = strokes
+        .iter()
+        .filter(|stroke| stroke.visible)
+        .map(|stroke| SidecarAnnotation {
+            tool: stroke.tool_type,
+            points: stroke
+                .points
+                .iter()
+                .map(|point| [point.x * scale, point.y * scale])
+                .collect(),
+            color: [
+                stroke.color.red(),
+                stroke.color.green(),
+                stroke.color.blue(),
+                stroke.color.alpha(),
+            ],
+            thickness: stroke.thickness * scale,
+            text: &stroke.text,
+            note: stroke.note.as_deref(),
+        })
+        .collect();
+
+    let mut path = image_path.as_os_str().to_owned();
+    path.push(".json");
+    let path = PathBuf::from(path);
+
+    let json = serde_json::to_vec_pretty(&annotations)
+        .map_err(|e| anyhow!("Failed to serialize annotations: {}", e))?;
+    std::fs::write(&path, json)?;
+
+    info!(
+        "Wrote {} annotation coordinates to {}",
+        annotations.len(),
+        path.display()
+    );
+    Ok(path)
+}
+
+// Binary data is embedded in the JSON as base64 text
+mod base64_bytes {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        BASE64.decode(text).map_err(serde::de::Error::custom)
+    }
+}
+
+// gdk4::RGBA has no serde support, so colors are stored as [r, g, b, a]
+pub mod rgba {
+    use gdk4::RGBA;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &RGBA, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.red(), color.green(), color.blue(), color.alpha()].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RGBA, D::Error> {
+        let [red, green, blue, alpha] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(RGBA::new(red, green, blue, alpha))
+    }
+}
+
+// Stamp images are stored as base64 PNGs
+pub mod stamp_surface {
+    use super::*;
+    use cairo::ImageSurface;
+    use serde::{Deserializer, Serializer};
+
+    use crate::editor::AnnotationEditor;
+    use crate::stamps;
+
+    pub fn serialize<S: Serializer>(
+        surface: &Option<ImageSurface>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let png = match surface {
+            Some(surface) => {
+                let image = AnnotationEditor::surface_to_rgba(surface)
+                    .map_err(serde::ser::Error::custom)?;
+                let mut png = Vec::new();
+                image
+                    .write_to(
+                        &mut std::io::Cursor::new(&mut png),
+                        image::ImageOutputFormat::Png,
+                    )
+                    .map_err(serde::ser::Error::custom)?;
+                Some(BASE64.encode(png))
+            }
+            None => None,
+        };
+        png.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<ImageSurface>, D::Error> {
+        let png = match Option::<String>::deserialize(deserializer)? {
+            Some(text) => BASE64.decode(text).map_err(serde::de::Error::custom)?,
+            None => return Ok(None),
+        };
+        let image = image::load_from_memory(&png)
+            .map_err(serde::de::Error::custom)?
+            .to_rgba8();
+        stamps::surface_from_image(&image)
+            .map(Some)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Point;
+    use gdk4::RGBA;
+
+    #[test]
+    fn project_survives_a_save_and_load() {
+        let mut arrow = DrawingStroke::new(ToolType::Arrow, RGBA::new(0.0, 0.5, 1.0, 1.0), 4.0);
+        arrow.add_point(Point::new(10.0, 20.0));
+        arrow.add_point(Point::new(110.5, 80.25));
+        arrow.note = Some("check this".to_string());
+        let mut text = DrawingStroke::new(ToolType::Text, RGBA::new(1.0, 0.0, 0.0, 1.0), 3.0);
+        text.add_point(Point::new(30.0, 40.0));
+        text.text = "Hello\nworld".to_string();
+        text.visible = false;
+        let image_png = vec![0x89, b'P', b'N', b'G', 0, 1, 2, 255];
+
+        let path = std::env::temp_dir().join(format!(
+            "flint-test-{}.{}",
+            std::process::id(),
+            PROJECT_EXTENSION
+        ));
+        Project::new(image_png.clone(), vec![arrow, text])
+            .save(&path)
+            .unwrap();
+        let project = Project::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(project.image_png, image_png);
+        assert_eq!(project.strokes.len(), 2);
+
+        let arrow = &project.strokes[0];
+        assert_eq!(arrow.tool_type, ToolType::Arrow);
+        assert_eq!(arrow.thickness, 4.0);
+        let points: Vec<_> = arrow
+            .points
+            .iter()
+            .map(|point| (point.x, point.y))
+            .collect();
+        assert_eq!(points, [(10.0, 20.0), (110.5, 80.25)]);
+        assert_eq!(
+            [
+                arrow.color.red(),
+                arrow.color.green(),
+                arrow.color.blue(),
+                arrow.color.alpha()
+            ],
+            [0.0, 0.5, 1.0, 1.0]
+        );
+        assert_eq!(arrow.note.as_deref(), Some("check this"));
+
+        let text = &project.strokes[1];
+        assert_eq!(text.tool_type, ToolType::Text);
+        assert_eq!(text.text, "Hello\nworld");
+        assert!(!text.visible);
+    }
+
+    #[test]
+    fn projects_from_newer_versions_are_refused() {
+        let path = std::env::temp_dir().join(format!(
+            "flint-test-newer-{}.{}",
+            std::process::id(),
+            PROJECT_EXTENSION
+        ));
+        let json = format!(
+            r#"{{"version": {}, "image_png": "", "strokes": []}}"#,
+            PROJECT_VERSION + 1
+        );
+        std::fs::write(&path, json).unwrap();
+        let result = Project::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
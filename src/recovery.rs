@@ -0,0 +1,76 @@
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::cell::Cell;
+use std::fs;
+use std::path::PathBuf;
+
+// The raw capture handed to the editor is kept in
+// $XDG_CACHE_HOME/flint/last-capture.png (~/.cache/flint/last-capture.png)
+// until it has been saved or dismissed, so an editor that fails to open or
+// goes down doesn't take a hard-to-repeat screenshot with it
+fn recovery_path() -> Option<PathBuf> {
+    let cache_dir = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+
+    Some(cache_dir.join("flint").join("last-capture.png"))
+}
+
+thread_local! {
+    // Number of the capture the copy was last written for, counting from 1.
+    // Several editors can be open at once but only the latest capture is
+    // kept, so an older one must not remove it.
+    static STORED_CAPTURE: Cell<u64> = Cell::new(0);
+}
+
+// Keep a copy of the capture and return its number for release. Failing to
+// is logged but never stops the capture itself.
+pub fn store(png_data: &[u8]) -> u64 {
+    let capture = STORED_CAPTURE.with(|stored| {
+        stored.set(stored.get() + 1);
+        stored.get()
+    });
+    let Some(path) = recovery_path() else {
+        return capture;
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, png_data));
+    match result {
+        Ok(_) => info!("Kept a recovery copy of the capture at {}", path.display()),
+        Err(e) => warn!("Failed to write recovery copy {}: {}", path.display(), e),
+    }
+    capture
+}
+
+// The capture left behind by an earlier run, if there is one
+pub fn pending() -> Option<PathBuf> {
+    recovery_path().filter(|path| path.is_file())
+}
+
+pub fn load() -> Result<Vec<u8>> {
+    let path = pending().ok_or_else(|| anyhow!("No capture to recover"))?;
+    fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))
+}
+
+// Remove the copy once the capture is saved or its editor closed, unless a
+// later capture has replaced it
+pub fn release(capture: u64) {
+    if STORED_CAPTURE.with(Cell::get) == capture {
+        discard();
+    }
+}
+
+pub fn discard() {
+    let Some(path) = pending() else {
+        return;
+    };
+
+    match fs::remove_file(&path) {
+        Ok(_) => info!("Removed recovery copy {}", path.display()),
+        Err(e) => warn!("Failed to remove recovery copy {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{anyhow, Result};
+use cairo::{Context, Format, ImageSurface};
+use log::{info, warn};
+use std::f64::consts::PI;
+use std::path::PathBuf;
+
+use crate::capture;
+
+// Stamps larger than this are scaled down when loaded
+const MAX_STAMP_SIZE: u32 = 256;
+
+// Size of the built-in stamps
+const BUILTIN_STAMP_SIZE: i32 = 64;
+
+#[derive(Debug, Clone)]
+pub struct Stamp {
+    pub name: String,
+    pub surface: ImageSurface,
+}
+
+// User stamps live in $XDG_DATA_HOME/flint/stamps (~/.local/share/flint/stamps)
+pub fn stamps_dir() -> Option<PathBuf> {
+    let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".local")
+            .join("share"),
+    };
+
+    Some(data_dir.join("flint").join("stamps"))
+}
+
+// Built-in stamps first, followed by any PNGs in the user stamps directory
+pub fn load_stamps() -> Vec<Stamp> {
+    let mut stamps = builtin_stamps();
+
+    let dir = match stamps_dir() {
+        Some(dir) => dir,
+        None => return stamps,
+    };
+
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            info!("No user stamps at {}", dir.display());
+            return stamps;
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .map_or(false, |extension| extension.eq_ignore_ascii_case("png"))
+        })
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        match load_stamp(&path) {
+            Ok(stamp) => stamps.push(stamp),
+            Err(e) => warn!("Skipping stamp {}: {}", path.display(), e),
+        }
+    }
+
+    info!("Loaded {} stamps", stamps.len());
+    stamps
+}
+
+fn load_stamp(path: &std::path::Path) -> Result<Stamp> {
+    let image = image::open(path).map_err(|e| anyhow!("Failed to load image: {}", e))?;
+    let image = capture::downscale_to_fit(image, MAX_STAMP_SIZE, MAX_STAMP_SIZE).to_rgba8();
+    let surface = surface_from_image(&image)?;
+
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("Stamp")
+        .to_string();
+
+    Ok(Stamp { name, surface })
+}
+
+// Cairo wants premultiplied BGRA, and stamps usually have transparency
+pub fn surface_from_image(image: &image::RgbaImage) -> Result<ImageSurface> {
+    let (width, height) = image.dimensions();
+
+    let stride = Format::ARgb32
+        .stride_for_width(width)
+        .map_err(|e| anyhow!("Failed to calculate stride: {}", e))?;
+    let mut data = vec![0u8; stride as usize * height as usize];
+
+    let row_len = width as usize * 4;
+    for (dst_row, src_row) in data
+        .chunks_exact_mut(stride as usize)
+        .zip(image.as_raw().chunks_exact(row_len))
+    {
+        for (dst, src) in dst_row[..row_len]
+            .chunks_exact_mut(4)
+            .zip(src_row.chunks_exact(4))
+        {
+            let alpha = src[3] as u32;
+            let premultiply = |channel: u8| ((channel as u32 * alpha + 127) / 255) as u8;
+            dst[0] = premultiply(src[2]);
+            dst[1] = premultiply(src[1]);
+            dst[2] = premultiply(src[0]);
+            dst[3] = src[3];
+        }
+    }
+
+    ImageSurface::create_for_data(data, Format::ARgb32, width as i32, height as i32, stride)
+        .map_err(|e| anyhow!("Failed to create stamp surface: {}", e))
+}
+
+fn builtin_stamps() -> Vec<Stamp> {
+    let builtins: [(&str, fn(&Context, f64)); 3] = [
+        ("Check", draw_check),
+        ("Cross", draw_cross),
+        ("Star", draw_star),
+    ];
+
+    builtins
+        .iter()
+        .filter_map(|(name, draw)| {
+            let surface =
+                ImageSurface::create(Format::ARgb32, BUILTIN_STAMP_SIZE, BUILTIN_STAMP_SIZE)
+                    .ok()?;
+            {
+                let ctx = Context::new(&surface).ok()?;
+                draw(&ctx, BUILTIN_STAMP_SIZE as f64);
+            }
+            Some(Stamp {
+                name: name.to_string(),
+                surface,
+            })
+        })
+        .collect()
+}
+
+fn draw_badge(ctx: &Context, size: f64, red: f64, green: f64, blue: f64) {
+    ctx.arc(size / 2.0, size / 2.0, size / 2.0 - 2.0, 0.0, 2.0 * PI);
+    ctx.set_source_rgb(red, green, blue);
+    ctx.fill_preserve().ok();
+    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    ctx.set_line_width(3.0);
+    ctx.stroke().ok();
+}
+
+fn draw_check(ctx: &Context, size: f64) {
+    draw_badge(ctx, size, 0.1, 0.65, 0.3);
+
+    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    ctx.set_line_width(size / 9.0);
+    ctx.set_line_cap(cairo::LineCap::Round);
+    ctx.set_line_join(cairo::LineJoin::Round);
+    ctx.move_to(size * 0.28, size * 0.52);
+    ctx.line_to(size * 0.44, size * 0.68);
+    ctx.line_to(size * 0.72, size * 0.36);
+    ctx.stroke().ok();
+}
+
+fn draw_cross(ctx: &Context, size: f64) {
+    draw_badge(ctx, size, 0.85, 0.15, 0.15);
+
+    ctx.set_source_rgb(1.0, 1.0, 1.0);
+    ctx.set_line_width(size / 9.0);
+    ctx.set_line_cap(cairo::LineCap::Round);
+    ctx.move_to(size * 0.33, size * 0.33);
+    ctx.line_to(size * 0.67, size * 0.67);
+    ctx.move_to(size * 0.67, size * 0.33);
+    ctx.line_to(size * 0.33, size * 0.67);
+    ctx.stroke().ok();
+}
+
+fn draw_star(ctx: &Context, size: f64) {
+    let center = size / 2.0;
+    let outer = size / 2.0 - 3.0;
+    let inner = outer * 0.45;
+
+    // Five points, alternating between the outer and inner radius
+    for i in 0..10 {
+        let radius = if i % 2 == 0 { outer } else { inner };
+        let angle = -PI / 2.0 + i as f64 * PI / 5.0;
+        let (x, y) = (center + radius * angle.cos(), center + radius * angle.sin());
+        if i == 0 {
+            ctx.move_to(x, y);
+        } else {
+            ctx.line_to(x, y);
+        }
+    }
+    ctx.close_path();
+
+    ctx.set_source_rgb(1.0, 0.8, 0.1);
+    ctx.fill_preserve().ok();
+    ctx.set_source_rgb(0.6, 0.4, 0.0);
+    ctx.set_line_width(2.0);
+    ctx.set_line_join(cairo::LineJoin::Round);
+    ctx.stroke().ok();
+}
@@ -0,0 +1,30 @@
+use gtk4::prelude::*;
+use log::info;
+
+const FALLBACK_CSS: &str = include_str!("style.css");
+
+// Adwaita already styles every class Flint uses, so its look is left alone.
+// Any other theme gets the fallbacks at application priority, otherwise the
+// theme's plain button and list rules would win over them.
+pub fn load_fallback_css() {
+    let Some(display) = gdk4::Display::default() else {
+        return;
+    };
+
+    let theme = gtk4::Settings::for_display(&display)
+        .gtk_theme_name()
+        .map(|name| name.to_string())
+        .unwrap_or_default();
+    if theme.starts_with("Adwaita") || theme == "Default" {
+        return;
+    }
+
+    info!("Loading fallback styles for GTK theme '{}'", theme);
+    let provider = gtk4::CssProvider::new();
+    provider.load_from_data(FALLBACK_CSS);
+    gtk4::style_context_add_provider_for_display(
+        &display,
+        &provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+}
@@ -1,16 +1,120 @@
-use cairo::{Context, LineCap, LineJoin};
+use cairo::{
+    Antialias, Context, FontSlant, FontWeight, Format, ImageSurface, LineCap, LineJoin, Operator,
+};
 use gdk4::RGBA;
-use log::info;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::preferences::{self, HighlighterBlend, PaletteColor};
+use crate::project;
+use crate::stamps::Stamp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ToolType {
     Pencil,
     Line,
     Arrow,
     Highlighter,
+    Text,
+    Rectangle,
+    Stamp,
+    Measure,
+    Eraser,
 }
 
-#[derive(Debug, Clone)]
+// The toolbar palette as (name, color), read from the preferences once
+static PALETTE: OnceLock<Vec<(String, RGBA)>> = OnceLock::new();
+
+// Every tool in toolbar order, with its button icon and tooltip
+const TOOLS: &[(ToolType, &str, &str)] = &[
+    (ToolType::Pencil, "✏️", "Pencil"),
+    (ToolType::Line, "📏", "Line"),
+    (ToolType::Arrow, "➡️", "Arrow"),
+    (ToolType::Highlighter, "🖍️", "Highlighter"),
+    (ToolType::Text, "🔤", "Text"),
+    (ToolType::Rectangle, "⬜", "Rectangle"),
+    (ToolType::Stamp, "🏷️", "Stamp"),
+    (ToolType::Measure, "📐", "Measure distance and angle"),
+    (
+        ToolType::Eraser,
+        "🧽",
+        "Erase annotations inside a dragged box",
+    ),
+];
+
+impl ToolType {
+    // The toolbar creates one button per entry, so a tool's position here is
+    // also its button index
+    pub fn all() -> &'static [(ToolType, &'static str, &'static str)] {
+        TOOLS
+    }
+
+    pub fn from_index(index: usize) -> Option<ToolType> {
+        TOOLS.get(index).map(|&(tool_type, _, _)| tool_type)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ArrowShape {
+    Straight,
+    Elbow,
+    Curved,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ArrowHead {
+    // Two lines forming a V at the end point
+    #[default]
+    Open,
+    // Solid triangle at the end point
+    Filled,
+    // Open heads at both the start and end points
+    Double,
+}
+
+// How the open ends of a stroke are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum CapStyle {
+    #[default]
+    Round,
+    // Squared off half the line width past the end point
+    Square,
+    // Squared off exactly at the end point
+    Butt,
+}
+
+// How the corners of a stroke are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum JoinStyle {
+    #[default]
+    Round,
+    Miter,
+    Bevel,
+}
+
+impl CapStyle {
+    fn line_cap(self) -> LineCap {
+        match self {
+            CapStyle::Round => LineCap::Round,
+            CapStyle::Square => LineCap::Square,
+            CapStyle::Butt => LineCap::Butt,
+        }
+    }
+}
+
+impl JoinStyle {
+    fn line_join(self) -> LineJoin {
+        match self {
+            JoinStyle::Round => LineJoin::Round,
+            JoinStyle::Miter => LineJoin::Miter,
+            JoinStyle::Bevel => LineJoin::Bevel,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -20,15 +124,115 @@ impl Point {
     pub fn new(x: f64, y: f64) -> Self {
         Self { x, y }
     }
+
+    pub fn distance_to(&self, other: &Point) -> f64 {
+        (other.x - self.x).hypot(other.y - self.y)
+    }
 }
 
-#[derive(Debug, Clone)]
+// Distance in image pixels within which a live endpoint snaps into place
+const SNAP_DISTANCE: f64 = 8.0;
+
+// On-screen size of the resize handles around a selected shape
+const RESIZE_HANDLE_SIZE: f64 = 8.0;
+
+// Strongest stabilizer; any more and the line barely follows the pointer
+pub const MAX_STABILIZER: f64 = 0.9;
+
+// Which part of a shape's box a resize handle drags: the horizontal and
+// vertical edge it moves, -1 for left/top, 1 for right/bottom, 0 for neither
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ResizeHandle {
+    horizontal: i8,
+    vertical: i8,
+}
+
+impl ResizeHandle {
+    // Corners first, then edge midpoints
+    const ALL: [ResizeHandle; 8] = [
+        ResizeHandle::new(-1, -1),
+        ResizeHandle::new(1, -1),
+        ResizeHandle::new(1, 1),
+        ResizeHandle::new(-1, 1),
+        ResizeHandle::new(0, -1),
+        ResizeHandle::new(1, 0),
+        ResizeHandle::new(0, 1),
+        ResizeHandle::new(-1, 0),
+    ];
+
+    const fn new(horizontal: i8, vertical: i8) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    fn is_corner(&self) -> bool {
+        self.horizontal != 0 && self.vertical != 0
+    }
+
+    // Where the handle sits on the box (left, top, right, bottom)
+    fn position(&self, (left, top, right, bottom): (f64, f64, f64, f64)) -> Point {
+        let along = |edge: i8, low: f64, high: f64| match edge {
+            -1 => low,
+            1 => high,
+            _ => (low + high) / 2.0,
+        };
+        Point::new(
+            along(self.horizontal, left, right),
+            along(self.vertical, top, bottom),
+        )
+    }
+}
+
+// A shape being resized by one of its handles
+#[derive(Debug)]
+struct ShapeResize {
+    index: usize,
+    // None for a curved arrow's control point
+    handle: Option<ResizeHandle>,
+    // The stroke as it was before the drag, for undo and the aspect ratio
+    original: DrawingStroke,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DrawingStroke {
     pub tool_type: ToolType,
     pub points: Vec<Point>,
+    #[serde(with = "project::rgba")]
     pub color: RGBA,
     pub thickness: f64,
     pub finished: bool,
+    pub text: String,
+    pub max_width: Option<f64>,
+    pub corner_radius: f64,
+    pub arrow_shape: ArrowShape,
+    // Projects saved before head styles existed load with open heads
+    #[serde(default)]
+    pub arrow_head: ArrowHead,
+    pub control_point: Option<Point>,
+    pub visible: bool,
+    #[serde(with = "project::stamp_surface")]
+    pub stamp: Option<ImageSurface>,
+    pub stamp_scale: f64,
+    // Reference note kept in projects, e.g. for building a legend. Never
+    // drawn, so it leaves the image and raster exports untouched.
+    #[serde(default)]
+    pub note: Option<String>,
+    // Indices of the points a continued line turns at, between its first
+    // and last points
+    #[serde(default)]
+    pub corners: Vec<usize>,
+    // Lines and rectangles drawn with pixel snapping land on whole pixels
+    // without anti-aliasing, for crisp UI mockups
+    #[serde(default)]
+    pub pixel_snap: bool,
+    // Projects saved before line styles existed load with round ends and
+    // corners, as they were drawn
+    #[serde(default)]
+    pub cap_style: CapStyle,
+    #[serde(default)]
+    pub join_style: JoinStyle,
 }
 
 impl DrawingStroke {
@@ -39,17 +243,170 @@ impl DrawingStroke {
             color,
             thickness,
             finished: false,
+            text: String::new(),
+            max_width: None,
+            corner_radius: 0.0,
+            arrow_shape: ArrowShape::Straight,
+            arrow_head: ArrowHead::Open,
+            control_point: None,
+            visible: true,
+            stamp: None,
+            stamp_scale: 1.0,
+            note: None,
+            corners: Vec::new(),
+            pixel_snap: false,
+            cap_style: CapStyle::Round,
+            join_style: JoinStyle::Round,
+        }
+    }
+
+    // Only straight edges of a whole-pixel width can be snapped
+    fn is_pixel_snapped(&self) -> bool {
+        self.pixel_snap
+            && matches!(self.tool_type, ToolType::Line | ToolType::Rectangle)
+            && self.thickness >= 1.0
+            && (self.thickness - self.thickness.round()).abs() < 0.01
+    }
+
+    // Where a point of a snapped stroke is drawn. Odd widths are centered on
+    // a pixel and even widths on the boundary between two, so either way
+    // the edge covers whole pixels.
+    fn snap_to_pixels(&self, point: &Point) -> Point {
+        let offset = if self.thickness.round() as i64 % 2 == 1 {
+            0.5
+        } else {
+            0.0
+        };
+        Point::new(
+            (point.x - offset).round() + offset,
+            (point.y - offset).round() + offset,
+        )
+    }
+
+    // Ends and corners as chosen for the stroke
+    fn set_line_style(&self, ctx: &Context) {
+        ctx.set_line_cap(self.cap_style.line_cap());
+        ctx.set_line_join(self.join_style.line_join());
+    }
+
+    // The stroke's points as drawn, snapped to pixels when asked for
+    fn drawn_point(&self, point: &Point) -> Point {
+        if self.is_pixel_snapped() {
+            self.snap_to_pixels(point)
+        } else {
+            point.clone()
+        }
+    }
+
+    // Bounding box in image coordinates, padded by half the stroke width
+    pub fn bounds(&self, ctx: &Context) -> Option<(f64, f64, f64, f64)> {
+        let first = self.points.first()?;
+
+        if self.stamp.is_some() {
+            return self.stamp_bounds();
+        }
+
+        if self.tool_type == ToolType::Text {
+            let layout = self.layout_text(ctx);
+            let width = self
+                .max_width
+                .map_or(layout.width, |max| layout.width.min(max));
+            return Some((first.x, first.y, width, layout.height()));
+        }
+
+        let mut min_x = first.x;
+        let mut min_y = first.y;
+        let mut max_x = first.x;
+        let mut max_y = first.y;
+        for point in self.points.iter().chain(self.control_point.as_ref()) {
+            min_x = min_x.min(point.x);
+            min_y = min_y.min(point.y);
+            max_x = max_x.max(point.x);
+            max_y = max_y.max(point.y);
         }
+
+        let padding = self.thickness / 2.0;
+        Some((
+            min_x - padding,
+            min_y - padding,
+            max_x - min_x + self.thickness,
+            max_y - min_y + self.thickness,
+        ))
+    }
+
+    pub fn stamp_bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        let center = self.points.first()?;
+        let stamp = self.stamp.as_ref()?;
+        let width = stamp.width() as f64 * self.stamp_scale;
+        let height = stamp.height() as f64 * self.stamp_scale;
+
+        Some((
+            center.x - width / 2.0,
+            center.y - height / 2.0,
+            width,
+            height,
+        ))
     }
 
     pub fn add_point(&mut self, point: Point) {
         self.points.push(point);
     }
 
+    // Move the whole stroke, including a curved arrow's pinned control point
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        for point in self.points.iter_mut().chain(self.control_point.as_mut()) {
+            point.x += dx;
+            point.y += dy;
+        }
+    }
+
+    // Resize the whole stroke about the image origin, e.g. between a
+    // downscaled working copy and the full-resolution capture
+    pub fn scale_by(&mut self, factor: f64) {
+        for point in self.points.iter_mut().chain(self.control_point.as_mut()) {
+            point.x *= factor;
+            point.y *= factor;
+        }
+        self.thickness *= factor;
+        self.corner_radius *= factor;
+        self.stamp_scale *= factor;
+        if let Some(ref mut max_width) = self.max_width {
+            *max_width *= factor;
+        }
+    }
+
     pub fn finish(&mut self) {
+        // Pin the curve's control point so it can be adjusted later
+        if self.tool_type == ToolType::Arrow && self.arrow_shape == ArrowShape::Curved {
+            self.control_point = self.curve_control_point();
+        }
         self.finished = true;
     }
 
+    // Where a curved arrow's control point can be dragged from
+    fn control_handle(&self) -> Option<Point> {
+        if self.tool_type != ToolType::Arrow || self.arrow_shape != ArrowShape::Curved {
+            return None;
+        }
+        self.curve_control_point()
+    }
+
+    // Box of a rectangle as (left, top, right, bottom), from its first and
+    // last points
+    fn shape_box(&self) -> Option<(f64, f64, f64, f64)> {
+        if self.tool_type != ToolType::Rectangle || self.points.len() < 2 {
+            return None;
+        }
+        let start = self.points.first()?;
+        let end = self.points.last()?;
+        Some((
+            start.x.min(end.x),
+            start.y.min(end.y),
+            start.x.max(end.x),
+            start.y.max(end.y),
+        ))
+    }
+
     pub fn draw(&self, ctx: &Context) {
         if self.points.is_empty() {
             return;
@@ -70,6 +427,11 @@ impl DrawingStroke {
             ToolType::Line => self.draw_line(ctx),
             ToolType::Arrow => self.draw_arrow(ctx),
             ToolType::Highlighter => self.draw_highlighter(ctx),
+            ToolType::Text => self.draw_text(ctx),
+            ToolType::Rectangle => self.draw_rectangle(ctx),
+            ToolType::Stamp => self.draw_stamp(ctx),
+            // Measurements and erasing are never strokes
+            ToolType::Measure | ToolType::Eraser => {}
         }
 
         ctx.restore().ok();
@@ -77,8 +439,7 @@ impl DrawingStroke {
 
     fn draw_pencil(&self, ctx: &Context) {
         ctx.set_line_width(self.thickness);
-        ctx.set_line_cap(LineCap::Round);
-        ctx.set_line_join(LineJoin::Round);
+        self.set_line_style(ctx);
 
         if let Some(first_point) = self.points.first() {
             ctx.move_to(first_point.x, first_point.y);
@@ -93,13 +454,28 @@ impl DrawingStroke {
 
     fn draw_line(&self, ctx: &Context) {
         if self.points.len() >= 2 {
-            let start = &self.points[0];
-            let end = &self.points[self.points.len() - 1];
+            let start = self.drawn_point(&self.points[0]);
+            let end = self.drawn_point(&self.points[self.points.len() - 1]);
 
             ctx.set_line_width(self.thickness);
-            ctx.set_line_cap(LineCap::Round);
+            if self.is_pixel_snapped() {
+                // Square ends stop on a pixel edge where round ones would not
+                ctx.set_antialias(Antialias::None);
+                ctx.set_line_cap(LineCap::Square);
+                ctx.set_line_join(LineJoin::Miter);
+            } else {
+                self.set_line_style(ctx);
+            }
 
             ctx.move_to(start.x, start.y);
+            for corner in self
+                .corners
+                .iter()
+                .filter_map(|&index| self.points.get(index))
+            {
+                let corner = self.drawn_point(corner);
+                ctx.line_to(corner.x, corner.y);
+            }
             ctx.line_to(end.x, end.y);
             ctx.stroke().unwrap();
         }
@@ -112,18 +488,95 @@ impl DrawingStroke {
 
             // Draw the main line
             ctx.set_line_width(self.thickness);
-            ctx.set_line_cap(LineCap::Round);
+            self.set_line_style(ctx);
 
             ctx.move_to(start.x, start.y);
-            ctx.line_to(end.x, end.y);
+
+            // Each arrowhead follows the direction of the segment it ends
+            let (head_from, tail_from) = match self.arrow_shape {
+                ArrowShape::Straight => {
+                    ctx.line_to(end.x, end.y);
+                    (start.clone(), end.clone())
+                }
+                ArrowShape::Elbow => {
+                    let corner = Self::elbow_corner(start, end);
+                    ctx.line_to(corner.x, corner.y);
+                    ctx.line_to(end.x, end.y);
+                    (corner.clone(), corner)
+                }
+                ArrowShape::Curved => {
+                    let control = self.curve_control_point().unwrap_or_else(|| start.clone());
+
+                    // Express the quadratic curve as the cubic Cairo expects
+                    ctx.curve_to(
+                        start.x + 2.0 / 3.0 * (control.x - start.x),
+                        start.y + 2.0 / 3.0 * (control.y - start.y),
+                        end.x + 2.0 / 3.0 * (control.x - end.x),
+                        end.y + 2.0 / 3.0 * (control.y - end.y),
+                        end.x,
+                        end.y,
+                    );
+                    (control.clone(), control)
+                }
+            };
             ctx.stroke().unwrap();
 
             // Draw arrowhead
-            self.draw_arrowhead(ctx, start, end);
+            self.draw_arrowhead(ctx, &head_from, end);
+            if self.arrow_head == ArrowHead::Double {
+                self.draw_arrowhead(ctx, &tail_from, start);
+            }
+        }
+    }
+
+    // Route along the dominant axis first, then turn towards the end point
+    fn elbow_corner(start: &Point, end: &Point) -> Point {
+        if (end.x - start.x).abs() >= (end.y - start.y).abs() {
+            Point::new(end.x, start.y)
+        } else {
+            Point::new(start.x, end.y)
         }
     }
 
+    // Quadratic control point for curved arrows. Until one is stored, bow the
+    // curve out from the midpoint by a quarter of the arrow's length.
+    pub fn curve_control_point(&self) -> Option<Point> {
+        if let Some(ref control) = self.control_point {
+            return Some(control.clone());
+        }
+
+        let start = self.points.first()?;
+        let end = self.points.last()?;
+        let dx = end.x - start.x;
+        let dy = end.y - start.y;
+
+        Some(Point::new(
+            (start.x + end.x) / 2.0 + dy * 0.25,
+            (start.y + end.y) / 2.0 - dx * 0.25,
+        ))
+    }
+
     fn draw_arrowhead(&self, ctx: &Context, start: &Point, end: &Point) {
+        let Some((barb1, barb2)) = self.arrowhead_points(start, end) else {
+            return;
+        };
+
+        ctx.move_to(end.x, end.y);
+        ctx.line_to(barb1.x, barb1.y);
+        if self.arrow_head == ArrowHead::Filled {
+            ctx.line_to(barb2.x, barb2.y);
+            ctx.close_path();
+            ctx.fill().unwrap();
+        } else {
+            ctx.move_to(end.x, end.y);
+            ctx.line_to(barb2.x, barb2.y);
+            ctx.stroke().unwrap();
+        }
+    }
+
+    // Outer corners of an arrowhead pointing at `end` along the direction from
+    // `start`, or None when the two points coincide
+    fn arrowhead_points(&self, start: &Point, end: &Point) -> Option<(Point, Point)> {
         let arrow_length = self.thickness * 3.0;
         let arrow_angle = std::f64::consts::PI / 6.0; // 30 degrees
 
@@ -133,7 +586,7 @@ impl DrawingStroke {
         let length = (dx * dx + dy * dy).sqrt();
 
         if length == 0.0 {
-            return;
+            return None;
         }
 
         // Normalize the direction vector
@@ -151,25 +604,34 @@ impl DrawingStroke {
         let arrow_y2 =
             end.y - arrow_length * (-unit_x * arrow_angle.sin() + unit_y * arrow_angle.cos());
 
-        // Draw the arrowhead
-        ctx.move_to(end.x, end.y);
-        ctx.line_to(arrow_x1, arrow_y1);
-        ctx.move_to(end.x, end.y);
-        ctx.line_to(arrow_x2, arrow_y2);
-        ctx.stroke().unwrap();
+        Some((
+            Point::new(arrow_x1, arrow_y1),
+            Point::new(arrow_x2, arrow_y2),
+        ))
+    }
+
+    // Multiply can only darken, so it takes a stronger tint than plain
+    // alpha blending without hiding the text underneath
+    pub fn highlighter_alpha() -> f64 {
+        match preferences::get().highlighter.blend_mode {
+            HighlighterBlend::Multiply => 0.6,
+            HighlighterBlend::Normal => 0.3,
+        }
     }
 
     fn draw_highlighter(&self, ctx: &Context) {
+        ctx.save().ok();
         ctx.set_line_width(self.thickness);
-        ctx.set_line_cap(LineCap::Round);
-        ctx.set_line_join(LineJoin::Round);
+        self.set_line_style(ctx);
 
-        // Highlighter should be semi-transparent
+        if preferences::get().highlighter.blend_mode == HighlighterBlend::Multiply {
+            ctx.set_operator(Operator::Multiply);
+        }
         ctx.set_source_rgba(
             self.color.red() as f64,
             self.color.green() as f64,
             self.color.blue() as f64,
-            0.3, // Semi-transparent
+            Self::highlighter_alpha(),
         );
 
         if let Some(first_point) = self.points.first() {
@@ -181,99 +643,2504 @@ impl DrawingStroke {
 
             ctx.stroke().unwrap();
         }
+
+        // Restores the operator so later strokes paint normally
+        ctx.restore().ok();
+    }
+
+    fn draw_rectangle(&self, ctx: &Context) {
+        if self.points.len() >= 2 {
+            let start = self.drawn_point(&self.points[0]);
+            let end = self.drawn_point(&self.points[self.points.len() - 1]);
+
+            ctx.set_line_width(self.thickness);
+            if self.is_pixel_snapped() {
+                ctx.set_antialias(Antialias::None);
+                ctx.set_line_join(LineJoin::Miter);
+            } else {
+                // A rectangle has no open ends, only corners
+                ctx.set_line_join(self.join_style.line_join());
+            }
+
+            rounded_rectangle_path(
+                ctx,
+                start.x.min(end.x),
+                start.y.min(end.y),
+                (end.x - start.x).abs(),
+                (end.y - start.y).abs(),
+                self.corner_radius,
+            );
+            ctx.stroke().unwrap();
+        }
+    }
+
+    pub fn font_size(&self) -> f64 {
+        // Text size follows the thickness slider so one control sizes every tool
+        (self.thickness * 4.0).max(12.0)
+    }
+
+    fn select_font(&self, ctx: &Context) {
+        ctx.select_font_face("Sans", FontSlant::Normal, FontWeight::Bold);
+        ctx.set_font_size(self.font_size());
+    }
+
+    // Split the text into display lines: embedded newlines start a new line
+    // and words that would run past `max_width` wrap onto the next one
+    fn text_lines(&self, ctx: &Context) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for paragraph in self.text.split('\n') {
+            let max_width = match self.max_width {
+                Some(max_width) => max_width,
+                None => {
+                    lines.push(paragraph.to_string());
+                    continue;
+                }
+            };
+
+            let mut current = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if current.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{} {}", current, word)
+                };
+
+                let width = ctx
+                    .text_extents(&candidate)
+                    .map(|extents| extents.x_advance())
+                    .unwrap_or(0.0);
+
+                if width > max_width && !current.is_empty() {
+                    lines.push(std::mem::take(&mut current));
+                    current = word.to_string();
+                } else {
+                    current = candidate;
+                }
+            }
+            lines.push(current);
+        }
+
+        lines
+    }
+
+    // Measure the text with the stroke's font. Drawing, the editing overlay
+    // and exports all go through this so line breaks and spacing match.
+    // Leaves the stroke's font selected on `ctx`.
+    pub fn layout_text(&self, ctx: &Context) -> TextLayout {
+        self.select_font(ctx);
+
+        let (ascent, line_height) = match ctx.font_extents() {
+            Ok(extents) => (extents.ascent(), extents.height()),
+            Err(_) => (self.font_size(), self.font_size() * 1.2),
+        };
+
+        let lines = self.text_lines(ctx);
+        let widest = lines
+            .iter()
+            .filter_map(|line| ctx.text_extents(line).ok())
+            .map(|extents| extents.x_advance())
+            .fold(0.0, f64::max);
+        let width = match self.max_width {
+            Some(max_width) => widest.min(max_width),
+            None => widest,
+        };
+
+        TextLayout {
+            lines,
+            ascent,
+            line_height,
+            width,
+        }
+    }
+
+    // Stamps are centered on their single point
+    fn draw_stamp(&self, ctx: &Context) {
+        if let (Some(center), Some(ref stamp)) = (self.points.first(), &self.stamp) {
+            ctx.translate(center.x, center.y);
+            ctx.scale(self.stamp_scale, self.stamp_scale);
+            ctx.set_source_surface(
+                stamp,
+                -stamp.width() as f64 / 2.0,
+                -stamp.height() as f64 / 2.0,
+            )
+            .ok();
+            ctx.source().set_filter(cairo::Filter::Good);
+            ctx.paint().ok();
+        }
+    }
+
+    fn draw_text(&self, ctx: &Context) {
+        if let Some(origin) = self.points.first() {
+            let layout = self.layout_text(ctx);
+
+            // Clip words too long to wrap at the box or image edge
+            if let Some(max_width) = self.max_width {
+                ctx.rectangle(origin.x, origin.y, max_width, layout.height());
+                ctx.clip();
+            }
+
+            for (i, line) in layout.lines.iter().enumerate() {
+                ctx.move_to(
+                    origin.x,
+                    origin.y + layout.ascent + i as f64 * layout.line_height,
+                );
+                ctx.show_text(line).unwrap();
+            }
+        }
+    }
+}
+
+// Build a rectangle path whose corners are quarter-circle arcs. The radius is
+// clamped to half the shorter side; a radius of 0 gives sharp corners.
+pub fn rounded_rectangle_path(ctx: &Context, x: f64, y: f64, width: f64, height: f64, radius: f64) {
+    let radius = radius.max(0.0).min(width.min(height) / 2.0);
+
+    if radius <= 0.0 {
+        ctx.rectangle(x, y, width, height);
+        return;
+    }
+
+    let degrees = std::f64::consts::PI / 180.0;
+
+    ctx.new_sub_path();
+    ctx.arc(
+        x + width - radius,
+        y + radius,
+        radius,
+        -90.0 * degrees,
+        0.0 * degrees,
+    );
+    ctx.arc(
+        x + width - radius,
+        y + height - radius,
+        radius,
+        0.0 * degrees,
+        90.0 * degrees,
+    );
+    ctx.arc(
+        x + radius,
+        y + height - radius,
+        radius,
+        90.0 * degrees,
+        180.0 * degrees,
+    );
+    ctx.arc(
+        x + radius,
+        y + radius,
+        radius,
+        180.0 * degrees,
+        270.0 * degrees,
+    );
+    ctx.close_path();
+}
+
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    pub lines: Vec<String>,
+    pub ascent: f64,
+    pub line_height: f64,
+    pub width: f64,
+}
+
+impl TextLayout {
+    pub fn height(&self) -> f64 {
+        self.lines.len().max(1) as f64 * self.line_height
     }
 }
 
+// A reversible change to the finished strokes. Each carries what is needed to
+// restore the previous state, so multi-part operations like clear-all undo in
+// a single step.
+// The capture at full size, kept as PNG while the editor works on a
+// downscaled copy. Exports paint it in place of the working surface.
+#[derive(Debug, Clone)]
+pub struct FullResolution {
+    pub png: Vec<u8>,
+    // Working size divided by full size
+    pub scale: f64,
+}
+
+// A screenshot an undo or redo switched to, with the full-size capture that
+// goes with it
+#[derive(Debug)]
+pub struct ScreenshotChange {
+    pub surface: ImageSurface,
+    pub full_resolution: Option<FullResolution>,
+}
+
+#[derive(Debug, Clone)]
+enum EditCommand {
+    AddStroke {
+        index: usize,
+        stroke: DrawingStroke,
+    },
+    RemoveStroke {
+        index: usize,
+        stroke: DrawingStroke,
+    },
+    ClearAll {
+        strokes: Vec<DrawingStroke>,
+    },
+    // Removed strokes with their former indices, in ascending order
+    RemoveStrokes {
+        removed: Vec<(usize, DrawingStroke)>,
+    },
+    SetVisibility {
+        index: usize,
+        visible: bool,
+    },
+    // A stroke edited in place, such as a resized shape
+    ReplaceStroke {
+        index: usize,
+        before: DrawingStroke,
+        after: DrawingStroke,
+    },
+    // Several strokes edited in place together, as (index, before, after)
+    ReplaceStrokes {
+        changes: Vec<(usize, DrawingStroke, DrawingStroke)>,
+    },
+    // The screenshot itself changed, as when a blur is baked into it. The
+    // full-size capture no longer matches the result and comes back on undo.
+    ReplaceScreenshot {
+        before: ImageSurface,
+        after: ImageSurface,
+        full_resolution: Option<FullResolution>,
+    },
+}
+
 #[derive(Debug)]
 pub struct AnnotationTools {
     pub current_tool: ToolType,
+    // Tool used before the current one, for swapping back and forth
+    previous_tool: Option<ToolType>,
     pub current_color: RGBA,
     pub current_thickness: f64,
+    pub current_corner_radius: f64,
+    pub current_pixel_snap: bool,
+    pub current_cap_style: CapStyle,
+    pub current_join_style: JoinStyle,
+    pub current_arrow_shape: ArrowShape,
+    pub current_arrow_head: ArrowHead,
+    // How far freehand strokes trail the pointer, from 0 (off) to 0.9
+    pub current_stabilizer: f64,
+    pub tool_colors: HashMap<ToolType, RGBA>,
     pub strokes: Vec<DrawingStroke>,
     pub current_stroke: Option<DrawingStroke>,
+    pub snap_indicator: Option<Point>,
+    pub selected_stroke: Option<usize>,
+    // Strokes picked with Ctrl+click or a Ctrl+drag box, which move, delete
+    // and recolor as one
+    pub selected_group: Vec<usize>,
+    // Where a group drag started, with the group as it was before it
+    group_drag: Option<(Point, Vec<(usize, DrawingStroke)>)>,
+    // Corners of the selection box being dragged out
+    group_box: Option<(Point, Point)>,
+    pub stamps: Vec<Stamp>,
+    pub current_stamp: usize,
+    // Stamp being dragged, its offset from the pointer and the stroke as it
+    // was before the drag
+    stamp_drag: Option<(usize, f64, f64, DrawingStroke)>,
+    // Start and end of the measurement being dragged out; drawn on the
+    // canvas only, so it never becomes part of an export
+    measurement: Option<(Point, Point)>,
+    // Corners of the eraser box being dragged out
+    erase_box: Option<(Point, Point)>,
+    shape_resize: Option<ShapeResize>,
+    // Index of the finished stroke being continued, and how it was before
+    resumed_stroke: Option<(usize, DrawingStroke)>,
+    revision: u64,
+    undo_stack: Vec<EditCommand>,
+    redo_stack: Vec<EditCommand>,
+    // Screenshot an undo or redo switched to, until the editor takes it
+    screenshot_change: Option<ScreenshotChange>,
 }
 
 impl AnnotationTools {
     pub fn new() -> Self {
         Self {
             current_tool: ToolType::Pencil,
+            previous_tool: None,
             current_color: RGBA::new(1.0, 0.0, 0.0, 1.0), // Red
             current_thickness: 3.0,
+            current_corner_radius: 0.0,
+            current_pixel_snap: false,
+            current_cap_style: CapStyle::Round,
+            current_join_style: JoinStyle::Round,
+            current_arrow_shape: ArrowShape::Straight,
+            current_arrow_head: ArrowHead::Open,
+            current_stabilizer: preferences::get()
+                .pencil
+                .stabilizer
+                .clamp(0.0, MAX_STABILIZER),
+            tool_colors: Self::default_tool_colors(),
             strokes: Vec::new(),
             current_stroke: None,
+            snap_indicator: None,
+            selected_stroke: None,
+            selected_group: Vec::new(),
+            group_drag: None,
+            group_box: None,
+            stamps: Vec::new(),
+            current_stamp: 0,
+            stamp_drag: None,
+            measurement: None,
+            erase_box: None,
+            shape_resize: None,
+            resumed_stroke: None,
+            revision: 0,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            screenshot_change: None,
         }
     }
 
-    pub fn set_tool(&mut self, tool: ToolType) {
-        self.current_tool = tool;
+    // Increases whenever the set of finished strokes changes, so renderers
+    // can tell when cached output is stale
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
 
-        // Set default thickness based on tool
-        self.current_thickness = match tool {
-            ToolType::Pencil => 3.0,
-            ToolType::Line => 2.0,
-            ToolType::Arrow => 2.0,
-            ToolType::Highlighter => 8.0,
-        };
+    fn mark_changed(&mut self) {
+        self.revision = self.revision.wrapping_add(1);
     }
 
-    pub fn set_color(&mut self, color: RGBA) {
-        self.current_color = color;
+    // Replace the finished strokes wholesale, e.g. from a reopened project
+    pub fn load_strokes(&mut self, strokes: Vec<DrawingStroke>) {
+        self.strokes = strokes;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.after_history_change();
     }
 
-    pub fn set_thickness(&mut self, thickness: f64) {
-        self.current_thickness = thickness;
+    // A new edit invalidates anything that was undone before it
+    fn record(&mut self, command: EditCommand) {
+        self.undo_stack.push(command);
+        self.redo_stack.clear();
     }
 
-    pub fn start_stroke(&mut self, point: Point) {
-        let mut stroke = DrawingStroke::new(
-            self.current_tool,
-            self.current_color,
-            self.current_thickness,
-        );
-        stroke.add_point(point);
-        self.current_stroke = Some(stroke);
+    // Make a change the editor made to the screenshot undoable
+    pub fn record_screenshot_change(
+        &mut self,
+        before: ImageSurface,
+        after: ImageSurface,
+        full_resolution: Option<FullResolution>,
+    ) {
+        self.record(EditCommand::ReplaceScreenshot {
+            before,
+            after,
+            full_resolution,
+        });
     }
 
-    pub fn add_point_to_stroke(&mut self, point: Point) {
-        if let Some(ref mut stroke) = self.current_stroke {
-            stroke.add_point(point);
+    // The screenshot to show after the last undo or redo, if it changed it
+    pub fn take_screenshot_change(&mut self) -> Option<ScreenshotChange> {
+        self.screenshot_change.take()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let command = match self.undo_stack.pop() {
+            Some(command) => command,
+            None => return false,
+        };
+
+        match &command {
+            EditCommand::AddStroke { index, .. } => {
+                if *index < self.strokes.len() {
+                    self.strokes.remove(*index);
+                }
+            }
+            EditCommand::RemoveStroke { index, stroke } => {
+                let index = (*index).min(self.strokes.len());
+                self.strokes.insert(index, stroke.clone());
+            }
+            EditCommand::ClearAll { strokes } => {
+                self.strokes = strokes.clone();
+            }
+            EditCommand::RemoveStrokes { removed } => {
+                for (index, stroke) in removed {
+                    let index = (*index).min(self.strokes.len());
+                    self.strokes.insert(index, stroke.clone());
+                }
+            }
+            EditCommand::SetVisibility { index, visible } => {
+                if let Some(stroke) = self.strokes.get_mut(*index) {
+                    stroke.visible = !*visible;
+                }
+            }
+            EditCommand::ReplaceStroke { index, before, .. } => {
+                if let Some(stroke) = self.strokes.get_mut(*index) {
+                    *stroke = before.clone();
+                }
+            }
+            EditCommand::ReplaceStrokes { changes } => {
+                for (index, before, _) in changes {
+                    if let Some(stroke) = self.strokes.get_mut(*index) {
+                        *stroke = before.clone();
+                    }
+                }
+            }
+            EditCommand::ReplaceScreenshot {
+                before,
+                full_resolution,
+                ..
+            } => {
+                self.screenshot_change = Some(ScreenshotChange {
+                    surface: before.clone(),
+                    full_resolution: full_resolution.clone(),
+                });
+            }
         }
+
+        self.redo_stack.push(command);
+        self.after_history_change();
+        true
     }
 
-    pub fn finish_stroke(&mut self) {
-        if let Some(mut stroke) = self.current_stroke.take() {
-            stroke.finish();
-            self.strokes.push(stroke);
+    pub fn redo(&mut self) -> bool {
+        let command = match self.redo_stack.pop() {
+            Some(command) => command,
+            None => return false,
+        };
+
+        match &command {
+            EditCommand::AddStroke { index, stroke } => {
+                let index = (*index).min(self.strokes.len());
+                self.strokes.insert(index, stroke.clone());
+            }
+            EditCommand::RemoveStroke { index, .. } => {
+                if *index < self.strokes.len() {
+                    self.strokes.remove(*index);
+                }
+            }
+            EditCommand::ClearAll { .. } => {
+                self.strokes.clear();
+            }
+            EditCommand::RemoveStrokes { removed } => {
+                for (index, _) in removed.iter().rev() {
+                    if *index < self.strokes.len() {
+                        self.strokes.remove(*index);
+                    }
+                }
+            }
+            EditCommand::SetVisibility { index, visible } => {
+                if let Some(stroke) = self.strokes.get_mut(*index) {
+                    stroke.visible = *visible;
+                }
+            }
+            EditCommand::ReplaceStroke { index, after, .. } => {
+                if let Some(stroke) = self.strokes.get_mut(*index) {
+                    *stroke = after.clone();
+                }
+            }
+            EditCommand::ReplaceStrokes { changes } => {
+                for (index, _, after) in changes {
+                    if let Some(stroke) = self.strokes.get_mut(*index) {
+                        *stroke = after.clone();
+                    }
+                }
+            }
+            EditCommand::ReplaceScreenshot { after, .. } => {
+                self.screenshot_change = Some(ScreenshotChange {
+                    surface: after.clone(),
+                    full_resolution: None,
+                });
+            }
         }
+
+        self.undo_stack.push(command);
+        self.after_history_change();
+        true
     }
 
-    pub fn cancel_stroke(&mut self) {
+    // Indices may have shifted, so drop anything that refers to a stroke
+    fn after_history_change(&mut self) {
         self.current_stroke = None;
+        self.snap_indicator = None;
+        self.selected_stroke = None;
+        self.selected_group.clear();
+        self.group_drag = None;
+        self.stamp_drag = None;
+        self.shape_resize = None;
+        self.resumed_stroke = None;
+        self.mark_changed();
     }
 
-    pub fn clear_all(&mut self) {
-        let stroke_count = self.strokes.len();
-        info!("Clearing {} annotations", stroke_count);
-        self.strokes.clear();
-        self.current_stroke = None;
-        info!("All annotations cleared");
+    // The palette offered in the toolbar, in order. Names and colors are
+    // kept as pairs in this one list, which everything that offers or
+    // matches palette colors reads, so they can't drift apart.
+    pub fn get_predefined_colors() -> &'static [(String, RGBA)] {
+        PALETTE.get_or_init(Self::load_palette)
     }
 
-    pub fn draw_all(&self, ctx: &Context) {
-        // Draw all finished strokes
-        for stroke in &self.strokes {
-            stroke.draw(ctx);
-        }
+    fn load_palette() -> Vec<(String, RGBA)> {
+        Self::palette_from(&preferences::get().ui.palette)
+    }
 
-        // Draw current stroke if any
-        if let Some(ref stroke) = self.current_stroke {
-            stroke.draw(ctx);
+    // The palette from the preferences' entries, skipping colors that don't
+    // parse. Without any usable entry the built-in colors are offered.
+    fn palette_from(entries: &[PaletteColor]) -> Vec<(String, RGBA)> {
+        let palette: Vec<_> = entries
+            .iter()
+            .filter_map(|entry| match RGBA::parse(entry.color.as_str()) {
+                Ok(color) => Some((entry.name.clone(), color)),
+                Err(_) => {
+                    warn!(
+                        "Invalid palette color '{}' for '{}'",
+                        entry.color, entry.name
+                    );
+                    None
+                }
+            })
+            .fold(Vec::new(), |mut palette, (name, color)| {
+                // The toolbar finds a color by its name, so names are unique
+                if palette.iter().any(|(existing, _)| *existing == name) {
+                    warn!(
+                        "Palette color '{}' is listed twice, keeping the first",
+                        name
+                    );
+                } else {
+                    palette.push((name, color));
+                }
+                palette
+            });
+        if !palette.is_empty() {
+            info!("Using a custom palette of {} colors", palette.len());
+            return palette;
         }
+
+        [
+            ("Red", RGBA::new(1.0, 0.0, 0.0, 1.0)),
+            ("Green", RGBA::new(0.0, 0.8, 0.0, 1.0)),
+            ("Blue", RGBA::new(0.0, 0.0, 1.0, 1.0)),
+            ("Yellow", RGBA::new(1.0, 0.9, 0.0, 1.0)),
+            ("Pink", RGBA::new(1.0, 0.4, 0.7, 1.0)),
+            ("Cyan", RGBA::new(0.0, 0.8, 0.8, 1.0)),
+            ("Black", RGBA::new(0.0, 0.0, 0.0, 1.0)),
+            ("White", RGBA::new(1.0, 1.0, 1.0, 1.0)),
+        ]
+        .into_iter()
+        .map(|(name, color)| (name.to_string(), color))
+        .collect()
     }
-}
 
-impl Default for AnnotationTools {
-    fn default() -> Self {
-        Self::new()
+    // The color the toolbar combo emits for the entry with this label
+    pub fn palette_color(palette: &[(String, RGBA)], name: &str) -> Option<RGBA> {
+        palette
+            .iter()
+            .find(|(candidate, _)| candidate == name)
+            .map(|(_, color)| *color)
+    }
+
+    // The combo label to show for a color, ignoring its alpha
+    pub fn palette_name(palette: &[(String, RGBA)], color: RGBA) -> Option<&str> {
+        palette
+            .iter()
+            .find(|(_, candidate)| {
+                (candidate.red() - color.red()).abs() < f32::EPSILON
+                    && (candidate.green() - color.green()).abs() < f32::EPSILON
+                    && (candidate.blue() - color.blue()).abs() < f32::EPSILON
+            })
+            .map(|(name, _)| name.as_str())
+    }
+
+    // Switch to the next predefined color, wrapping around. A color that is
+    // not in the palette starts the cycle from the first one.
+    pub fn cycle_color(&mut self) -> (&'static str, RGBA) {
+        let colors = Self::get_predefined_colors();
+        let current = self.current_color;
+        let next = colors
+            .iter()
+            .position(|(_, color)| {
+                (color.red() - current.red()).abs() < f32::EPSILON
+                    && (color.green() - current.green()).abs() < f32::EPSILON
+                    && (color.blue() - current.blue()).abs() < f32::EPSILON
+            })
+            .map_or(0, |index| (index + 1) % colors.len());
+
+        let (name, color) = &colors[next];
+        self.set_color(*color);
+        (name.as_str(), *color)
+    }
+
+    fn default_tool_colors() -> HashMap<ToolType, RGBA> {
+        let red = RGBA::new(1.0, 0.0, 0.0, 1.0);
+        let yellow = RGBA::new(1.0, 0.9, 0.0, 1.0);
+
+        HashMap::from([
+            (ToolType::Pencil, red),
+            (ToolType::Line, red),
+            (ToolType::Arrow, red),
+            (ToolType::Highlighter, yellow),
+            (ToolType::Text, red),
+            (ToolType::Rectangle, red),
+            (ToolType::Stamp, red),
+        ])
+    }
+
+    pub fn set_tool(&mut self, tool: ToolType) {
+        // Commit any text being typed before switching away
+        if self.is_editing_text() {
+            self.finish_stroke();
+        }
+
+        // Selecting the current tool again keeps the one to swap back to
+        if tool != self.current_tool {
+            self.previous_tool = Some(self.current_tool);
+        }
+        self.current_tool = tool;
+
+        // Set default thickness based on tool
+        self.current_thickness = match tool {
+            ToolType::Pencil => 3.0,
+            ToolType::Line => 2.0,
+            ToolType::Arrow => 2.0,
+            ToolType::Highlighter => 8.0,
+            ToolType::Text => 4.0,
+            ToolType::Rectangle => 3.0,
+            ToolType::Stamp => 3.0,
+            ToolType::Measure => 1.0,
+            ToolType::Eraser => 1.0,
+        };
+
+        // Each tool remembers the color it was last used with
+        if let Some(color) = self.tool_colors.get(&tool) {
+            self.current_color = *color;
+        }
+    }
+
+    // Switch to the tool used before the current one, which in turn becomes
+    // the previous tool. Returns the new tool, or None before any switch.
+    pub fn swap_tool(&mut self) -> Option<ToolType> {
+        let previous = self.previous_tool?;
+        self.set_tool(previous);
+        Some(previous)
+    }
+
+    pub fn set_color(&mut self, color: RGBA) {
+        self.current_color = color;
+        self.tool_colors.insert(self.current_tool, color);
+    }
+
+    // The current color as the current tool will paint it, e.g. see-through
+    // for the highlighter
+    pub fn effective_color(&self) -> RGBA {
+        let color = self.current_color;
+        match self.current_tool {
+            ToolType::Highlighter => RGBA::new(
+                color.red(),
+                color.green(),
+                color.blue(),
+                DrawingStroke::highlighter_alpha() as f32,
+            ),
+            _ => color,
+        }
+    }
+
+    pub fn set_thickness(&mut self, thickness: f64) {
+        self.current_thickness = thickness;
+    }
+
+    pub fn set_corner_radius(&mut self, radius: f64) {
+        self.current_corner_radius = radius.max(0.0);
+    }
+
+    pub fn set_stabilizer(&mut self, strength: f64) {
+        self.current_stabilizer = strength.clamp(0.0, MAX_STABILIZER);
+    }
+
+    pub fn set_pixel_snap(&mut self, pixel_snap: bool) {
+        self.current_pixel_snap = pixel_snap;
+    }
+
+    pub fn set_cap_style(&mut self, cap_style: CapStyle) {
+        self.current_cap_style = cap_style;
+    }
+
+    pub fn set_join_style(&mut self, join_style: JoinStyle) {
+        self.current_join_style = join_style;
+    }
+
+    pub fn set_arrow_shape(&mut self, shape: ArrowShape) {
+        self.current_arrow_shape = shape;
+    }
+
+    pub fn set_arrow_head(&mut self, head: ArrowHead) {
+        self.current_arrow_head = head;
+    }
+
+    pub fn start_stroke(&mut self, point: Point) {
+        let mut stroke = DrawingStroke::new(
+            self.current_tool,
+            self.current_color,
+            self.current_thickness,
+        );
+        stroke.corner_radius = self.current_corner_radius;
+        stroke.pixel_snap = self.current_pixel_snap;
+        stroke.cap_style = self.current_cap_style;
+        stroke.join_style = self.current_join_style;
+        stroke.arrow_shape = self.current_arrow_shape;
+        stroke.arrow_head = self.current_arrow_head;
+        stroke.add_point(point);
+        self.current_stroke = Some(stroke);
+    }
+
+    pub fn add_point_to_stroke(&mut self, point: Point) {
+        let strength = self.current_stabilizer;
+        if let Some(ref mut stroke) = self.current_stroke {
+            let point = Self::stabilize(stroke, point, strength);
+            stroke.add_point(point);
+        } else if let Some((index, _)) = self.resumed_stroke {
+            if let Some(stroke) = self.strokes.get_mut(index) {
+                let point = Self::stabilize(stroke, point, strength);
+                stroke.add_point(point);
+                self.mark_changed();
+            }
+        }
+    }
+
+    // Lazy brush for freehand strokes: the drawn point only covers part of
+    // the way to the pointer, so jitter is averaged out. The stored points
+    // are the steadied ones, so the finished stroke looks as drawn.
+    fn stabilize(stroke: &DrawingStroke, point: Point, strength: f64) -> Point {
+        if strength <= 0.0 || !matches!(stroke.tool_type, ToolType::Pencil | ToolType::Highlighter)
+        {
+            return point;
+        }
+        let Some(last) = stroke.points.last() else {
+            return point;
+        };
+
+        let follow = 1.0 - strength;
+        Point::new(
+            last.x + (point.x - last.x) * follow,
+            last.y + (point.y - last.y) * follow,
+        )
+    }
+
+    // Continue the most recent visible stroke of the current tool instead of
+    // starting a new one, so pencil, highlighter and line strokes can be
+    // built up over several drags. A line turns a corner where it used to
+    // end. Returns false when there is no such stroke to continue.
+    pub fn resume_last_stroke(&mut self, point: Point) -> bool {
+        let tool = self.current_tool;
+        if !matches!(
+            tool,
+            ToolType::Pencil | ToolType::Highlighter | ToolType::Line
+        ) {
+            return false;
+        }
+        let Some(index) = self
+            .strokes
+            .iter()
+            .rposition(|stroke| stroke.visible && stroke.tool_type == tool)
+        else {
+            return false;
+        };
+
+        let stroke = &mut self.strokes[index];
+        let original = stroke.clone();
+        if tool == ToolType::Line {
+            // The press only anchors the drag; the line goes on from its end
+            stroke.corners.push(stroke.points.len() - 1);
+        } else {
+            stroke.add_point(point);
+        }
+        self.resumed_stroke = Some((index, original));
+        self.mark_changed();
+        true
+    }
+
+    pub fn end_stroke_resume(&mut self) {
+        let Some((index, original)) = self.resumed_stroke.take() else {
+            return;
+        };
+        let Some(stroke) = self.strokes.get_mut(index) else {
+            return;
+        };
+
+        // A line that was clicked without dragging gains nothing
+        if stroke.points.len() == original.points.len() {
+            *stroke = original;
+            self.mark_changed();
+            return;
+        }
+        let after = stroke.clone();
+        self.record(EditCommand::ReplaceStroke {
+            index,
+            before: original,
+            after,
+        });
+    }
+
+    // Snap the live endpoint of a line, arrow or rectangle to a nearby endpoint
+    // of another stroke, or failing that to the image edges
+    pub fn snap_point(&mut self, point: Point, image_width: f64, image_height: f64) -> Point {
+        self.snap_indicator = None;
+
+        let is_snappable = |tool_type: ToolType| {
+            matches!(
+                tool_type,
+                ToolType::Line | ToolType::Arrow | ToolType::Rectangle
+            )
+        };
+        match self.current_stroke {
+            Some(ref stroke) if is_snappable(stroke.tool_type) => {}
+            _ => return point,
+        }
+
+        let nearest_endpoint = self
+            .strokes
+            .iter()
+            .filter(|stroke| stroke.visible && is_snappable(stroke.tool_type))
+            .flat_map(|stroke| {
+                stroke
+                    .points
+                    .first()
+                    .into_iter()
+                    .chain(stroke.points.last())
+            })
+            .map(|endpoint| {
+                let distance = (endpoint.x - point.x).hypot(endpoint.y - point.y);
+                (distance, endpoint)
+            })
+            .filter(|(distance, _)| *distance <= SNAP_DISTANCE)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, endpoint)| endpoint.clone());
+
+        if let Some(endpoint) = nearest_endpoint {
+            self.snap_indicator = Some(endpoint.clone());
+            return endpoint;
+        }
+
+        let snap_axis = |value: f64, limit: f64| {
+            if value.abs() <= SNAP_DISTANCE {
+                Some(0.0)
+            } else if (limit - value).abs() <= SNAP_DISTANCE {
+                Some(limit)
+            } else {
+                None
+            }
+        };
+        let snapped_x = snap_axis(point.x, image_width);
+        let snapped_y = snap_axis(point.y, image_height);
+
+        if snapped_x.is_none() && snapped_y.is_none() {
+            return point;
+        }
+
+        let snapped = Point::new(snapped_x.unwrap_or(point.x), snapped_y.unwrap_or(point.y));
+        self.snap_indicator = Some(snapped.clone());
+        snapped
+    }
+
+    pub fn finish_stroke(&mut self) {
+        self.snap_indicator = None;
+        if let Some(mut stroke) = self.current_stroke.take() {
+            // Don't keep text annotations that never received any input
+            if stroke.tool_type == ToolType::Text && stroke.text.is_empty() {
+                return;
+            }
+            stroke.finish();
+            self.record(EditCommand::AddStroke {
+                index: self.strokes.len(),
+                stroke: stroke.clone(),
+            });
+            self.strokes.push(stroke);
+            self.mark_changed();
+        }
+    }
+
+    pub fn start_text(&mut self, point: Point, max_width: f64) {
+        let mut stroke =
+            DrawingStroke::new(ToolType::Text, self.current_color, self.current_thickness);
+        stroke.add_point(point);
+        stroke.max_width = Some(max_width.max(1.0));
+        self.current_stroke = Some(stroke);
+    }
+
+    // Dragging out a text box sets its wrap width, never past the image edge
+    pub fn resize_text_box(&mut self, edge_x: f64, image_width: f64) {
+        if let Some(ref mut stroke) = self.current_stroke {
+            if stroke.tool_type != ToolType::Text {
+                return;
+            }
+            if let Some(origin) = stroke.points.first() {
+                let limit = (image_width - origin.x).max(1.0);
+                let width = edge_x - origin.x;
+                stroke.max_width = Some(if width > 20.0 {
+                    width.min(limit)
+                } else {
+                    limit
+                });
+            }
+        }
+    }
+
+    pub fn is_editing_text(&self) -> bool {
+        matches!(self.current_stroke, Some(ref stroke) if stroke.tool_type == ToolType::Text)
+    }
+
+    pub fn insert_text(&mut self, text: &str) {
+        if let Some(ref mut stroke) = self.current_stroke {
+            if stroke.tool_type == ToolType::Text {
+                stroke.text.push_str(text);
+            }
+        }
+    }
+
+    pub fn delete_last_char(&mut self) {
+        if let Some(ref mut stroke) = self.current_stroke {
+            if stroke.tool_type == ToolType::Text {
+                stroke.text.pop();
+            }
+        }
+    }
+
+    pub fn cancel_stroke(&mut self) {
+        // A continued stroke goes back to how it was
+        if let Some((index, original)) = self.resumed_stroke.take() {
+            if let Some(stroke) = self.strokes.get_mut(index) {
+                *stroke = original;
+                self.mark_changed();
+            }
+        }
+        self.current_stroke = None;
+        self.snap_indicator = None;
+        self.measurement = None;
+    }
+
+    pub fn start_measurement(&mut self, point: Point) {
+        self.measurement = Some((point.clone(), point));
+    }
+
+    pub fn update_measurement(&mut self, point: Point) {
+        if let Some((_, ref mut end)) = self.measurement {
+            *end = point;
+        }
+    }
+
+    pub fn end_measurement(&mut self) {
+        self.measurement = None;
+    }
+
+    pub fn is_measuring(&self) -> bool {
+        self.measurement.is_some()
+    }
+
+    pub fn start_erase_box(&mut self, point: Point) {
+        self.erase_box = Some((point.clone(), point));
+    }
+
+    pub fn update_erase_box(&mut self, point: Point) {
+        if let Some((_, ref mut end)) = self.erase_box {
+            *end = point;
+        }
+    }
+
+    pub fn is_erasing(&self) -> bool {
+        self.erase_box.is_some()
+    }
+
+    // Remove every stroke inside the dragged box and return how many went
+    pub fn end_erase_box(&mut self) -> usize {
+        let (start, end) = match self.erase_box.take() {
+            Some(corners) => corners,
+            None => return 0,
+        };
+
+        self.remove_strokes_in_rect(
+            start.x.min(end.x),
+            start.y.min(end.y),
+            (end.x - start.x).abs(),
+            (end.y - start.y).abs(),
+        )
+    }
+
+    // Remove the visible strokes with at least one point inside the
+    // rectangle, as one undoable step. Returns the number removed.
+    pub fn remove_strokes_in_rect(&mut self, x: f64, y: f64, width: f64, height: f64) -> usize {
+        let contains = |point: &Point| {
+            point.x >= x && point.x <= x + width && point.y >= y && point.y <= y + height
+        };
+
+        let mut removed = Vec::new();
+        let mut kept = Vec::with_capacity(self.strokes.len());
+        for (index, stroke) in std::mem::take(&mut self.strokes).into_iter().enumerate() {
+            if stroke.visible && stroke.points.iter().any(contains) {
+                removed.push((index, stroke));
+            } else {
+                kept.push(stroke);
+            }
+        }
+        self.strokes = kept;
+
+        let count = removed.len();
+        if count > 0 {
+            info!("Erased {} annotations", count);
+            self.record(EditCommand::RemoveStrokes { removed });
+            self.selected_stroke = None;
+            self.selected_group.clear();
+            self.stamp_drag = None;
+            self.mark_changed();
+        }
+        count
+    }
+
+    // Dashed marquee of the eraser box, sized to stay constant on screen
+    pub fn draw_erase_box(&self, ctx: &Context) {
+        let (start, end) = match &self.erase_box {
+            Some(corners) => corners,
+            None => return,
+        };
+
+        let scale = ctx.matrix().xx().abs().max(f64::EPSILON);
+        let (x, y) = (start.x.min(end.x), start.y.min(end.y));
+        let (width, height) = ((end.x - start.x).abs(), (end.y - start.y).abs());
+
+        ctx.save().ok();
+        ctx.rectangle(x, y, width, height);
+        ctx.set_source_rgba(1.0, 0.3, 0.3, 0.15);
+        ctx.fill_preserve().ok();
+        ctx.set_line_width(1.5 / scale);
+        ctx.set_dash(&[6.0 / scale, 4.0 / scale], 0.0);
+        ctx.set_source_rgba(1.0, 0.3, 0.3, 0.9);
+        ctx.stroke().ok();
+        ctx.restore().ok();
+    }
+
+    // Distance in image pixels and angle in degrees, counterclockwise from
+    // the positive x axis as on screen (image y grows downwards)
+    pub fn measurement(&self) -> Option<(f64, f64)> {
+        let (start, end) = self.measurement.as_ref()?;
+        let angle = (start.y - end.y).atan2(end.x - start.x).to_degrees();
+        Some((start.distance_to(end), angle))
+    }
+
+    // Bounds of the most recent rectangle as (x, y, width, height)
+    pub fn last_rectangle_bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        let stroke =
+            self.strokes.iter().rev().find(|stroke| {
+                stroke.tool_type == ToolType::Rectangle && stroke.points.len() >= 2
+            })?;
+        let start = stroke.points.first()?;
+        let end = stroke.points.last()?;
+
+        Some((
+            start.x.min(end.x),
+            start.y.min(end.y),
+            (end.x - start.x).abs(),
+            (end.y - start.y).abs(),
+        ))
+    }
+
+    // Drop the current stamp centered on the point, as a finished stroke
+    pub fn place_stamp(&mut self, point: Point) {
+        let surface = match self.stamps.get(self.current_stamp) {
+            Some(stamp) => stamp.surface.clone(),
+            None => return,
+        };
+
+        let mut stroke =
+            DrawingStroke::new(ToolType::Stamp, self.current_color, self.current_thickness);
+        stroke.add_point(point);
+        stroke.stamp = Some(surface);
+        stroke.finish();
+
+        self.record(EditCommand::AddStroke {
+            index: self.strokes.len(),
+            stroke: stroke.clone(),
+        });
+        self.strokes.push(stroke);
+        self.selected_stroke = Some(self.strokes.len() - 1);
+        self.mark_changed();
+    }
+
+    // Topmost visible stamp under the point
+    pub fn stamp_at(&self, point: &Point) -> Option<usize> {
+        self.strokes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, stroke)| {
+                if !stroke.visible {
+                    return None;
+                }
+                let (x, y, width, height) = stroke.stamp_bounds()?;
+                let hit =
+                    point.x >= x && point.x <= x + width && point.y >= y && point.y <= y + height;
+                hit.then_some(index)
+            })
+    }
+
+    pub fn begin_stamp_drag(&mut self, index: usize, point: &Point) {
+        if let Some(stroke) = self.strokes.get(index) {
+            if let Some(center) = stroke.points.first() {
+                self.stamp_drag = Some((
+                    index,
+                    center.x - point.x,
+                    center.y - point.y,
+                    stroke.clone(),
+                ));
+                self.selected_stroke = Some(index);
+            }
+        }
+    }
+
+    pub fn is_dragging_stamp(&self) -> bool {
+        self.stamp_drag.is_some()
+    }
+
+    pub fn drag_stamp_to(&mut self, point: &Point) {
+        if let Some((index, offset_x, offset_y, _)) = self.stamp_drag {
+            if let Some(center) = self
+                .strokes
+                .get_mut(index)
+                .and_then(|stroke| stroke.points.first_mut())
+            {
+                *center = Point::new(point.x + offset_x, point.y + offset_y);
+                self.mark_changed();
+            }
+        }
+    }
+
+    pub fn end_stamp_drag(&mut self) {
+        if let Some((index, _, _, before)) = self.stamp_drag.take() {
+            let after = self
+                .strokes
+                .get(index)
+                .filter(|stroke| {
+                    let center = |stroke: &DrawingStroke| {
+                        stroke.points.first().map(|point| (point.x, point.y))
+                    };
+                    center(stroke) != center(&before)
+                })
+                .cloned();
+            // A click on a stamp without dragging changes nothing
+            if let Some(after) = after {
+                self.record(EditCommand::ReplaceStroke {
+                    index,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+
+    pub fn is_stamp_selected(&self) -> bool {
+        self.selected_stroke
+            .and_then(|index| self.strokes.get(index))
+            .map_or(false, |stroke| stroke.stamp.is_some())
+    }
+
+    pub fn scale_selected_stamp(&mut self, factor: f64) {
+        let Some((index, stroke)) = self
+            .selected_stroke
+            .and_then(|index| Some((index, self.strokes.get_mut(index)?)))
+            .filter(|(_, stroke)| stroke.stamp.is_some())
+        else {
+            return;
+        };
+
+        let before = stroke.clone();
+        stroke.stamp_scale = (stroke.stamp_scale * factor).clamp(0.1, 10.0);
+        // Already at the smallest or largest size
+        if stroke.stamp_scale == before.stamp_scale {
+            return;
+        }
+        let after = stroke.clone();
+        self.record(EditCommand::ReplaceStroke {
+            index,
+            before,
+            after,
+        });
+        self.mark_changed();
+    }
+
+    // Start resizing the selected shape if the point is on one of its
+    // handles, or bending a selected curved arrow if it is on the control
+    // point. `tolerance` is how far from a handle still hits it, in image
+    // pixels.
+    pub fn begin_shape_resize(&mut self, point: &Point, tolerance: f64) -> bool {
+        let Some((index, stroke)) = self
+            .selected_stroke
+            .and_then(|index| Some((index, self.strokes.get(index)?)))
+            .filter(|(_, stroke)| stroke.visible)
+        else {
+            return false;
+        };
+        let hits = |position: &Point| {
+            (point.x - position.x).abs() <= tolerance && (point.y - position.y).abs() <= tolerance
+        };
+
+        let handle = if let Some(control) = stroke.control_handle() {
+            hits(&control).then_some(None)
+        } else {
+            stroke.shape_box().and_then(|shape_box| {
+                ResizeHandle::ALL
+                    .into_iter()
+                    .find(|handle| hits(&handle.position(shape_box)))
+                    .map(Some)
+            })
+        };
+
+        match handle {
+            Some(handle) => {
+                self.shape_resize = Some(ShapeResize {
+                    index,
+                    handle,
+                    original: stroke.clone(),
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_resizing_shape(&self) -> bool {
+        self.shape_resize.is_some()
+    }
+
+    // Move the dragged handle's edges to the point. With `keep_aspect` a
+    // corner handle keeps the shape's original proportions.
+    pub fn resize_shape_to(&mut self, point: &Point, keep_aspect: bool) {
+        let Some(ref resize) = self.shape_resize else {
+            return;
+        };
+        let index = resize.index;
+        let Some(handle) = resize.handle else {
+            if let Some(stroke) = self.strokes.get_mut(index) {
+                stroke.control_point = Some(point.clone());
+                self.mark_changed();
+            }
+            return;
+        };
+        let Some((left, top, right, bottom)) = resize.original.shape_box() else {
+            return;
+        };
+
+        // The edges opposite the handle stay where they are
+        let (anchor_x, mut x) = match handle.horizontal {
+            -1 => (right, point.x),
+            1 => (left, point.x),
+            _ => (left, right),
+        };
+        let (anchor_y, mut y) = match handle.vertical {
+            -1 => (bottom, point.y),
+            1 => (top, point.y),
+            _ => (top, bottom),
+        };
+
+        let (width, height) = (right - left, bottom - top);
+        if keep_aspect && handle.is_corner() && width > 0.0 && height > 0.0 {
+            // Follow whichever side has been dragged further, relatively
+            let scale = ((x - anchor_x).abs() / width).max((y - anchor_y).abs() / height);
+            x = anchor_x + (x - anchor_x).signum() * width * scale;
+            y = anchor_y + (y - anchor_y).signum() * height * scale;
+        }
+
+        if let Some(stroke) = self.strokes.get_mut(index) {
+            stroke.points = vec![Point::new(anchor_x, anchor_y), Point::new(x, y)];
+            self.mark_changed();
+        }
+    }
+
+    pub fn end_shape_resize(&mut self) {
+        if let Some(resize) = self.shape_resize.take() {
+            let control = |stroke: &DrawingStroke| {
+                stroke
+                    .control_point
+                    .as_ref()
+                    .map(|point| (point.x, point.y))
+            };
+            let after = self
+                .strokes
+                .get(resize.index)
+                .filter(|stroke| {
+                    stroke.shape_box() != resize.original.shape_box()
+                        || control(stroke) != control(&resize.original)
+                })
+                .cloned();
+            // A click on a handle without dragging changes nothing
+            if let Some(after) = after {
+                self.record(EditCommand::ReplaceStroke {
+                    index: resize.index,
+                    before: resize.original,
+                    after,
+                });
+            }
+        }
+    }
+
+    // Topmost visible stroke whose bounds contain the point
+    pub fn stroke_at(&self, point: &Point) -> Option<usize> {
+        // Text needs a context to be measured; nothing is drawn on it
+        let surface = ImageSurface::create(Format::ARgb32, 1, 1).ok()?;
+        let ctx = Context::new(&surface).ok()?;
+
+        self.strokes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, stroke)| {
+                if !stroke.visible {
+                    return None;
+                }
+                let (x, y, width, height) = stroke.bounds(&ctx)?;
+                let hit =
+                    point.x >= x && point.x <= x + width && point.y >= y && point.y <= y + height;
+                hit.then_some(index)
+            })
+    }
+
+    // Add the stroke to the group, or take it out if it is already there
+    pub fn toggle_group_stroke(&mut self, index: usize) {
+        if index >= self.strokes.len() {
+            return;
+        }
+        match self
+            .selected_group
+            .iter()
+            .position(|&selected| selected == index)
+        {
+            Some(position) => {
+                self.selected_group.remove(position);
+            }
+            None => {
+                self.selected_group.push(index);
+                self.selected_group.sort_unstable();
+            }
+        }
+    }
+
+    pub fn clear_group(&mut self) {
+        self.selected_group.clear();
+        self.group_drag = None;
+        self.group_box = None;
+    }
+
+    pub fn start_group_box(&mut self, point: Point) {
+        self.group_box = Some((point.clone(), point));
+    }
+
+    pub fn update_group_box(&mut self, point: Point) {
+        if let Some((_, ref mut end)) = self.group_box {
+            *end = point;
+        }
+    }
+
+    pub fn is_selecting_group(&self) -> bool {
+        self.group_box.is_some()
+    }
+
+    // Add the visible strokes with at least one point inside the dragged box
+    // to the group, the same way the eraser picks them
+    pub fn end_group_box(&mut self) {
+        let Some((start, end)) = self.group_box.take() else {
+            return;
+        };
+        let (left, top) = (start.x.min(end.x), start.y.min(end.y));
+        let (right, bottom) = (start.x.max(end.x), start.y.max(end.y));
+        let contains = |point: &Point| {
+            point.x >= left && point.x <= right && point.y >= top && point.y <= bottom
+        };
+
+        for (index, stroke) in self.strokes.iter().enumerate() {
+            if stroke.visible
+                && stroke.points.iter().any(contains)
+                && !self.selected_group.contains(&index)
+            {
+                self.selected_group.push(index);
+            }
+        }
+        self.selected_group.sort_unstable();
+    }
+
+    // Combined bounding box of the group as (x, y, width, height)
+    fn group_bounds(&self, ctx: &Context) -> Option<(f64, f64, f64, f64)> {
+        let (left, top, right, bottom) = self
+            .selected_group
+            .iter()
+            .filter_map(|&index| self.strokes.get(index))
+            .filter(|stroke| stroke.visible)
+            .filter_map(|stroke| stroke.bounds(ctx))
+            .map(|(x, y, width, height)| (x, y, x + width, y + height))
+            .reduce(|(left, top, right, bottom), (x1, y1, x2, y2)| {
+                (left.min(x1), top.min(y1), right.max(x2), bottom.max(y2))
+            })?;
+        Some((left, top, right - left, bottom - top))
+    }
+
+    // Start moving the group if the point is inside its combined bounds
+    pub fn begin_group_drag(&mut self, point: &Point) -> bool {
+        let Ok(surface) = ImageSurface::create(Format::ARgb32, 1, 1) else {
+            return false;
+        };
+        let Ok(ctx) = Context::new(&surface) else {
+            return false;
+        };
+        let Some((x, y, width, height)) = self.group_bounds(&ctx) else {
+            return false;
+        };
+        if point.x < x || point.x > x + width || point.y < y || point.y > y + height {
+            return false;
+        }
+
+        let originals = self
+            .selected_group
+            .iter()
+            .filter_map(|&index| Some((index, self.strokes.get(index)?.clone())))
+            .collect();
+        self.group_drag = Some((point.clone(), originals));
+        true
+    }
+
+    pub fn is_dragging_group(&self) -> bool {
+        self.group_drag.is_some()
+    }
+
+    pub fn drag_group_to(&mut self, point: &Point) {
+        let Some((ref start, ref originals)) = self.group_drag else {
+            return;
+        };
+        let (dx, dy) = (point.x - start.x, point.y - start.y);
+
+        for (index, original) in originals {
+            if let Some(stroke) = self.strokes.get_mut(*index) {
+                *stroke = original.clone();
+                stroke.translate(dx, dy);
+            }
+        }
+        self.mark_changed();
+    }
+
+    // Record the move as one undoable step, unless nothing moved
+    pub fn end_group_drag(&mut self) {
+        let Some((_, originals)) = self.group_drag.take() else {
+            return;
+        };
+
+        // Every stroke moved by the same amount, so one tells for all
+        let moved = originals.first().map_or(false, |(index, before)| {
+            let start = before.points.first();
+            let end = self
+                .strokes
+                .get(*index)
+                .and_then(|stroke| stroke.points.first());
+            matches!((start, end), (Some(start), Some(end)) if start.distance_to(end) > 0.0)
+        });
+        if moved {
+            let changes = originals
+                .into_iter()
+                .filter_map(|(index, before)| {
+                    Some((index, before, self.strokes.get(index)?.clone()))
+                })
+                .collect();
+            self.record(EditCommand::ReplaceStrokes { changes });
+        }
+    }
+
+    // Remove every stroke in the group as one undoable step and return how
+    // many went
+    pub fn delete_group(&mut self) -> usize {
+        let mut indices = std::mem::take(&mut self.selected_group);
+        indices.retain(|&index| index < self.strokes.len());
+        if indices.is_empty() {
+            return 0;
+        }
+
+        // Back to front, so earlier indices stay valid while removing
+        let mut removed: Vec<_> = indices
+            .iter()
+            .rev()
+            .map(|&index| (index, self.strokes.remove(index)))
+            .collect();
+        removed.reverse();
+
+        let count = removed.len();
+        info!("Deleted {} selected annotations", count);
+        self.record(EditCommand::RemoveStrokes { removed });
+        self.selected_stroke = None;
+        self.stamp_drag = None;
+        self.group_drag = None;
+        self.mark_changed();
+        count
+    }
+
+    // Give every stroke in the group the color, as one undoable step
+    pub fn recolor_group(&mut self, color: RGBA) {
+        let mut changes = Vec::new();
+        for &index in &self.selected_group {
+            if let Some(stroke) = self.strokes.get_mut(index) {
+                let before = stroke.clone();
+                stroke.color = color;
+                changes.push((index, before, stroke.clone()));
+            }
+        }
+
+        if !changes.is_empty() {
+            self.record(EditCommand::ReplaceStrokes { changes });
+            self.mark_changed();
+        }
+    }
+
+    pub fn select_stroke(&mut self, index: Option<usize>) {
+        self.selected_stroke = index.filter(|&index| index < self.strokes.len());
+    }
+
+    // Move the selection to the next visible stroke, or the previous one
+    // when `forward` is false, wrapping around at either end. With nothing
+    // selected it starts from the first or last stroke.
+    pub fn cycle_selection(&mut self, forward: bool) -> Option<usize> {
+        let count = self.strokes.len();
+        if count == 0 {
+            return None;
+        }
+
+        let start = match (self.selected_stroke, forward) {
+            (Some(selected), _) if selected < count => selected,
+            (_, true) => count - 1,
+            (_, false) => 0,
+        };
+
+        self.selected_stroke = (1..=count)
+            .map(|step| {
+                if forward {
+                    (start + step) % count
+                } else {
+                    (start + count - step % count) % count
+                }
+            })
+            .find(|&index| self.strokes[index].visible);
+        self.selected_stroke
+    }
+
+    pub fn set_stroke_visible(&mut self, index: usize, visible: bool) {
+        let changed = match self.strokes.get_mut(index) {
+            Some(stroke) if stroke.visible != visible => {
+                stroke.visible = visible;
+                true
+            }
+            _ => false,
+        };
+
+        if changed {
+            self.record(EditCommand::SetVisibility { index, visible });
+            self.mark_changed();
+        }
+    }
+
+    // An empty note clears it
+    pub fn set_stroke_note(&mut self, index: usize, note: &str) {
+        let note = Some(note.trim().to_string()).filter(|note| !note.is_empty());
+        let Some(stroke) = self.strokes.get_mut(index) else {
+            return;
+        };
+        if stroke.note == note {
+            return;
+        }
+
+        let before = stroke.clone();
+        stroke.note = note;
+        let after = stroke.clone();
+        self.record(EditCommand::ReplaceStroke {
+            index,
+            before,
+            after,
+        });
+        self.mark_changed();
+    }
+
+    pub fn remove_stroke(&mut self, index: usize) {
+        if index >= self.strokes.len() {
+            return;
+        }
+
+        let stroke = self.strokes.remove(index);
+        self.record(EditCommand::RemoveStroke { index, stroke });
+        self.stamp_drag = None;
+        self.selected_group.clear();
+        self.selected_stroke = match self.selected_stroke {
+            Some(selected) if selected == index => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            selected => selected,
+        };
+        self.mark_changed();
+    }
+
+    pub fn clear_all(&mut self) {
+        let stroke_count = self.strokes.len();
+        info!("Clearing {} annotations", stroke_count);
+        if stroke_count > 0 {
+            let strokes = std::mem::take(&mut self.strokes);
+            self.record(EditCommand::ClearAll { strokes });
+        }
+        self.current_stroke = None;
+        self.selected_stroke = None;
+        self.selected_group.clear();
+        self.stamp_drag = None;
+        self.mark_changed();
+        info!("All annotations cleared");
+    }
+
+    pub fn draw_all(&self, ctx: &Context) {
+        self.draw_finished(ctx);
+        self.draw_current(ctx);
+    }
+
+    pub fn draw_finished(&self, ctx: &Context) {
+        for stroke in self.strokes.iter().filter(|stroke| stroke.visible) {
+            stroke.draw(ctx);
+        }
+    }
+
+    pub fn draw_current(&self, ctx: &Context) {
+        if let Some(ref stroke) = self.current_stroke {
+            stroke.draw(ctx);
+        }
+    }
+
+    // Dashed box around the stroke picked in the annotation list
+    pub fn draw_selection(&self, ctx: &Context) {
+        let bounds = self
+            .selected_stroke
+            .and_then(|index| self.strokes.get(index))
+            .and_then(|stroke| stroke.bounds(ctx));
+
+        if let Some((x, y, width, height)) = bounds {
+            ctx.save().ok();
+            ctx.set_source_rgba(0.2, 0.6, 1.0, 0.9);
+            ctx.set_line_width(1.5);
+            ctx.set_dash(&[6.0, 4.0], 0.0);
+            ctx.rectangle(x - 4.0, y - 4.0, width + 8.0, height + 8.0);
+            ctx.stroke().ok();
+            ctx.restore().ok();
+        }
+
+        self.draw_resize_handles(ctx);
+        self.draw_group_selection(ctx);
+    }
+
+    // One dashed box around the whole group, and the marquee of a selection
+    // box being dragged out, sized to stay constant on screen
+    fn draw_group_selection(&self, ctx: &Context) {
+        let scale = ctx.matrix().xx().abs().max(f64::EPSILON);
+
+        ctx.save().ok();
+        ctx.set_line_width(1.5 / scale);
+        ctx.set_dash(&[6.0 / scale, 4.0 / scale], 0.0);
+        if let Some((x, y, width, height)) = self.group_bounds(ctx) {
+            let margin = 4.0 / scale;
+            ctx.rectangle(
+                x - margin,
+                y - margin,
+                width + 2.0 * margin,
+                height + 2.0 * margin,
+            );
+            ctx.set_source_rgba(0.2, 0.6, 1.0, 0.9);
+            ctx.stroke().ok();
+        }
+        if let Some((start, end)) = &self.group_box {
+            ctx.rectangle(
+                start.x.min(end.x),
+                start.y.min(end.y),
+                (end.x - start.x).abs(),
+                (end.y - start.y).abs(),
+            );
+            ctx.set_source_rgba(0.2, 0.6, 1.0, 0.15);
+            ctx.fill_preserve().ok();
+            ctx.set_source_rgba(0.2, 0.6, 1.0, 0.9);
+            ctx.stroke().ok();
+        }
+        ctx.restore().ok();
+    }
+
+    // Square handles on the corners and edges of a selected shape, or a
+    // round one on a selected curved arrow's control point, kept the same
+    // size on screen whatever the zoom
+    fn draw_resize_handles(&self, ctx: &Context) {
+        let Some(stroke) = self
+            .selected_stroke
+            .and_then(|index| self.strokes.get(index))
+            .filter(|stroke| stroke.visible)
+        else {
+            return;
+        };
+
+        let scale = ctx.matrix().xx().abs().max(f64::EPSILON);
+        let size = RESIZE_HANDLE_SIZE / scale;
+
+        if let Some(control) = stroke.control_handle() {
+            ctx.save().ok();
+            ctx.set_line_width(1.0 / scale);
+            ctx.arc(
+                control.x,
+                control.y,
+                size / 2.0,
+                0.0,
+                2.0 * std::f64::consts::PI,
+            );
+            ctx.set_source_rgb(0.2, 0.6, 1.0);
+            ctx.fill_preserve().ok();
+            ctx.set_source_rgb(1.0, 1.0, 1.0);
+            ctx.stroke().ok();
+            ctx.restore().ok();
+            return;
+        }
+        let Some(shape_box) = stroke.shape_box() else {
+            return;
+        };
+
+        ctx.save().ok();
+        ctx.set_line_width(1.0 / scale);
+        for handle in ResizeHandle::ALL {
+            let position = handle.position(shape_box);
+            ctx.rectangle(position.x - size / 2.0, position.y - size / 2.0, size, size);
+            ctx.set_source_rgb(0.2, 0.6, 1.0);
+            ctx.fill_preserve().ok();
+            ctx.set_source_rgb(1.0, 1.0, 1.0);
+            ctx.stroke().ok();
+        }
+        ctx.restore().ok();
+    }
+
+    // On-screen handle size in image pixels, for hit-testing at a given zoom
+    pub fn resize_handle_tolerance(scale: f64) -> f64 {
+        RESIZE_HANDLE_SIZE / scale.max(f64::EPSILON)
+    }
+
+    // Dashed ruler between the measured points with a distance/angle label.
+    // Sizes are divided by the current scale so they stay constant on screen.
+    pub fn draw_measurement(&self, ctx: &Context) {
+        let ((start, end), (distance, angle)) = match (&self.measurement, self.measurement()) {
+            (Some(points), Some(reading)) => (points, reading),
+            _ => return,
+        };
+
+        let scale = ctx.matrix().xx().abs().max(f64::EPSILON);
+
+        ctx.save().ok();
+        ctx.set_line_width(1.5 / scale);
+        ctx.set_dash(&[6.0 / scale, 4.0 / scale], 0.0);
+        ctx.set_source_rgba(0.2, 0.6, 1.0, 0.9);
+        ctx.move_to(start.x, start.y);
+        ctx.line_to(end.x, end.y);
+        ctx.stroke().ok();
+
+        ctx.set_dash(&[], 0.0);
+        for point in [start, end] {
+            ctx.arc(
+                point.x,
+                point.y,
+                3.0 / scale,
+                0.0,
+                2.0 * std::f64::consts::PI,
+            );
+            ctx.fill().ok();
+        }
+
+        let label = format!("{:.1} px, {:.1}°", distance, angle);
+        ctx.select_font_face("Sans", FontSlant::Normal, FontWeight::Normal);
+        ctx.set_font_size(12.0 / scale);
+        if let Ok(extents) = ctx.text_extents(&label) {
+            let padding = 4.0 / scale;
+            let x = end.x + 10.0 / scale;
+            let y = end.y + 10.0 / scale;
+            ctx.set_source_rgba(0.0, 0.0, 0.0, 0.75);
+            ctx.rectangle(
+                x,
+                y,
+                extents.width() + 2.0 * padding,
+                extents.height() + 2.0 * padding,
+            );
+            ctx.fill().ok();
+            ctx.set_source_rgb(1.0, 1.0, 1.0);
+            ctx.move_to(
+                x + padding - extents.x_bearing(),
+                y + padding - extents.y_bearing(),
+            );
+            ctx.show_text(&label).ok();
+        }
+        ctx.restore().ok();
+    }
+
+    // Small ring marking where the live endpoint has snapped to
+    pub fn draw_snap_indicator(&self, ctx: &Context) {
+        if let Some(ref point) = self.snap_indicator {
+            ctx.save().ok();
+            ctx.set_source_rgba(0.2, 0.6, 1.0, 0.9);
+            ctx.set_line_width(1.5);
+            ctx.arc(point.x, point.y, 6.0, 0.0, 2.0 * std::f64::consts::PI);
+            ctx.stroke().ok();
+            ctx.restore().ok();
+        }
+    }
+
+    // Editing aids for the text being typed: its live bounding box, a caret
+    // and a word/character count. Display only, never part of exports.
+    pub fn draw_text_overlay(&self, ctx: &Context) {
+        let stroke = match self.current_stroke {
+            Some(ref stroke) if stroke.tool_type == ToolType::Text => stroke,
+            _ => return,
+        };
+
+        if let Some(origin) = stroke.points.first() {
+            ctx.save().ok();
+
+            let layout = stroke.layout_text(ctx);
+            let (x, y) = (origin.x, origin.y);
+            let (width, height) = (layout.width, layout.height());
+
+            // Caret at the end of the last line
+            let last_line = layout.lines.last().map(String::as_str).unwrap_or("");
+            let caret_x = x + ctx
+                .text_extents(last_line)
+                .map(|extents| extents.x_advance())
+                .unwrap_or(0.0)
+                .min(width);
+            let caret_y = y + (layout.lines.len().max(1) - 1) as f64 * layout.line_height;
+
+            ctx.set_source_rgba(0.2, 0.6, 1.0, 0.9);
+            ctx.set_line_width(1.0);
+            ctx.move_to(caret_x + 1.0, caret_y);
+            ctx.line_to(caret_x + 1.0, caret_y + layout.line_height);
+            ctx.stroke().ok();
+
+            // Dashed bounding rectangle
+            ctx.set_dash(&[4.0, 3.0], 0.0);
+            ctx.rectangle(x - 3.0, y - 3.0, width + 6.0, height + 6.0);
+            ctx.stroke().ok();
+            ctx.set_dash(&[], 0.0);
+
+            // Word and character count below the box
+            let label = format!(
+                "{} words · {} chars",
+                stroke.text.split_whitespace().count(),
+                stroke.text.chars().count()
+            );
+            ctx.select_font_face("Sans", FontSlant::Normal, FontWeight::Normal);
+            ctx.set_font_size(11.0);
+            if let Ok(extents) = ctx.text_extents(&label) {
+                let label_x = x - 3.0;
+                let label_y = y + height + 8.0;
+                ctx.set_source_rgba(0.0, 0.0, 0.0, 0.7);
+                ctx.rectangle(label_x, label_y, extents.width() + 8.0, 16.0);
+                ctx.fill().ok();
+                ctx.set_source_rgb(1.0, 1.0, 1.0);
+                ctx.move_to(label_x + 4.0, label_y + 12.0);
+                ctx.show_text(&label).ok();
+            }
+
+            ctx.restore().ok();
+        }
+    }
+}
+
+impl Default for AnnotationTools {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_context() -> Context {
+        let surface = ImageSurface::create(Format::ARgb32, 200, 200).unwrap();
+        Context::new(&surface).unwrap()
+    }
+
+    fn components(color: RGBA) -> [f32; 4] {
+        [color.red(), color.green(), color.blue(), color.alpha()]
+    }
+
+    fn text_stroke(text: &str, max_width: Option<f64>) -> DrawingStroke {
+        let mut stroke = DrawingStroke::new(ToolType::Text, RGBA::new(0.0, 0.0, 0.0, 1.0), 3.0);
+        stroke.add_point(Point::new(0.0, 0.0));
+        stroke.text = text.to_string();
+        stroke.max_width = max_width;
+        stroke
+    }
+
+    #[test]
+    fn tool_indices_match_the_toolbar_table() {
+        for (index, &(tool_type, icon, tooltip)) in ToolType::all().iter().enumerate() {
+            assert_eq!(ToolType::from_index(index), Some(tool_type));
+            assert!(!icon.is_empty() && !tooltip.is_empty());
+        }
+        assert_eq!(ToolType::from_index(ToolType::all().len()), None);
+    }
+
+    #[test]
+    fn every_tool_has_exactly_one_button() {
+        let tools = [
+            ToolType::Pencil,
+            ToolType::Line,
+            ToolType::Arrow,
+            ToolType::Highlighter,
+            ToolType::Text,
+            ToolType::Rectangle,
+            ToolType::Stamp,
+            ToolType::Measure,
+            ToolType::Eraser,
+        ];
+
+        assert_eq!(ToolType::all().len(), tools.len());
+        for tool in tools {
+            let buttons = ToolType::all()
+                .iter()
+                .filter(|&&(tool_type, _, _)| tool_type == tool)
+                .count();
+            assert_eq!(buttons, 1, "{:?}", tool);
+        }
+    }
+
+    #[test]
+    fn text_keeps_embedded_line_breaks() {
+        let ctx = test_context();
+        let layout = text_stroke("first\nsecond line\n\nlast", None).layout_text(&ctx);
+
+        assert_eq!(layout.lines, ["first", "second line", "", "last"]);
+        assert_eq!(layout.height(), 4.0 * layout.line_height);
+    }
+
+    #[test]
+    fn text_wraps_words_at_the_box_width() {
+        let ctx = test_context();
+        let stroke = text_stroke("one two three four five six", Some(60.0));
+        let layout = stroke.layout_text(&ctx);
+
+        assert!(layout.lines.len() > 1);
+        assert_eq!(layout.lines.join(" "), stroke.text);
+        for line in &layout.lines {
+            let width = ctx.text_extents(line).unwrap().x_advance();
+            assert!(
+                width <= 60.0 || !line.contains(' '),
+                "{:?} is {} wide",
+                line,
+                width
+            );
+        }
+        assert!(layout.width <= 60.0);
+    }
+
+    #[test]
+    fn rounded_rectangle_path_cuts_the_corners() {
+        let ctx = test_context();
+        rounded_rectangle_path(&ctx, 10.0, 20.0, 100.0, 50.0, 10.0);
+
+        assert_eq!(ctx.path_extents().unwrap(), (10.0, 20.0, 110.0, 70.0));
+        // Just inside each corner lies outside the arc
+        for (x, y) in [(11.0, 21.0), (109.0, 21.0), (109.0, 69.0), (11.0, 69.0)] {
+            assert!(!ctx.in_fill(x, y).unwrap(), "({}, {}) is filled", x, y);
+        }
+        // The straight edges between the arcs stay put
+        for (x, y) in [(60.0, 20.5), (109.5, 45.0), (60.0, 69.5), (10.5, 45.0)] {
+            assert!(ctx.in_fill(x, y).unwrap(), "({}, {}) is not filled", x, y);
+        }
+    }
+
+    #[test]
+    fn rounded_rectangle_path_without_radius_is_sharp() {
+        let ctx = test_context();
+        rounded_rectangle_path(&ctx, 10.0, 20.0, 100.0, 50.0, 0.0);
+
+        assert_eq!(ctx.path_extents().unwrap(), (10.0, 20.0, 110.0, 70.0));
+        assert!(ctx.in_fill(10.5, 20.5).unwrap());
+        assert!(ctx.in_fill(109.5, 69.5).unwrap());
+    }
+
+    #[test]
+    fn rounded_rectangle_radius_is_clamped_to_half_the_short_side() {
+        let ctx = test_context();
+        rounded_rectangle_path(&ctx, 0.0, 0.0, 100.0, 20.0, 50.0);
+
+        // A 10px radius makes the ends semicircles that still reach the box
+        assert_eq!(ctx.path_extents().unwrap(), (0.0, 0.0, 100.0, 20.0));
+        assert!(ctx.in_fill(0.5, 10.0).unwrap());
+        assert!(!ctx.in_fill(1.0, 1.0).unwrap());
+    }
+
+    #[test]
+    fn each_tool_gets_its_own_color_back() {
+        let mut tools = AnnotationTools::new();
+        let blue = RGBA::new(0.0, 0.4, 1.0, 1.0);
+        let green = RGBA::new(0.0, 0.8, 0.0, 1.0);
+
+        tools.set_tool(ToolType::Arrow);
+        tools.set_color(blue);
+        tools.set_tool(ToolType::Highlighter);
+        assert_eq!(
+            components(tools.current_color),
+            components(RGBA::new(1.0, 0.9, 0.0, 1.0))
+        );
+        tools.set_color(green);
+
+        tools.set_tool(ToolType::Arrow);
+        assert_eq!(components(tools.current_color), components(blue));
+        tools.set_tool(ToolType::Highlighter);
+        assert_eq!(components(tools.current_color), components(green));
+        tools.set_tool(ToolType::Pencil);
+        assert_eq!(
+            components(tools.current_color),
+            components(RGBA::new(1.0, 0.0, 0.0, 1.0))
+        );
+    }
+
+    // A 10px arrow along y = 100 from x = 20 to x = 180, so its heads are
+    // 30px long with barbs 15px either side of the shaft
+    fn arrow_stroke(head: ArrowHead) -> DrawingStroke {
+        let mut stroke = DrawingStroke::new(ToolType::Arrow, RGBA::new(0.0, 0.0, 0.0, 1.0), 10.0);
+        stroke.add_point(Point::new(20.0, 100.0));
+        stroke.add_point(Point::new(180.0, 100.0));
+        stroke.arrow_head = head;
+        stroke
+    }
+
+    // Alpha of each of `pixels` once the stroke is drawn on its own
+    fn rendered_alpha(stroke: &DrawingStroke, pixels: &[(usize, usize)]) -> Vec<u8> {
+        let mut surface = ImageSurface::create(Format::ARgb32, 200, 200).unwrap();
+        stroke.draw(&Context::new(&surface).unwrap());
+        surface.flush();
+        let stride = surface.stride() as usize;
+        let data = surface.data().unwrap();
+        pixels
+            .iter()
+            .map(|&(x, y)| data[y * stride + x * 4 + 3])
+            .collect()
+    }
+
+    fn assert_near(point: &Point, x: f64, y: f64) {
+        assert!(
+            (point.x - x).abs() < 0.01 && (point.y - y).abs() < 0.01,
+            "({}, {}) is not near ({}, {})",
+            point.x,
+            point.y,
+            x,
+            y
+        );
+    }
+
+    #[test]
+    fn arrowhead_barbs_sit_30_degrees_off_the_shaft() {
+        let stroke = arrow_stroke(ArrowHead::Open);
+        let (start, end) = (&stroke.points[0], &stroke.points[1]);
+        let back = 30.0 * (std::f64::consts::PI / 6.0).cos();
+
+        let (barb1, barb2) = stroke.arrowhead_points(start, end).unwrap();
+        assert_near(&barb1, 180.0 - back, 85.0);
+        assert_near(&barb2, 180.0 - back, 115.0);
+
+        // The second head of a double arrow points back at the start
+        let (barb1, barb2) = stroke.arrowhead_points(end, start).unwrap();
+        assert_near(&barb1, 20.0 + back, 115.0);
+        assert_near(&barb2, 20.0 + back, 85.0);
+
+        assert!(stroke.arrowhead_points(start, start).is_none());
+    }
+
+    #[test]
+    fn each_arrowhead_style_draws_its_own_head() {
+        // Inside the end head's triangle but clear of its barbs and the
+        // shaft, and on the lower barb a double arrow adds at the start
+        let pixels = [(156, 106), (33, 107)];
+
+        assert_eq!(
+            rendered_alpha(&arrow_stroke(ArrowHead::Open), &pixels),
+            [0, 0]
+        );
+        assert_eq!(
+            rendered_alpha(&arrow_stroke(ArrowHead::Filled), &pixels),
+            [255, 0]
+        );
+        assert_eq!(
+            rendered_alpha(&arrow_stroke(ArrowHead::Double), &pixels),
+            [0, 255]
+        );
+    }
+
+    #[test]
+    fn swapping_tools_goes_back_and_forth() {
+        let mut tools = AnnotationTools::new();
+        assert_eq!(tools.swap_tool(), None);
+
+        tools.set_tool(ToolType::Arrow);
+        tools.set_tool(ToolType::Text);
+        assert_eq!(tools.swap_tool(), Some(ToolType::Arrow));
+        assert_eq!(tools.current_tool, ToolType::Arrow);
+        assert_eq!(tools.swap_tool(), Some(ToolType::Text));
+        assert_eq!(tools.current_tool, ToolType::Text);
+    }
+
+    #[test]
+    fn reselecting_the_current_tool_keeps_the_one_to_swap_to() {
+        let mut tools = AnnotationTools::new();
+        tools.set_tool(ToolType::Rectangle);
+        tools.set_tool(ToolType::Rectangle);
+
+        assert_eq!(tools.swap_tool(), Some(ToolType::Pencil));
+        assert_eq!(tools.current_tool, ToolType::Pencil);
+    }
+
+    fn line_stroke(x: f64) -> DrawingStroke {
+        let mut stroke = DrawingStroke::new(ToolType::Line, RGBA::new(1.0, 0.0, 0.0, 1.0), 3.0);
+        stroke.add_point(Point::new(x, 10.0));
+        stroke.add_point(Point::new(x, 50.0));
+        stroke
+    }
+
+    fn stamp_stroke(x: f64, y: f64) -> DrawingStroke {
+        let mut stroke = DrawingStroke::new(ToolType::Stamp, RGBA::new(1.0, 1.0, 1.0, 1.0), 3.0);
+        stroke.stamp = Some(ImageSurface::create(Format::ARgb32, 20, 20).unwrap());
+        stroke.add_point(Point::new(x, y));
+        stroke
+    }
+
+    fn first_points(tools: &AnnotationTools) -> Vec<(f64, f64)> {
+        tools
+            .strokes
+            .iter()
+            .map(|stroke| (stroke.points[0].x, stroke.points[0].y))
+            .collect()
+    }
+
+    #[test]
+    fn undoing_clear_all_restores_every_stroke() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![
+            line_stroke(10.0),
+            line_stroke(20.0),
+            line_stroke(30.0),
+        ]);
+
+        tools.clear_all();
+        assert!(tools.strokes.is_empty());
+
+        assert!(tools.undo());
+        assert_eq!(
+            first_points(&tools),
+            [(10.0, 10.0), (20.0, 10.0), (30.0, 10.0)]
+        );
+        assert!(tools.redo());
+        assert!(tools.strokes.is_empty());
+    }
+
+    #[test]
+    fn eraser_box_removes_only_strokes_inside_it() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![
+            line_stroke(10.0),
+            line_stroke(20.0),
+            line_stroke(30.0),
+            line_stroke(40.0),
+        ]);
+
+        assert_eq!(tools.remove_strokes_in_rect(15.0, 0.0, 20.0, 100.0), 2);
+        assert_eq!(first_points(&tools), [(10.0, 10.0), (40.0, 10.0)]);
+
+        // Both come back at their old places in one step
+        assert!(tools.undo());
+        assert_eq!(
+            first_points(&tools),
+            [(10.0, 10.0), (20.0, 10.0), (30.0, 10.0), (40.0, 10.0)]
+        );
+    }
+
+    #[test]
+    fn eraser_box_over_nothing_changes_nothing() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![line_stroke(10.0)]);
+
+        assert_eq!(tools.remove_strokes_in_rect(100.0, 100.0, 50.0, 50.0), 0);
+        assert_eq!(tools.strokes.len(), 1);
+        assert!(!tools.undo());
+    }
+
+    #[test]
+    fn group_drag_moves_every_selected_stroke_in_one_step() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![
+            line_stroke(10.0),
+            line_stroke(20.0),
+            line_stroke(30.0),
+        ]);
+        tools.toggle_group_stroke(0);
+        tools.toggle_group_stroke(2);
+
+        assert!(tools.begin_group_drag(&Point::new(20.0, 30.0)));
+        tools.drag_group_to(&Point::new(25.0, 20.0));
+        tools.drag_group_to(&Point::new(30.0, 45.0));
+        tools.end_group_drag();
+        assert_eq!(
+            first_points(&tools),
+            [(20.0, 25.0), (20.0, 10.0), (40.0, 25.0)]
+        );
+
+        assert!(tools.undo());
+        assert_eq!(
+            first_points(&tools),
+            [(10.0, 10.0), (20.0, 10.0), (30.0, 10.0)]
+        );
+    }
+
+    #[test]
+    fn group_drag_outside_the_group_does_not_start() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![line_stroke(10.0), line_stroke(30.0)]);
+        tools.toggle_group_stroke(0);
+
+        assert!(!tools.begin_group_drag(&Point::new(30.0, 30.0)));
+        assert!(!tools.is_dragging_group());
+    }
+
+    #[test]
+    fn group_delete_removes_the_selection_and_undoes_in_place() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![
+            line_stroke(10.0),
+            line_stroke(20.0),
+            line_stroke(30.0),
+            line_stroke(40.0),
+        ]);
+        tools.toggle_group_stroke(3);
+        tools.toggle_group_stroke(1);
+
+        assert_eq!(tools.delete_group(), 2);
+        assert_eq!(first_points(&tools), [(10.0, 10.0), (30.0, 10.0)]);
+        assert!(tools.selected_group.is_empty());
+
+        assert!(tools.undo());
+        assert_eq!(
+            first_points(&tools),
+            [(10.0, 10.0), (20.0, 10.0), (30.0, 10.0), (40.0, 10.0)]
+        );
+        assert!(tools.redo());
+        assert_eq!(first_points(&tools), [(10.0, 10.0), (30.0, 10.0)]);
+    }
+
+    fn palette_entry(name: &str, color: &str) -> PaletteColor {
+        PaletteColor {
+            name: name.to_string(),
+            color: color.to_string(),
+        }
+    }
+
+    #[test]
+    fn palette_names_and_colors_stay_the_same_length() {
+        let custom = [
+            palette_entry("Brand", "#e01b24"),
+            palette_entry("Broken", "not a color"),
+            palette_entry("Sky", "rgb(0, 128, 255)"),
+            palette_entry("Brand", "#000000"),
+        ];
+
+        for palette in [
+            AnnotationTools::palette_from(&[]),
+            AnnotationTools::palette_from(&custom),
+        ] {
+            let names: Vec<_> = palette.iter().map(|(name, _)| name.as_str()).collect();
+            let colors: Vec<_> = palette.iter().map(|(_, color)| *color).collect();
+            assert_eq!(names.len(), colors.len());
+            assert!(!names.is_empty());
+        }
+
+        // The unparsable entry and the second "Brand" are dropped
+        let palette = AnnotationTools::palette_from(&custom);
+        let names: Vec<_> = palette.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["Brand", "Sky"]);
+        let brand = components(palette[0].1).map(|component| (component * 255.0).round());
+        assert_eq!(brand, [224.0, 27.0, 36.0, 255.0]);
+        assert_eq!(AnnotationTools::palette_from(&[]).len(), 8);
+    }
+
+    #[test]
+    fn unusable_custom_palette_falls_back_to_the_built_in_one() {
+        let palette = AnnotationTools::palette_from(&[palette_entry("Broken", "#zzz")]);
+        let names: Vec<_> = palette.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Red", "Green", "Blue", "Yellow", "Pink", "Cyan", "Black", "White"]
+        );
+    }
+
+    #[test]
+    fn combo_labels_match_the_colors_they_emit() {
+        let custom = [
+            palette_entry("Brand", "#e01b24"),
+            palette_entry("Sky", "#0080ff"),
+        ];
+
+        for palette in [
+            AnnotationTools::palette_from(&[]),
+            AnnotationTools::palette_from(&custom),
+        ] {
+            for (name, color) in &palette {
+                let emitted = AnnotationTools::palette_color(&palette, name).unwrap();
+                assert_eq!(components(emitted), components(*color), "{}", name);
+                assert_eq!(
+                    AnnotationTools::palette_name(&palette, *color),
+                    Some(name.as_str())
+                );
+            }
+        }
+
+        let built_in = AnnotationTools::palette_from(&[]);
+        let pink = AnnotationTools::palette_color(&built_in, "Pink").unwrap();
+        assert_eq!(components(pink), [1.0, 0.4, 0.7, 1.0]);
+        assert!(AnnotationTools::palette_color(&built_in, "Magenta").is_none());
+    }
+
+    #[test]
+    fn scaling_a_stroke_scales_its_points_and_sizes() {
+        let mut stroke = DrawingStroke::new(ToolType::Arrow, RGBA::new(1.0, 0.0, 0.0, 1.0), 3.0);
+        stroke.add_point(Point::new(10.0, 20.0));
+        stroke.add_point(Point::new(30.0, 5.0));
+        stroke.control_point = Some(Point::new(25.0, 25.0));
+        stroke.max_width = Some(100.0);
+
+        stroke.scale_by(2.0);
+        let points: Vec<_> = stroke
+            .points
+            .iter()
+            .map(|point| (point.x, point.y))
+            .collect();
+        assert_eq!(points, [(20.0, 40.0), (60.0, 10.0)]);
+        let control = stroke.control_point.as_ref().unwrap();
+        assert_eq!((control.x, control.y), (50.0, 50.0));
+        assert_eq!(stroke.thickness, 6.0);
+        assert_eq!(stroke.max_width, Some(200.0));
+        assert_eq!(stroke.stamp_scale, 2.0);
+    }
+
+    #[test]
+    fn dragging_a_curved_arrows_control_point_is_one_undo_step() {
+        let mut arrow = DrawingStroke::new(ToolType::Arrow, RGBA::new(1.0, 0.0, 0.0, 1.0), 3.0);
+        arrow.arrow_shape = ArrowShape::Curved;
+        arrow.add_point(Point::new(10.0, 50.0));
+        arrow.add_point(Point::new(90.0, 50.0));
+        arrow.finish();
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![arrow]);
+        tools.selected_stroke = Some(0);
+        let control = |tools: &AnnotationTools| {
+            let point = tools.strokes[0].control_point.as_ref().unwrap();
+            (point.x, point.y)
+        };
+        assert_eq!(control(&tools), (50.0, 30.0));
+
+        // Away from the control point the press is not taken
+        assert!(!tools.begin_shape_resize(&Point::new(50.0, 50.0), 4.0));
+
+        assert!(tools.begin_shape_resize(&Point::new(52.0, 31.0), 4.0));
+        tools.resize_shape_to(&Point::new(50.0, 10.0), false);
+        tools.resize_shape_to(&Point::new(60.0, 80.0), false);
+        tools.end_shape_resize();
+        assert_eq!(control(&tools), (60.0, 80.0));
+
+        assert!(tools.undo());
+        assert_eq!(control(&tools), (50.0, 30.0));
+        assert!(!tools.undo());
+    }
+
+    #[test]
+    fn tab_cycling_wraps_around_both_ways() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![
+            line_stroke(10.0),
+            line_stroke(20.0),
+            line_stroke(30.0),
+        ]);
+
+        let forward: Vec<_> = (0..4).map(|_| tools.cycle_selection(true)).collect();
+        assert_eq!(forward, [Some(0), Some(1), Some(2), Some(0)]);
+
+        tools.selected_stroke = None;
+        let backward: Vec<_> = (0..4).map(|_| tools.cycle_selection(false)).collect();
+        assert_eq!(backward, [Some(2), Some(1), Some(0), Some(2)]);
+    }
+
+    #[test]
+    fn tab_cycling_skips_hidden_strokes() {
+        let mut hidden = line_stroke(20.0);
+        hidden.visible = false;
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![line_stroke(10.0), hidden, line_stroke(30.0)]);
+
+        let forward: Vec<_> = (0..3).map(|_| tools.cycle_selection(true)).collect();
+        assert_eq!(forward, [Some(0), Some(2), Some(0)]);
+    }
+
+    #[test]
+    fn stamp_drag_undoes_in_one_step() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![stamp_stroke(50.0, 50.0)]);
+
+        tools.begin_stamp_drag(0, &Point::new(55.0, 55.0));
+        tools.drag_stamp_to(&Point::new(75.0, 65.0));
+        tools.drag_stamp_to(&Point::new(105.0, 85.0));
+        tools.end_stamp_drag();
+        assert_eq!(first_points(&tools), [(100.0, 80.0)]);
+
+        assert!(tools.undo());
+        assert_eq!(first_points(&tools), [(50.0, 50.0)]);
+        assert!(!tools.undo());
+    }
+
+    #[test]
+    fn stamp_resize_is_undoable() {
+        let mut tools = AnnotationTools::new();
+        tools.load_strokes(vec![stamp_stroke(50.0, 50.0)]);
+        tools.selected_stroke = Some(0);
+
+        tools.scale_selected_stamp(2.0);
+        tools.scale_selected_stamp(1.5);
+        assert_eq!(tools.strokes[0].stamp_scale, 3.0);
+
+        assert!(tools.undo());
+        assert_eq!(tools.strokes[0].stamp_scale, 2.0);
+        assert!(tools.undo());
+        assert_eq!(tools.strokes[0].stamp_scale, 1.0);
+    }
+
+    #[test]
+    fn screenshot_changes_come_back_on_undo_and_redo() {
+        let mut tools = AnnotationTools::new();
+        let before = ImageSurface::create(Format::ARgb32, 10, 10).unwrap();
+        let after = ImageSurface::create(Format::ARgb32, 20, 20).unwrap();
+        tools.record_screenshot_change(before, after, None);
+        assert!(tools.take_screenshot_change().is_none());
+
+        assert!(tools.undo());
+        assert_eq!(tools.take_screenshot_change().unwrap().surface.width(), 10);
+        assert!(tools.take_screenshot_change().is_none());
+        assert!(tools.redo());
+        assert_eq!(tools.take_screenshot_change().unwrap().surface.width(), 20);
+    }
+
+    #[test]
+    fn undoing_a_blur_brings_back_the_full_size_capture() {
+        let mut tools = AnnotationTools::new();
+        let before = ImageSurface::create(Format::ARgb32, 10, 10).unwrap();
+        let after = ImageSurface::create(Format::ARgb32, 10, 10).unwrap();
+        let full_resolution = FullResolution {
+            png: vec![1, 2, 3],
+            scale: 0.5,
+        };
+        tools.record_screenshot_change(before, after, Some(full_resolution));
+
+        assert!(tools.undo());
+        let change = tools.take_screenshot_change().unwrap();
+        let restored = change.full_resolution.unwrap();
+        assert_eq!(restored.png, vec![1, 2, 3]);
+        assert_eq!(restored.scale, 0.5);
+
+        // Redoing bakes the blur in again, which the full-size capture lacks
+        assert!(tools.redo());
+        assert!(tools
+            .take_screenshot_change()
+            .unwrap()
+            .full_resolution
+            .is_none());
     }
 }
@@ -1,20 +1,80 @@
+use anyhow::{anyhow, Result};
+use cairo::{Context, Format, ImageSurface};
 use gdk4::RGBA;
 use glib::clone;
+use gtk4::gio;
 use gtk4::prelude::*;
-use gtk4::{Box, Button, ComboBoxText, Label, Orientation, Scale, Separator, ToggleButton};
-use std::cell::RefCell;
+use gtk4::{
+    Box, Button, CheckButton, ComboBoxText, DrawingArea, Entry, Label, ListBox, ListBoxRow,
+    MenuButton, Orientation, PolicyType, Popover, Revealer, RevealerTransitionType, Scale,
+    ScrolledWindow, SelectionMode, Separator, ToggleButton,
+};
+use log::warn;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
-use crate::tools::ToolType;
+use crate::preferences;
+use crate::tools::{
+    AnnotationTools, ArrowHead, ArrowShape, CapStyle, JoinStyle, ToolType, MAX_STABILIZER,
+};
 
+// Two-by-two block of 8px grey squares, repeated like an image editor's
+// transparency grid
+pub fn checkerboard_pattern() -> Result<cairo::SurfacePattern> {
+    const SQUARE_SIZE: i32 = 8;
+
+    let tile = ImageSurface::create(Format::Rgb24, SQUARE_SIZE * 2, SQUARE_SIZE * 2)
+        .map_err(|e| anyhow!("Failed to create checkerboard tile: {}", e))?;
+    let ctx = Context::new(&tile).map_err(|e| anyhow!("Failed to create context: {}", e))?;
+
+    ctx.set_source_rgb(0.40, 0.40, 0.40);
+    ctx.paint().unwrap();
+    ctx.set_source_rgb(0.30, 0.30, 0.30);
+    let size = SQUARE_SIZE as f64;
+    ctx.rectangle(size, 0.0, size, size);
+    ctx.rectangle(0.0, size, size, size);
+    ctx.fill().unwrap();
+    drop(ctx);
+
+    let pattern = cairo::SurfacePattern::create(&tile);
+    pattern.set_extend(cairo::Extend::Repeat);
+    pattern.set_filter(cairo::Filter::Nearest);
+    Ok(pattern)
+}
+
+#[derive(Clone)]
 pub struct Toolbar {
     pub widget: Box,
     tool_buttons: Vec<ToggleButton>,
+    arrow_combo: ComboBoxText,
+    arrow_head_combo: ComboBoxText,
+    stamp_combo: ComboBoxText,
     color_combo: ComboBoxText,
+    color_swatch: DrawingArea,
     thickness_scale: Scale,
+    radius_scale: Scale,
+    stabilizer_scale: Scale,
+    pixel_snap_button: ToggleButton,
+    cap_combo: ComboBoxText,
+    join_combo: ComboBoxText,
     save_button: Button,
     copy_button: Button,
+    copy_data_uri_button: Button,
+    upload_button: Button,
+    open_with_button: MenuButton,
+    open_with_box: Box,
+    project_button: MenuButton,
+    save_project_button: Button,
+    open_project_button: Button,
+    history_button: MenuButton,
+    history_box: Box,
+    list_button: ToggleButton,
+    color_info_button: ToggleButton,
     clear_button: Button,
+    carry_over_button: ToggleButton,
+    blur_outside_button: Button,
+    blur_faces_button: Button,
+    recrop_button: Button,
 }
 
 impl Toolbar {
@@ -32,6 +92,17 @@ impl Toolbar {
 
         let tool_buttons = Self::create_tool_buttons(&tool_box, current_tool.clone());
 
+        // Arrow shape selection
+        let arrow_combo = Self::create_arrow_combo();
+        tool_box.append(&arrow_combo);
+        let arrow_head_combo = Self::create_arrow_head_combo();
+        tool_box.append(&arrow_head_combo);
+
+        // Stamp selection, filled in once the stamps are loaded
+        let stamp_combo = ComboBoxText::new();
+        stamp_combo.set_tooltip_text(Some("Stamp to place"));
+        tool_box.append(&stamp_combo);
+
         // Separator
         let separator1 = Separator::new(Orientation::Vertical);
 
@@ -40,8 +111,16 @@ impl Toolbar {
         let color_label = Label::new(Some("Color:"));
         let color_combo = Self::create_color_combo();
 
+        // The color as it will be painted, over a checkerboard so
+        // see-through colors show as such
+        let color_swatch = DrawingArea::new();
+        color_swatch.set_size_request(24, 24);
+        color_swatch.set_valign(gtk4::Align::Center);
+        color_swatch.set_tooltip_text(Some("Color and opacity of the current tool"));
+
         color_box.append(&color_label);
         color_box.append(&color_combo);
+        color_box.append(&color_swatch);
 
         // Separator
         let separator2 = Separator::new(Orientation::Vertical);
@@ -54,18 +133,65 @@ impl Toolbar {
         thickness_box.append(&thickness_label);
         thickness_box.append(&thickness_scale);
 
+        // Corner radius control for rectangles
+        let radius_label = Label::new(Some("Radius:"));
+        let radius_scale = Self::create_radius_scale();
+
+        thickness_box.append(&radius_label);
+        thickness_box.append(&radius_scale);
+
+        // Freehand stabilizer for the pencil and highlighter
+        let stabilizer_label = Label::new(Some("Steady:"));
+        let stabilizer_scale = Self::create_stabilizer_scale();
+
+        thickness_box.append(&stabilizer_label);
+        thickness_box.append(&stabilizer_scale);
+
+        // Crisp, whole-pixel edges for lines and rectangles
+        let pixel_snap_button = Self::create_pixel_snap_button();
+        thickness_box.append(&pixel_snap_button);
+
+        // Line ends and corners, e.g. square and mitered for diagrams
+        let (cap_combo, join_combo) = Self::create_line_style_combos();
+        thickness_box.append(&cap_combo);
+        thickness_box.append(&join_combo);
+
         // Separator
         let separator3 = Separator::new(Orientation::Vertical);
 
         // Action buttons
         let action_box = Box::new(Orientation::Horizontal, 6);
         let clear_button = Self::create_clear_button();
+        let carry_over_button = Self::create_carry_over_button();
+        let blur_outside_button = Self::create_blur_outside_button();
+        let blur_faces_button = Self::create_blur_faces_button();
         let save_button = Self::create_save_button();
         let copy_button = Self::create_copy_button();
+        let copy_data_uri_button = Self::create_copy_data_uri_button();
+        let upload_button = Self::create_upload_button();
+        let (open_with_button, open_with_box) = Self::create_open_with_menu();
+        let (project_button, save_project_button, open_project_button) =
+            Self::create_project_menu();
+        let (history_button, history_box) = Self::create_history_menu();
+        let list_button = Self::create_list_button();
+        let color_info_button = Self::create_color_info_button();
 
+        let recrop_button = Self::create_recrop_button();
+
+        action_box.append(&recrop_button);
+        action_box.append(&blur_outside_button);
+        action_box.append(&blur_faces_button);
         action_box.append(&clear_button);
+        action_box.append(&carry_over_button);
         action_box.append(&save_button);
         action_box.append(&copy_button);
+        action_box.append(&copy_data_uri_button);
+        action_box.append(&upload_button);
+        action_box.append(&open_with_button);
+        action_box.append(&project_button);
+        action_box.append(&history_button);
+        action_box.append(&list_button);
+        action_box.append(&color_info_button);
 
         // Add all sections to main toolbar
         widget.append(&tool_box);
@@ -79,11 +205,35 @@ impl Toolbar {
         Self {
             widget,
             tool_buttons,
+            arrow_combo,
+            arrow_head_combo,
+            stamp_combo,
             color_combo,
+            color_swatch,
             thickness_scale,
+            radius_scale,
+            stabilizer_scale,
+            pixel_snap_button,
+            cap_combo,
+            join_combo,
             save_button,
             copy_button,
+            copy_data_uri_button,
+            upload_button,
+            open_with_button,
+            open_with_box,
+            project_button,
+            save_project_button,
+            open_project_button,
+            history_button,
+            history_box,
+            list_button,
+            color_info_button,
             clear_button,
+            carry_over_button,
+            blur_outside_button,
+            blur_faces_button,
+            recrop_button,
         }
     }
 
@@ -91,16 +241,9 @@ impl Toolbar {
         container: &Box,
         current_tool: Rc<RefCell<ToolType>>,
     ) -> Vec<ToggleButton> {
-        let tools = vec![
-            (ToolType::Pencil, "✏️", "Pencil"),
-            (ToolType::Line, "📏", "Line"),
-            (ToolType::Arrow, "➡️", "Arrow"),
-            (ToolType::Highlighter, "🖍️", "Highlighter"),
-        ];
-
         let mut buttons = Vec::new();
 
-        for (i, (tool_type, icon, tooltip)) in tools.iter().enumerate() {
+        for (i, (tool_type, icon, tooltip)) in ToolType::all().iter().enumerate() {
             let button = ToggleButton::new();
             button.set_label(icon);
             button.set_tooltip_text(Some(tooltip));
@@ -135,18 +278,42 @@ impl Toolbar {
         buttons
     }
 
-    fn create_color_combo() -> ComboBoxText {
+    fn create_arrow_combo() -> ComboBoxText {
+        let combo = ComboBoxText::new();
+        combo.set_tooltip_text(Some("Arrow shape"));
+
+        for shape in &["Straight", "Elbow", "Curved"] {
+            combo.append_text(shape);
+        }
+
+        combo.set_active(Some(0)); // Default to Straight
+
+        combo
+    }
+
+    fn create_arrow_head_combo() -> ComboBoxText {
         let combo = ComboBoxText::new();
+        combo.set_tooltip_text(Some("Arrow head"));
 
-        let colors = vec![
-            "Red", "Green", "Blue", "Yellow", "Pink", "Cyan", "Black", "White",
-        ];
+        for head in &["Open", "Filled", "Double"] {
+            combo.append_text(head);
+        }
+
+        combo.set_active(Some(0)); // Default to Open
 
-        for color in &colors {
-            combo.append_text(color);
+        combo
+    }
+
+    fn create_color_combo() -> ComboBoxText {
+        let combo = ComboBoxText::new();
+
+        // Each entry is identified by its palette name, which is how the
+        // color is looked up again, never by its position in the list
+        for (name, _) in AnnotationTools::get_predefined_colors() {
+            combo.append(Some(name.as_str()), name);
         }
 
-        combo.set_active(Some(0)); // Default to Red
+        combo.set_active(Some(0)); // Default to the first color
 
         combo
     }
@@ -161,6 +328,73 @@ impl Toolbar {
         scale
     }
 
+    fn create_radius_scale() -> Scale {
+        let scale = Scale::with_range(Orientation::Horizontal, 0.0, 40.0, 1.0);
+        scale.set_value(0.0);
+        scale.set_size_request(80, -1);
+        scale.set_digits(0);
+        scale.set_draw_value(true);
+        scale.set_tooltip_text(Some("Corner radius for rectangles"));
+
+        scale
+    }
+
+    fn create_stabilizer_scale() -> Scale {
+        let scale = Scale::with_range(Orientation::Horizontal, 0.0, MAX_STABILIZER, 0.1);
+        scale.set_value(
+            preferences::get()
+                .pencil
+                .stabilizer
+                .clamp(0.0, MAX_STABILIZER),
+        );
+        scale.set_size_request(80, -1);
+        scale.set_digits(1);
+        scale.set_draw_value(true);
+        scale.set_tooltip_text(Some(
+            "Smooth shaky pencil and highlighter strokes by letting the line trail the pointer",
+        ));
+
+        scale
+    }
+
+    fn create_pixel_snap_button() -> ToggleButton {
+        let button = ToggleButton::with_label("⌗ Pixel");
+        button.set_tooltip_text(Some(
+            "Snap line and rectangle edges to whole pixels, without anti-aliasing",
+        ));
+
+        button
+    }
+
+    fn create_line_style_combos() -> (ComboBoxText, ComboBoxText) {
+        let cap_combo = ComboBoxText::new();
+        cap_combo.set_tooltip_text(Some("Line ends"));
+        for cap in &["Round ends", "Square ends", "Butt ends"] {
+            cap_combo.append_text(cap);
+        }
+        cap_combo.set_active(Some(0)); // Default to Round
+
+        let join_combo = ComboBoxText::new();
+        join_combo.set_tooltip_text(Some("Line corners"));
+        for join in &["Round corners", "Miter corners", "Bevel corners"] {
+            join_combo.append_text(join);
+        }
+        join_combo.set_active(Some(0)); // Default to Round
+
+        (cap_combo, join_combo)
+    }
+
+    // Only shown for region captures, once the editor can re-crop
+    fn create_recrop_button() -> Button {
+        let button = Button::with_label("✂️ Re-crop");
+        button.set_tooltip_text(Some(
+            "Choose a different region of the original full-screen capture",
+        ));
+        button.set_visible(false);
+
+        button
+    }
+
     fn create_clear_button() -> Button {
         let button = Button::with_label("🗑️ Clear");
         button.set_tooltip_text(Some("Clear all annotations"));
@@ -169,6 +403,31 @@ impl Toolbar {
         button
     }
 
+    fn create_carry_over_button() -> ToggleButton {
+        let button = ToggleButton::with_label("📌 Carry over");
+        button.set_tooltip_text(Some(
+            "Start the next capture of the same size with these annotations",
+        ));
+
+        button
+    }
+
+    fn create_blur_outside_button() -> Button {
+        let button = Button::with_label("🔒 Blur Outside");
+        button.set_tooltip_text(Some(
+            "Blur everything except the last rectangle (permanent)",
+        ));
+
+        button
+    }
+
+    fn create_blur_faces_button() -> Button {
+        let button = Button::with_label("🙈 Blur Faces");
+        button.set_tooltip_text(Some("Blur every detected face (permanent)"));
+
+        button
+    }
+
     fn create_save_button() -> Button {
         let button = Button::with_label("💾 Save");
         button.set_tooltip_text(Some("Save to file"));
@@ -184,17 +443,101 @@ impl Toolbar {
         button
     }
 
+    fn create_copy_data_uri_button() -> Button {
+        let button = Button::with_label("🔗 Data URI");
+        button.set_tooltip_text(Some(
+            "Copy as a data:image/png;base64 URI for embedding in Markdown or HTML",
+        ));
+
+        button
+    }
+
+    fn create_upload_button() -> Button {
+        let button = Button::with_label("☁️ Upload");
+        button.set_tooltip_text(Some("Upload to image host and copy the URL"));
+
+        button
+    }
+
+    // Menu listing the applications to open the export in; its entries are
+    // filled in when it opens
+    fn create_open_with_menu() -> (MenuButton, Box) {
+        let apps_box = Box::new(Orientation::Vertical, 2);
+
+        let popover = Popover::new();
+        popover.set_child(Some(&apps_box));
+
+        let menu_button = MenuButton::new();
+        menu_button.set_label("↗️ Open With");
+        menu_button.set_tooltip_text(Some("Open the annotated screenshot in another application"));
+        menu_button.set_popover(Some(&popover));
+
+        (menu_button, apps_box)
+    }
+
+    // Menu holding the editable project actions
+    fn create_project_menu() -> (MenuButton, Button, Button) {
+        let save_button = Button::with_label("Save Project…");
+        save_button.set_tooltip_text(Some("Save the screenshot with editable annotations"));
+        save_button.add_css_class("flat");
+
+        let open_button = Button::with_label("Open Project…");
+        open_button.set_tooltip_text(Some("Reopen a saved project for editing"));
+        open_button.add_css_class("flat");
+
+        let menu_box = Box::new(Orientation::Vertical, 2);
+        menu_box.append(&save_button);
+        menu_box.append(&open_button);
+
+        let popover = Popover::new();
+        popover.set_child(Some(&menu_box));
+
+        let menu_button = MenuButton::new();
+        menu_button.set_label("🗂️ Project");
+        menu_button.set_tooltip_text(Some("Save or open an editable project"));
+        menu_button.set_popover(Some(&popover));
+
+        (menu_button, save_button, open_button)
+    }
+
+    // Menu listing recent copies; its entries are filled in when it opens
+    fn create_history_menu() -> (MenuButton, Box) {
+        let history_box = Box::new(Orientation::Vertical, 4);
+
+        let popover = Popover::new();
+        popover.set_child(Some(&history_box));
+
+        let menu_button = MenuButton::new();
+        menu_button.set_label("🕘 History");
+        menu_button.set_tooltip_text(Some("Copy a recently copied screenshot again"));
+        menu_button.set_popover(Some(&popover));
+
+        (menu_button, history_box)
+    }
+
+    fn create_list_button() -> ToggleButton {
+        let button = ToggleButton::with_label("📑 List");
+        button.set_tooltip_text(Some("Show the list of annotations"));
+
+        button
+    }
+
+    fn create_color_info_button() -> ToggleButton {
+        let button = ToggleButton::with_label("📊 Colors");
+        button.set_tooltip_text(Some(
+            "Show the color histogram and the color under the pointer",
+        ));
+
+        button
+    }
+
     pub fn connect_tool_changed<F>(&self, callback: F)
     where
         F: Fn(ToolType) + 'static + Clone,
     {
         for (i, button) in self.tool_buttons.iter().enumerate() {
-            let tool_type = match i {
-                0 => ToolType::Pencil,
-                1 => ToolType::Line,
-                2 => ToolType::Arrow,
-                3 => ToolType::Highlighter,
-                _ => ToolType::Pencil,
+            let Some(tool_type) = ToolType::from_index(i) else {
+                continue;
             };
 
             let callback_clone = callback.clone();
@@ -206,30 +549,144 @@ impl Toolbar {
         }
     }
 
+    // Show `tool` as the active tool, as if its button had been clicked
+    pub fn select_tool(&self, tool: ToolType) {
+        let Some(index) = ToolType::all()
+            .iter()
+            .position(|&(tool_type, _, _)| tool_type == tool)
+        else {
+            return;
+        };
+
+        // Activate first so the previous tool's button may be released
+        self.tool_buttons[index].set_active(true);
+        for (i, button) in self.tool_buttons.iter().enumerate() {
+            if i != index {
+                button.set_active(false);
+            }
+        }
+    }
+
+    pub fn connect_arrow_shape_changed<F>(&self, callback: F)
+    where
+        F: Fn(ArrowShape) + 'static,
+    {
+        self.arrow_combo.connect_changed(move |combo| {
+            let shape = match combo.active() {
+                Some(1) => ArrowShape::Elbow,
+                Some(2) => ArrowShape::Curved,
+                _ => ArrowShape::Straight,
+            };
+            callback(shape);
+        });
+    }
+
+    pub fn connect_arrow_head_changed<F>(&self, callback: F)
+    where
+        F: Fn(ArrowHead) + 'static,
+    {
+        self.arrow_head_combo.connect_changed(move |combo| {
+            let head = match combo.active() {
+                Some(1) => ArrowHead::Filled,
+                Some(2) => ArrowHead::Double,
+                _ => ArrowHead::Open,
+            };
+            callback(head);
+        });
+    }
+
+    pub fn set_stamp_names(&self, names: &[String]) {
+        self.stamp_combo.remove_all();
+        for name in names {
+            self.stamp_combo.append_text(name);
+        }
+        if !names.is_empty() {
+            self.stamp_combo.set_active(Some(0));
+        }
+    }
+
+    pub fn connect_stamp_changed<F>(&self, callback: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        self.stamp_combo.connect_changed(move |combo| {
+            if let Some(index) = combo.active() {
+                callback(index as usize);
+            }
+        });
+    }
+
     pub fn connect_color_changed<F>(&self, callback: F)
     where
         F: Fn(RGBA) + 'static,
     {
         self.color_combo.connect_changed(move |combo| {
-            let colors = vec![
-                RGBA::new(1.0, 0.0, 0.0, 1.0), // Red
-                RGBA::new(0.0, 0.8, 0.0, 1.0), // Green
-                RGBA::new(0.0, 0.0, 1.0, 1.0), // Blue
-                RGBA::new(1.0, 0.9, 0.0, 1.0), // Yellow
-                RGBA::new(1.0, 0.4, 0.7, 1.0), // Pink
-                RGBA::new(0.0, 0.8, 0.8, 1.0), // Cyan
-                RGBA::new(0.0, 0.0, 0.0, 1.0), // Black
-                RGBA::new(1.0, 1.0, 1.0, 1.0), // White
-            ];
-
-            if let Some(active) = combo.active() {
-                if let Some(color) = colors.get(active as usize) {
-                    callback(*color);
-                }
+            let color = combo.active_id().and_then(|id| {
+                AnnotationTools::palette_color(AnnotationTools::get_predefined_colors(), &id)
+            });
+
+            if let Some(color) = color {
+                callback(color);
             }
         });
     }
 
+    // Draw the swatch from the tools' live color; call refresh_color_swatch
+    // whenever the color or tool changes
+    pub fn set_color_swatch_source(&self, tools: Rc<RefCell<AnnotationTools>>) {
+        self.color_swatch
+            .set_draw_func(move |_, ctx, width, height| {
+                let (width, height) = (width as f64, height as f64);
+                let color = tools.borrow().effective_color();
+
+                ctx.rectangle(0.0, 0.0, width, height);
+                match checkerboard_pattern() {
+                    Ok(pattern) => {
+                        ctx.set_source(&pattern).ok();
+                        ctx.fill().ok();
+                    }
+                    Err(e) => warn!("Skipping swatch checkerboard: {}", e),
+                }
+
+                ctx.rectangle(0.0, 0.0, width, height);
+                ctx.set_source_rgba(
+                    color.red() as f64,
+                    color.green() as f64,
+                    color.blue() as f64,
+                    color.alpha() as f64,
+                );
+                ctx.fill_preserve().ok();
+                ctx.set_source_rgba(0.5, 0.5, 0.5, 1.0);
+                ctx.set_line_width(1.0);
+                ctx.stroke().ok();
+            });
+    }
+
+    pub fn refresh_color_swatch(&self) {
+        self.color_swatch.queue_draw();
+    }
+
+    // Reflect a color chosen elsewhere (e.g. a tool's remembered color) in the combo
+    pub fn select_color(&self, color: RGBA) {
+        let name = AnnotationTools::palette_name(AnnotationTools::get_predefined_colors(), color);
+
+        if let Some(name) = name {
+            if self.color_combo.active_id().as_deref() != Some(name) {
+                self.color_combo.set_active_id(Some(name));
+            }
+        }
+    }
+
+    // Step the thickness slider, within its range; the change is reported
+    // through connect_thickness_changed like a drag of the slider
+    pub fn step_thickness(&self, steps: f64) -> f64 {
+        let adjustment = self.thickness_scale.adjustment();
+        let value = (adjustment.value() + steps * adjustment.step_increment())
+            .clamp(adjustment.lower(), adjustment.upper());
+        self.thickness_scale.set_value(value);
+        value
+    }
+
     pub fn connect_thickness_changed<F>(&self, callback: F)
     where
         F: Fn(f64) + 'static,
@@ -240,6 +697,67 @@ impl Toolbar {
         });
     }
 
+    pub fn connect_corner_radius_changed<F>(&self, callback: F)
+    where
+        F: Fn(f64) + 'static,
+    {
+        self.radius_scale.connect_value_changed(move |scale| {
+            let value = scale.value();
+            callback(value);
+        });
+    }
+
+    pub fn connect_stabilizer_changed<F>(&self, callback: F)
+    where
+        F: Fn(f64) + 'static,
+    {
+        self.stabilizer_scale.connect_value_changed(move |scale| {
+            callback(scale.value());
+        });
+    }
+
+    pub fn connect_pixel_snap_toggled<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        self.pixel_snap_button.connect_toggled(move |button| {
+            callback(button.is_active());
+        });
+    }
+
+    pub fn connect_line_style_changed<F>(&self, callback: F)
+    where
+        F: Fn(CapStyle, JoinStyle) + 'static,
+    {
+        let callback = Rc::new(callback);
+        let style = |cap_combo: &ComboBoxText, join_combo: &ComboBoxText| {
+            let cap = match cap_combo.active() {
+                Some(1) => CapStyle::Square,
+                Some(2) => CapStyle::Butt,
+                _ => CapStyle::Round,
+            };
+            let join = match join_combo.active() {
+                Some(1) => JoinStyle::Miter,
+                Some(2) => JoinStyle::Bevel,
+                _ => JoinStyle::Round,
+            };
+            (cap, join)
+        };
+
+        let join_combo = self.join_combo.clone();
+        let callback_cap = callback.clone();
+        self.cap_combo.connect_changed(move |cap_combo| {
+            let (cap, join) = style(cap_combo, &join_combo);
+            callback_cap(cap, join);
+        });
+
+        let cap_combo = self.cap_combo.clone();
+        self.join_combo.connect_changed(move |join_combo| {
+            let (cap, join) = style(&cap_combo, join_combo);
+            callback(cap, join);
+        });
+    }
+
     pub fn connect_save_clicked<F>(&self, callback: F)
     where
         F: Fn() + 'static,
@@ -258,6 +776,157 @@ impl Toolbar {
         });
     }
 
+    pub fn connect_copy_data_uri_clicked<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.copy_data_uri_button.connect_clicked(move |_| {
+            callback();
+        });
+    }
+
+    pub fn connect_upload_clicked<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.upload_button.connect_clicked(move |_| {
+            callback();
+        });
+    }
+
+    pub fn connect_open_with_opened<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        if let Some(popover) = self.open_with_button.popover() {
+            popover.connect_show(move |_| callback());
+        }
+    }
+
+    // Replace the application entries. Clicking one closes the menu and
+    // passes its index to `on_pick`.
+    pub fn set_open_with_entries<F>(&self, apps: &[gio::AppInfo], on_pick: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        while let Some(child) = self.open_with_box.first_child() {
+            self.open_with_box.remove(&child);
+        }
+
+        if apps.is_empty() {
+            self.open_with_box
+                .append(&Label::new(Some("No image applications found")));
+            return;
+        }
+
+        let on_pick = Rc::new(on_pick);
+        for (index, app) in apps.iter().enumerate() {
+            let entry = Box::new(Orientation::Horizontal, 6);
+            if let Some(icon) = app.icon() {
+                entry.append(&gtk4::Image::from_gicon(&icon));
+            }
+            entry.append(&Label::new(Some(app.display_name().as_str())));
+
+            let button = Button::new();
+            button.set_child(Some(&entry));
+            button.add_css_class("flat");
+
+            let on_pick = on_pick.clone();
+            let open_with_button = self.open_with_button.clone();
+            button.connect_clicked(move |_| {
+                open_with_button.popdown();
+                on_pick(index);
+            });
+            self.open_with_box.append(&button);
+        }
+    }
+
+    pub fn connect_save_project_clicked<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        let project_button = self.project_button.clone();
+        self.save_project_button.connect_clicked(move |_| {
+            project_button.popdown();
+            callback();
+        });
+    }
+
+    pub fn connect_open_project_clicked<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        let project_button = self.project_button.clone();
+        self.open_project_button.connect_clicked(move |_| {
+            project_button.popdown();
+            callback();
+        });
+    }
+
+    pub fn connect_history_opened<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        if let Some(popover) = self.history_button.popover() {
+            popover.connect_show(move |_| callback());
+        }
+    }
+
+    // Replace the history entries, newest first. Clicking one closes the
+    // menu and passes its index to `on_pick`.
+    pub fn set_history_entries<F>(&self, thumbnails: &[gdk4::Texture], on_pick: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        while let Some(child) = self.history_box.first_child() {
+            self.history_box.remove(&child);
+        }
+
+        if thumbnails.is_empty() {
+            self.history_box
+                .append(&Label::new(Some("Nothing copied yet")));
+            return;
+        }
+
+        let on_pick = Rc::new(on_pick);
+        for (index, texture) in thumbnails.iter().enumerate() {
+            let picture = gtk4::Picture::for_paintable(texture);
+            picture.set_can_shrink(true);
+            picture.set_size_request(160, 90);
+
+            let button = Button::new();
+            button.set_child(Some(&picture));
+            button.add_css_class("flat");
+            button.set_tooltip_text(Some(&format!("{}×{}", texture.width(), texture.height())));
+
+            let on_pick = on_pick.clone();
+            let history_button = self.history_button.clone();
+            button.connect_clicked(move |_| {
+                history_button.popdown();
+                on_pick(index);
+            });
+            self.history_box.append(&button);
+        }
+    }
+
+    pub fn connect_list_toggled<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        self.list_button.connect_toggled(move |button| {
+            callback(button.is_active());
+        });
+    }
+
+    pub fn connect_color_info_toggled<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        self.color_info_button.connect_toggled(move |button| {
+            callback(button.is_active());
+        });
+    }
+
     pub fn connect_clear_clicked<F>(&self, callback: F)
     where
         F: Fn() + 'static,
@@ -267,6 +936,47 @@ impl Toolbar {
         });
     }
 
+    pub fn connect_recrop_clicked<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.recrop_button.set_visible(true);
+        self.recrop_button.connect_clicked(move |_| {
+            callback();
+        });
+    }
+
+    pub fn set_carry_over_active(&self, active: bool) {
+        self.carry_over_button.set_active(active);
+    }
+
+    pub fn connect_carry_over_toggled<F>(&self, callback: F)
+    where
+        F: Fn(bool) + 'static,
+    {
+        self.carry_over_button.connect_toggled(move |button| {
+            callback(button.is_active());
+        });
+    }
+
+    pub fn connect_blur_outside_clicked<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.blur_outside_button.connect_clicked(move |_| {
+            callback();
+        });
+    }
+
+    pub fn connect_blur_faces_clicked<F>(&self, callback: F)
+    where
+        F: Fn() + 'static,
+    {
+        self.blur_faces_button.connect_clicked(move |_| {
+            callback();
+        });
+    }
+
     pub fn get_widget(&self) -> &Box {
         &self.widget
     }
@@ -278,10 +988,400 @@ impl Default for Toolbar {
     }
 }
 
+type Callback<T> = Rc<RefCell<Option<Rc<dyn Fn(T)>>>>;
+
+// Collapsible side panel listing every finished annotation, like layers
+#[derive(Clone)]
+pub struct AnnotationList {
+    pub widget: Revealer,
+    list_box: ListBox,
+    synced_revision: Rc<Cell<Option<u64>>>,
+    updating: Rc<Cell<bool>>,
+    on_selected: Callback<Option<usize>>,
+    on_visibility_toggled: Callback<(usize, bool)>,
+    on_note_changed: Callback<(usize, String)>,
+    on_delete: Callback<usize>,
+}
+
+// What a row shows about one annotation
+pub struct AnnotationEntry {
+    pub tool_type: ToolType,
+    pub color: RGBA,
+    pub visible: bool,
+    pub note: Option<String>,
+}
+
+impl AnnotationList {
+    pub fn new() -> Self {
+        let list_box = ListBox::new();
+        list_box.set_selection_mode(SelectionMode::Single);
+
+        let scrolled = ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .min_content_width(220)
+            .vexpand(true)
+            .child(&list_box)
+            .build();
+
+        let header = Label::new(Some("Annotations"));
+        header.set_halign(gtk4::Align::Start);
+        header.add_css_class("heading");
+
+        let content = Box::new(Orientation::Vertical, 6);
+        content.set_margin_start(6);
+        content.set_margin_end(6);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.append(&header);
+        content.append(&scrolled);
+
+        let widget = Revealer::new();
+        widget.set_transition_type(RevealerTransitionType::SlideLeft);
+        widget.set_child(Some(&content));
+        widget.set_reveal_child(false);
+
+        let list = Self {
+            widget,
+            list_box,
+            synced_revision: Rc::new(Cell::new(None)),
+            updating: Rc::new(Cell::new(false)),
+            on_selected: Rc::new(RefCell::new(None)),
+            on_visibility_toggled: Rc::new(RefCell::new(None)),
+            on_note_changed: Rc::new(RefCell::new(None)),
+            on_delete: Rc::new(RefCell::new(None)),
+        };
+
+        let updating = list.updating.clone();
+        let on_selected = list.on_selected.clone();
+        list.list_box.connect_row_selected(move |_, row| {
+            // Rebuilding the rows re-selects programmatically; don't echo that
+            if updating.get() {
+                return;
+            }
+            let callback = on_selected.borrow().clone();
+            if let Some(callback) = callback {
+                callback(row.map(|row| row.index() as usize));
+            }
+        });
+
+        list
+    }
+
+    pub fn set_revealed(&self, revealed: bool) {
+        self.widget.set_reveal_child(revealed);
+    }
+
+    // Rows are only rebuilt when the set of annotations has changed
+    pub fn needs_sync(&self, revision: u64) -> bool {
+        self.synced_revision.get() != Some(revision)
+    }
+
+    pub fn sync(&self, revision: u64, entries: &[AnnotationEntry], selected: Option<usize>) {
+        self.synced_revision.set(Some(revision));
+        self.updating.set(true);
+
+        while let Some(child) = self.list_box.first_child() {
+            self.list_box.remove(&child);
+        }
+
+        for (index, entry) in entries.iter().enumerate() {
+            self.list_box.append(&self.create_row(index, entry));
+        }
+
+        if let Some(row) = selected.and_then(|index| self.list_box.row_at_index(index as i32)) {
+            self.list_box.select_row(Some(&row));
+        }
+
+        self.updating.set(false);
+    }
+
+    // Highlight a row without reporting it back as a user selection
+    pub fn select(&self, index: Option<usize>) {
+        self.updating.set(true);
+        let row = index.and_then(|index| self.list_box.row_at_index(index as i32));
+        self.list_box.select_row(row.as_ref());
+        self.updating.set(false);
+    }
+
+    fn create_row(&self, index: usize, entry: &AnnotationEntry) -> ListBoxRow {
+        let color = entry.color;
+
+        let row_box = Box::new(Orientation::Horizontal, 6);
+        row_box.set_margin_start(3);
+        row_box.set_margin_end(3);
+        row_box.set_margin_top(3);
+        row_box.set_margin_bottom(3);
+
+        let swatch = DrawingArea::new();
+        swatch.set_content_width(16);
+        swatch.set_content_height(16);
+        swatch.set_valign(gtk4::Align::Center);
+        swatch.set_draw_func(move |_, ctx, width, height| {
+            ctx.set_source_rgba(
+                color.red() as f64,
+                color.green() as f64,
+                color.blue() as f64,
+                color.alpha() as f64,
+            );
+            ctx.rectangle(0.0, 0.0, width as f64, height as f64);
+            ctx.fill().ok();
+        });
+
+        let label = Label::new(Some(&format!("#{} {:?}", index + 1, entry.tool_type)));
+        label.set_halign(gtk4::Align::Start);
+        label.set_hexpand(true);
+        label.set_tooltip_text(entry.note.as_deref());
+
+        let visible_check = CheckButton::new();
+        visible_check.set_active(entry.visible);
+        visible_check.set_tooltip_text(Some("Show or hide this annotation"));
+        let on_visibility_toggled = self.on_visibility_toggled.clone();
+        visible_check.connect_toggled(move |button| {
+            let callback = on_visibility_toggled.borrow().clone();
+            if let Some(callback) = callback {
+                callback((index, button.is_active()));
+            }
+        });
+
+        let delete_button = Button::with_label("✕");
+        delete_button.set_tooltip_text(Some("Delete this annotation"));
+        delete_button.add_css_class("flat");
+        let on_delete = self.on_delete.clone();
+        delete_button.connect_clicked(move |_| {
+            let callback = on_delete.borrow().clone();
+            if let Some(callback) = callback {
+                callback(index);
+            }
+        });
+
+        row_box.append(&swatch);
+        row_box.append(&label);
+        row_box.append(&self.create_note_button(index, entry.note.as_deref()));
+        row_box.append(&visible_check);
+        row_box.append(&delete_button);
+
+        let row = ListBoxRow::new();
+        row.set_child(Some(&row_box));
+        row
+    }
+
+    // Popover for the annotation's note; Enter applies it
+    fn create_note_button(&self, index: usize, note: Option<&str>) -> MenuButton {
+        let entry = Entry::new();
+        entry.set_placeholder_text(Some("Note, not drawn on the image"));
+        entry.set_text(note.unwrap_or_default());
+        entry.set_width_chars(24);
+
+        let popover = Popover::new();
+        popover.set_child(Some(&entry));
+
+        let on_note_changed = self.on_note_changed.clone();
+        entry.connect_activate(clone!(@weak popover => move |entry| {
+            popover.popdown();
+            let callback = on_note_changed.borrow().clone();
+            if let Some(callback) = callback {
+                callback((index, entry.text().to_string()));
+            }
+        }));
+
+        let button = MenuButton::new();
+        button.set_label(if note.is_some() { "📝" } else { "✎" });
+        button.set_tooltip_text(Some("Edit this annotation's note"));
+        button.add_css_class("flat");
+        button.set_popover(Some(&popover));
+        button
+    }
+
+    pub fn connect_selected<F>(&self, callback: F)
+    where
+        F: Fn(Option<usize>) + 'static,
+    {
+        *self.on_selected.borrow_mut() = Some(Rc::new(callback));
+    }
+
+    pub fn connect_visibility_toggled<F>(&self, callback: F)
+    where
+        F: Fn(usize, bool) + 'static,
+    {
+        *self.on_visibility_toggled.borrow_mut() =
+            Some(Rc::new(move |(index, visible)| callback(index, visible)));
+    }
+
+    pub fn connect_note_changed<F>(&self, callback: F)
+    where
+        F: Fn(usize, String) + 'static,
+    {
+        *self.on_note_changed.borrow_mut() =
+            Some(Rc::new(move |(index, note)| callback(index, note)));
+    }
+
+    pub fn connect_delete_clicked<F>(&self, callback: F)
+    where
+        F: Fn(usize) + 'static,
+    {
+        *self.on_delete.borrow_mut() = Some(Rc::new(callback));
+    }
+
+    pub fn get_widget(&self) -> &Revealer {
+        &self.widget
+    }
+}
+
+impl Default for AnnotationList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Per-channel pixel counts for each of the 256 levels, red, green then blue
+type Histogram = [[u32; 256]; 3];
+
+// Collapsible side panel with the screenshot's RGB histogram and the color
+// under the pointer. Read-only analysis; nothing here reaches an export.
+#[derive(Clone)]
+pub struct ColorInfoPanel {
+    pub widget: Revealer,
+    histogram_area: DrawingArea,
+    histogram: Rc<RefCell<Option<Histogram>>>,
+    swatch_label: Label,
+    color_label: Label,
+}
+
+impl ColorInfoPanel {
+    pub fn new() -> Self {
+        let header = Label::new(Some("Colors"));
+        header.set_halign(gtk4::Align::Start);
+        header.add_css_class("heading");
+
+        let histogram: Rc<RefCell<Option<Histogram>>> = Rc::new(RefCell::new(None));
+        let histogram_area = DrawingArea::new();
+        histogram_area.set_content_width(256);
+        histogram_area.set_content_height(120);
+
+        let histogram_draw = histogram.clone();
+        histogram_area.set_draw_func(move |_, ctx, width, height| {
+            let (width, height) = (width as f64, height as f64);
+            ctx.set_source_rgb(0.12, 0.12, 0.12);
+            ctx.paint().ok();
+
+            let histogram = histogram_draw.borrow();
+            let Some(ref histogram) = *histogram else {
+                return;
+            };
+            let peak = histogram
+                .iter()
+                .flatten()
+                .copied()
+                .max()
+                .unwrap_or(0)
+                .max(1) as f64;
+            let column_width = width / 256.0;
+
+            let channel_colors = [(1.0, 0.2, 0.2), (0.2, 0.9, 0.2), (0.3, 0.4, 1.0)];
+            for (counts, (red, green, blue)) in histogram.iter().zip(channel_colors) {
+                ctx.move_to(0.0, height);
+                for (level, &count) in counts.iter().enumerate() {
+                    ctx.line_to(
+                        level as f64 * column_width,
+                        height - count as f64 / peak * height,
+                    );
+                }
+                ctx.line_to(width, height);
+                ctx.close_path();
+                ctx.set_source_rgba(red, green, blue, 0.45);
+                ctx.fill().ok();
+            }
+        });
+
+        let swatch_label = Label::new(None);
+        let color_label = Label::new(Some("Hover the image"));
+        color_label.set_halign(gtk4::Align::Start);
+        color_label.set_selectable(true);
+
+        let color_row = Box::new(Orientation::Horizontal, 6);
+        color_row.append(&swatch_label);
+        color_row.append(&color_label);
+
+        let content = Box::new(Orientation::Vertical, 6);
+        content.set_margin_start(6);
+        content.set_margin_end(6);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.append(&header);
+        content.append(&histogram_area);
+        content.append(&color_row);
+
+        let widget = Revealer::new();
+        widget.set_transition_type(RevealerTransitionType::SlideLeft);
+        widget.set_child(Some(&content));
+        widget.set_reveal_child(false);
+
+        Self {
+            widget,
+            histogram_area,
+            histogram,
+            swatch_label,
+            color_label,
+        }
+    }
+
+    // Counted once for the loaded screenshot
+    pub fn set_histogram(&self, image: &image::RgbaImage) {
+        let mut histogram: Histogram = [[0; 256]; 3];
+        for pixel in image.pixels() {
+            for (channel, &value) in pixel.0[..3].iter().enumerate() {
+                histogram[channel][value as usize] += 1;
+            }
+        }
+        *self.histogram.borrow_mut() = Some(histogram);
+        self.histogram_area.queue_draw();
+    }
+
+    // Position and straight-alpha color of the hovered pixel, or None when
+    // the pointer is off the image
+    pub fn set_pixel(&self, pixel: Option<(i32, i32, [u8; 4])>) {
+        match pixel {
+            Some((x, y, [red, green, blue, alpha])) => {
+                self.swatch_label.set_markup(&format!(
+                    "<span foreground=\"#{:02x}{:02x}{:02x}\" size=\"x-large\">■</span>",
+                    red, green, blue
+                ));
+                self.color_label.set_text(&format!(
+                    "({}, {})\n#{:02X}{:02X}{:02X}\nRGB {} {} {}  A {}",
+                    x, y, red, green, blue, red, green, blue, alpha
+                ));
+            }
+            None => {
+                self.swatch_label.set_text("");
+                self.color_label.set_text("Hover the image");
+            }
+        }
+    }
+
+    pub fn set_revealed(&self, revealed: bool) {
+        self.widget.set_reveal_child(revealed);
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.widget.reveals_child()
+    }
+
+    pub fn get_widget(&self) -> &Revealer {
+        &self.widget
+    }
+}
+
+impl Default for ColorInfoPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone)]
 pub struct StatusBar {
     pub widget: Box,
     status_label: Label,
+    tool_label: Label,
     coordinates_label: Label,
 }
 
@@ -296,16 +1396,21 @@ impl StatusBar {
         let status_label = Label::new(Some("Ready"));
         status_label.set_halign(gtk4::Align::Start);
 
+        let tool_label = Label::new(Some(""));
+        tool_label.set_halign(gtk4::Align::End);
+        tool_label.set_hexpand(true);
+
         let coordinates_label = Label::new(Some(""));
         coordinates_label.set_halign(gtk4::Align::End);
-        coordinates_label.set_hexpand(true);
 
         widget.append(&status_label);
+        widget.append(&tool_label);
         widget.append(&coordinates_label);
 
         Self {
             widget,
             status_label,
+            tool_label,
             coordinates_label,
         }
     }
@@ -314,11 +1419,45 @@ impl StatusBar {
         self.status_label.set_text(status);
     }
 
+    // Status message led by a swatch of the color
+    pub fn set_color_status(&self, name: &str, color: RGBA) {
+        let swatch = format!(
+            "#{:02x}{:02x}{:02x}",
+            (color.red() * 255.0).round() as u8,
+            (color.green() * 255.0).round() as u8,
+            (color.blue() * 255.0).round() as u8
+        );
+        self.status_label.set_markup(&format!(
+            "<span foreground=\"{}\">■</span> Color: {}",
+            swatch,
+            glib::markup_escape_text(name)
+        ));
+    }
+
+    pub fn set_tool_info(&self, tool: ToolType, thickness: f64) {
+        self.tool_label
+            .set_text(&format!("{:?} · {:.0}px", tool, thickness));
+    }
+
     pub fn set_coordinates(&self, x: f64, y: f64) {
         self.coordinates_label
             .set_text(&format!("({:.0}, {:.0})", x, y));
     }
 
+    // Pixel position and color of the hovered pixel in the pixel-grid view
+    pub fn set_pixel_info(&self, x: i32, y: i32, color: [u8; 4]) {
+        let [red, green, blue, alpha] = color;
+        let text = if alpha == 255 {
+            format!("({}, {}) #{:02X}{:02X}{:02X}", x, y, red, green, blue)
+        } else {
+            format!(
+                "({}, {}) #{:02X}{:02X}{:02X}{:02X}",
+                x, y, red, green, blue, alpha
+            )
+        };
+        self.coordinates_label.set_text(&text);
+    }
+
     pub fn clear_coordinates(&self) {
         self.coordinates_label.set_text("");
     }
@@ -0,0 +1,115 @@
+use anyhow::{anyhow, Result};
+use log::info;
+use std::io::Read;
+
+use crate::preferences::UploadPreferences;
+
+// Limit on how much of the host's response we read when looking for the URL
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+pub trait Uploader {
+    // Uploads an encoded PNG and returns the public URL for it
+    fn upload(&self, png: &[u8]) -> Result<String>;
+}
+
+// Posts the image as a multipart form to a generic HTTP endpoint that replies
+// with the URL of the uploaded image as its plain-text body
+pub struct HttpUploader {
+    url: String,
+    auth_header: Option<String>,
+    field_name: String,
+}
+
+impl HttpUploader {
+    pub fn from_preferences(preferences: &UploadPreferences) -> Option<Self> {
+        if preferences.url.trim().is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            url: preferences.url.trim().to_string(),
+            auth_header: Some(preferences.auth_header.trim().to_string())
+                .filter(|header| !header.is_empty()),
+            field_name: preferences.field_name.clone(),
+        })
+    }
+
+    fn multipart_body(&self, boundary: &str, png: &[u8]) -> Vec<u8> {
+        let mut body = Vec::with_capacity(png.len() + 256);
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"flint-screenshot.png\"\r\nContent-Type: image/png\r\n\r\n",
+                boundary, self.field_name
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(png);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        body
+    }
+}
+
+impl Uploader for HttpUploader {
+    fn upload(&self, png: &[u8]) -> Result<String> {
+        let boundary = format!(
+            "flint-{:x}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos())
+                .unwrap_or_default()
+        );
+
+        info!("Uploading {} bytes to {}", png.len(), self.url);
+
+        let mut request = ureq::post(&self.url).set(
+            "Content-Type",
+            &format!("multipart/form-data; boundary={}", boundary),
+        );
+
+        // Auth header is configured as a full "Name: value" line. It holds a
+        // secret, so it is never repeated in an error
+        if let Some(ref header) = self.auth_header {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Invalid auth header: expected 'Name: value'"))?;
+            request = request.set(name.trim(), value.trim());
+        }
+
+        let response = request
+            .send_bytes(&self.multipart_body(&boundary, png))
+            .map_err(|e| anyhow!("Request to {} failed: {}", self.url, e))?;
+
+        let mut body = String::new();
+        response
+            .into_reader()
+            .take(MAX_RESPONSE_BYTES)
+            .read_to_string(&mut body)
+            .map_err(|e| anyhow!("Failed to read upload response: {}", e))?;
+
+        let url = body.trim();
+        if url.is_empty() {
+            return Err(anyhow!("Upload response did not contain a URL"));
+        }
+
+        info!("Upload finished: {}", url);
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_auth_header_is_not_repeated_in_the_error() {
+        let uploader = HttpUploader {
+            url: "http://127.0.0.1:9/upload".to_string(),
+            auth_header: Some("Bearer secret-token".to_string()),
+            field_name: "file".to_string(),
+        };
+
+        let error = uploader.upload(b"png").unwrap_err().to_string();
+        assert!(error.starts_with("Invalid auth header"), "{}", error);
+        assert!(!error.contains("secret-token"), "{}", error);
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Result};
+use cairo::{Context, FontSlant, FontWeight};
+use log::warn;
+use std::path::PathBuf;
+
+use crate::preferences::{self, WatermarkPosition, WatermarkPreferences};
+use crate::stamps;
+
+// Distance from the export's edges, and between the logo and the text
+const MARGIN: f64 = 16.0;
+const SPACING: f64 = 8.0;
+
+fn logo_path(configured: &str) -> PathBuf {
+    match configured.strip_prefix("~/") {
+        Some(relative) => glib::home_dir().join(relative),
+        None => PathBuf::from(configured),
+    }
+}
+
+// Whether there is anything to draw at all, so the save dialog only offers
+// the watermark once one has been set up
+pub fn is_configured() -> bool {
+    let watermark = &preferences::get().watermark;
+    !watermark.text.is_empty() || !watermark.image.is_empty()
+}
+
+// Where a block of the given size goes on an export of the given size
+fn block_origin(
+    position: WatermarkPosition,
+    (block_width, block_height): (f64, f64),
+    (width, height): (f64, f64),
+) -> (f64, f64) {
+    let left = MARGIN;
+    let right = width - block_width - MARGIN;
+    let top = MARGIN;
+    let bottom = height - block_height - MARGIN;
+
+    match position {
+        WatermarkPosition::TopLeft => (left, top),
+        WatermarkPosition::TopRight => (right, top),
+        WatermarkPosition::BottomLeft => (left, bottom),
+        WatermarkPosition::BottomRight => (right, bottom),
+        WatermarkPosition::Center => ((width - block_width) / 2.0, (height - block_height) / 2.0),
+    }
+}
+
+// Draw the configured logo and text over an export `width` by `height`
+// pixels, with the context in export pixels. The watermark is never a
+// stroke, so it looks the same on every export whatever was annotated.
+pub fn draw(ctx: &Context, width: f64, height: f64) -> Result<()> {
+    draw_with(ctx, width, height, &preferences::get().watermark)
+}
+
+fn draw_with(
+    ctx: &Context,
+    width: f64,
+    height: f64,
+    watermark: &WatermarkPreferences,
+) -> Result<()> {
+    // A logo that cannot be read still leaves the text
+    let logo = if watermark.image.is_empty() {
+        None
+    } else {
+        let path = logo_path(&watermark.image);
+        image::open(&path)
+            .map_err(|e| warn!("Failed to load watermark image {}: {}", path.display(), e))
+            .ok()
+            .map(|image| stamps::surface_from_image(&image.to_rgba8()))
+            .transpose()?
+    };
+
+    ctx.save()
+        .map_err(|e| anyhow!("Failed to save context: {}", e))?;
+    ctx.select_font_face("Sans", FontSlant::Normal, FontWeight::Bold);
+    ctx.set_font_size(watermark.font_size);
+
+    let (logo_width, logo_height) = logo.as_ref().map_or((0.0, 0.0), |logo| {
+        (logo.width() as f64, logo.height() as f64)
+    });
+    let (text_width, text_height) = if watermark.text.is_empty() {
+        (0.0, 0.0)
+    } else {
+        let extents = ctx
+            .text_extents(&watermark.text)
+            .map_err(|e| anyhow!("Failed to measure watermark text: {}", e))?;
+        (extents.x_advance(), extents.height())
+    };
+    let spacing = if logo.is_some() && text_width > 0.0 {
+        SPACING
+    } else {
+        0.0
+    };
+    let block = (
+        logo_width + spacing + text_width,
+        logo_height.max(text_height),
+    );
+    let (x, y) = block_origin(watermark.position, block, (width, height));
+
+    // Drawn as one group so overlapping parts don't add up their opacity
+    ctx.push_group();
+    if let Some(ref logo) = logo {
+        ctx.set_source_surface(logo, x, y + (block.1 - logo_height) / 2.0)
+            .map_err(|e| anyhow!("Failed to set watermark image: {}", e))?;
+        ctx.paint()
+            .map_err(|e| anyhow!("Failed to draw watermark image: {}", e))?;
+    }
+    if text_width > 0.0 {
+        // Light text with a dark outline reads on any background
+        let baseline = y + (block.1 + text_height) / 2.0;
+        ctx.move_to(x + logo_width + spacing, baseline);
+        ctx.text_path(&watermark.text);
+        ctx.set_source_rgb(0.0, 0.0, 0.0);
+        ctx.set_line_width((watermark.font_size / 8.0).max(1.0));
+        ctx.stroke_preserve()
+            .map_err(|e| anyhow!("Failed to draw watermark text: {}", e))?;
+        ctx.set_source_rgb(1.0, 1.0, 1.0);
+        ctx.fill()
+            .map_err(|e| anyhow!("Failed to draw watermark text: {}", e))?;
+    }
+    ctx.pop_group_to_source()
+        .map_err(|e| anyhow!("Failed to composite watermark: {}", e))?;
+    ctx.paint_with_alpha(watermark.opacity.clamp(0.0, 1.0))
+        .map_err(|e| anyhow!("Failed to composite watermark: {}", e))?;
+
+    ctx.restore()
+        .map_err(|e| anyhow!("Failed to restore context: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cairo::{Format, ImageSurface};
+
+    // Bounding box (left, top, right, bottom) of every pixel the watermark
+    // touched on a 400x300 export
+    fn drawn_area(position: WatermarkPosition) -> (usize, usize, usize, usize) {
+        let watermark = WatermarkPreferences {
+            text: "Flint".to_string(),
+            position,
+            opacity: 1.0,
+            ..WatermarkPreferences::default()
+        };
+        let mut surface = ImageSurface::create(Format::ARgb32, 400, 300).unwrap();
+        draw_with(&Context::new(&surface).unwrap(), 400.0, 300.0, &watermark).unwrap();
+        surface.flush();
+
+        let stride = surface.stride() as usize;
+        let data = surface.data().unwrap();
+        let mut area = (usize::MAX, usize::MAX, 0, 0);
+        for y in 0..300 {
+            for x in 0..400 {
+                if data[y * stride + x * 4 + 3] > 0 {
+                    area = (area.0.min(x), area.1.min(y), area.2.max(x), area.3.max(y));
+                }
+            }
+        }
+        area
+    }
+
+    #[test]
+    fn watermark_lands_in_the_configured_corner() {
+        let (left, top, right, bottom) = drawn_area(WatermarkPosition::BottomRight);
+        assert!(left > 200 && top > 150, "drawn from ({}, {})", left, top);
+        // Close to the margin, allowing for side bearings and the outline
+        assert!((376..=386).contains(&right) && (278..=286).contains(&bottom));
+
+        let (left, top, right, bottom) = drawn_area(WatermarkPosition::TopLeft);
+        assert!(
+            right < 200 && bottom < 150,
+            "drawn to ({}, {})",
+            right,
+            bottom
+        );
+        assert!((12..=20).contains(&left) && (12..=20).contains(&top));
+    }
+
+    #[test]
+    fn blocks_keep_the_margin_from_the_edges() {
+        let export = (400.0, 300.0);
+        let block = (100.0, 20.0);
+
+        assert_eq!(
+            block_origin(WatermarkPosition::TopLeft, block, export),
+            (16.0, 16.0)
+        );
+        assert_eq!(
+            block_origin(WatermarkPosition::TopRight, block, export),
+            (284.0, 16.0)
+        );
+        assert_eq!(
+            block_origin(WatermarkPosition::BottomLeft, block, export),
+            (16.0, 264.0)
+        );
+        assert_eq!(
+            block_origin(WatermarkPosition::BottomRight, block, export),
+            (284.0, 264.0)
+        );
+        assert_eq!(
+            block_origin(WatermarkPosition::Center, block, export),
+            (150.0, 140.0)
+        );
+    }
+}
@@ -1,11 +1,83 @@
-use anyhow::{anyhow, Result};
 use log::{info, warn};
+use thiserror::Error;
+
+// Why a window could not be listed or captured. Unsupported covers Wayland
+// and builds without X11, where the UI should point at region capture.
+#[derive(Debug, Error)]
+pub enum WindowError {
+    #[error("Failed to connect to X11 server: {0}")]
+    NoConnection(String),
+
+    #[error("{0}")]
+    Unsupported(&'static str),
+
+    #[error("Window is not visible on screen")]
+    NotVisible,
+
+    #[error("Window was resized or closed while it was being captured")]
+    Changed,
+
+    #[error("No window found at ({x}, {y})")]
+    NoWindowAt { x: i32, y: i32 },
+
+    #[error("No window has the input focus")]
+    NoActiveWindow,
+
+    #[error("Window has no usable {0} property")]
+    MissingProperty(String),
+
+    #[error("Unsupported color depth: {0}")]
+    UnsupportedDepth(u8),
+
+    #[error("Invalid window image: {0}")]
+    InvalidImage(String),
+
+    #[error("X11 request failed: {0}")]
+    Protocol(String),
+
+    #[error("Failed to convert window image to PNG: {0}")]
+    Encode(#[from] image::ImageError),
+}
+
+#[cfg(feature = "x11")]
+impl From<x11rb::errors::ConnectionError> for WindowError {
+    fn from(error: x11rb::errors::ConnectionError) -> Self {
+        Self::Protocol(error.to_string())
+    }
+}
+
+#[cfg(feature = "x11")]
+impl From<x11rb::errors::ReplyError> for WindowError {
+    fn from(error: x11rb::errors::ReplyError) -> Self {
+        Self::Protocol(error.to_string())
+    }
+}
+
+// An X11 error while capturing a window from the list almost always means it
+// went away or changed size since it was listed
+#[cfg(feature = "x11")]
+fn window_changed(error: x11rb::errors::ReplyError) -> WindowError {
+    match error {
+        x11rb::errors::ReplyError::X11Error(e) => {
+            warn!("Window capture failed with {:?}", e);
+            WindowError::Changed
+        }
+        error => error.into(),
+    }
+}
+
+type Result<T> = std::result::Result<T, WindowError>;
 
 #[derive(Debug, Clone)]
 pub struct WindowInfo {
     pub id: u64,
+    // Outermost window including the WM's decorations; the same as `id` for
+    // windows without a frame
+    pub frame_id: u64,
     pub title: String,
     pub class: String,
+    pub x: i32,
+    pub y: i32,
     pub width: u32,
     pub height: u32,
     pub is_minimized: bool,
@@ -60,6 +132,114 @@ impl WindowManager {
             WindowBackend::Wayland(manager) => manager.capture_window(window_id),
         }
     }
+
+    // The window shown at a point in root coordinates (device pixels)
+    pub fn window_at_point(&self, x: i32, y: i32) -> Result<WindowInfo> {
+        match &self.backend {
+            WindowBackend::X11(manager) => manager.window_at_point(x, y),
+            WindowBackend::Wayland(_) => Err(WindowError::Unsupported(
+                "Wayland does not reveal which window is under the pointer",
+            )),
+        }
+    }
+
+    // The window with the input focus, as the window manager reports it
+    pub fn active_window(&self) -> Result<WindowInfo> {
+        match &self.backend {
+            WindowBackend::X11(manager) => manager.active_window(),
+            WindowBackend::Wayland(_) => Err(WindowError::Unsupported(
+                "Wayland does not reveal which window has the focus. Use Selection instead.",
+            )),
+        }
+    }
+
+    pub fn is_window_mapped(&self, window_id: u64) -> Result<bool> {
+        match &self.backend {
+            WindowBackend::X11(manager) => manager.is_window_mapped(window_id),
+            WindowBackend::Wayland(_) => Err(WindowError::Unsupported(
+                "Window map state is not available on Wayland",
+            )),
+        }
+    }
+
+    pub fn move_window(&self, window_id: u64, x: i32, y: i32) -> Result<()> {
+        match &self.backend {
+            WindowBackend::X11(manager) => manager.move_window(window_id, x, y),
+            WindowBackend::Wayland(_) => Err(WindowError::Unsupported(
+                "Wayland compositors place windows themselves",
+            )),
+        }
+    }
+
+    // Ask the window manager to keep a window above all others
+    pub fn keep_above(&self, window_id: u64) -> Result<()> {
+        match &self.backend {
+            WindowBackend::X11(manager) => manager.keep_above(window_id),
+            WindowBackend::Wayland(_) => Err(WindowError::Unsupported(
+                "Wayland compositors decide which windows stay on top",
+            )),
+        }
+    }
+}
+
+// X11 window ID behind a GTK window, once it has been realized
+#[cfg(feature = "x11")]
+pub fn x11_window_id(window: &gtk4::ApplicationWindow) -> Option<u64> {
+    use gtk4::prelude::*;
+
+    let surface = window.surface()?;
+    let surface = surface.downcast::<gdk4_x11::X11Surface>().ok()?;
+    Some(surface.xid() as u64)
+}
+
+#[cfg(not(feature = "x11"))]
+pub fn x11_window_id(_window: &gtk4::ApplicationWindow) -> Option<u64> {
+    None
+}
+
+// Why window listing and capture cannot work in this session, if they
+// cannot. WindowManager falls back to X11 under Wayland, which only sees
+// XWayland windows, so the UI disables window capture there up front.
+pub fn window_capture_unavailable_reason() -> Option<&'static str> {
+    if std::env::var("WAYLAND_DISPLAY").is_ok() {
+        Some(
+            "Not available on Wayland, where applications cannot list or capture \
+             other windows. Use Selection instead.",
+        )
+    } else {
+        None
+    }
+}
+
+// Visible windows grouped by WM_CLASS, in the order each class first appears
+pub fn group_by_class(windows: &[WindowInfo]) -> Vec<(String, Vec<WindowInfo>)> {
+    let mut groups: Vec<(String, Vec<WindowInfo>)> = Vec::new();
+    for window in windows.iter().filter(|window| !window.is_minimized) {
+        let class = window.class.replace('\0', "");
+        match groups.iter_mut().find(|(name, _)| *name == class) {
+            Some((_, members)) => members.push(window.clone()),
+            None => groups.push((class, vec![window.clone()])),
+        }
+    }
+    groups
+}
+
+// Smallest rectangle in root coordinates covering all the windows, cut off
+// at the top-left corner of the screen
+pub fn bounding_box(windows: &[WindowInfo]) -> Option<(i32, i32, i32, i32)> {
+    let (left, top, right, bottom) = windows.iter().fold(
+        (i32::MAX, i32::MAX, i32::MIN, i32::MIN),
+        |(left, top, right, bottom), window| {
+            (
+                left.min(window.x),
+                top.min(window.y),
+                right.max(window.x.saturating_add(window.width as i32)),
+                bottom.max(window.y.saturating_add(window.height as i32)),
+            )
+        },
+    );
+    let (left, top) = (left.max(0), top.max(0));
+    (right > left && bottom > top).then(|| (left, top, right - left, bottom - top))
 }
 
 // X11 Window Manager Implementation
@@ -76,12 +256,12 @@ impl X11WindowManager {
                 Ok((conn, _)) => Ok(Self {
                     connection: Some(conn),
                 }),
-                Err(e) => Err(anyhow!("Failed to connect to X11 server: {}", e)),
+                Err(e) => Err(WindowError::NoConnection(e.to_string())),
             }
         }
         #[cfg(not(feature = "x11"))]
         {
-            Err(anyhow!("X11 support not compiled in"))
+            Err(WindowError::Unsupported("X11 support not compiled in"))
         }
     }
 
@@ -94,16 +274,24 @@ impl X11WindowManager {
             let conn = self
                 .connection
                 .as_ref()
-                .ok_or_else(|| anyhow!("No X11 connection"))?;
+                .ok_or_else(|| WindowError::NoConnection("not connected".to_string()))?;
             let screen = &conn.setup().roots[0];
             let root = screen.root;
 
             // Query the window tree
             let tree_reply = conn.query_tree(root)?.reply()?;
+            let wm_state = conn.intern_atom(false, b"WM_STATE")?.reply()?.atom;
             let mut windows = Vec::new();
 
-            for &window_id in &tree_reply.children {
-                if let Ok(window_info) = self.get_window_info(conn, window_id) {
+            for &top_level in &tree_reply.children {
+                // Windows the WM does not manage have no client below them
+                let window_id = self
+                    .find_client_window(conn, top_level, wm_state)
+                    .unwrap_or(top_level);
+
+                if let Ok(mut window_info) = self.get_window_info(conn, window_id) {
+                    window_info.frame_id = top_level as u64;
+
                     // Filter out windows that shouldn't be captured
                     if !window_info.title.is_empty()
                         && !window_info.is_minimized
@@ -119,7 +307,135 @@ impl X11WindowManager {
         }
         #[cfg(not(feature = "x11"))]
         {
-            Err(anyhow!("X11 support not compiled in"))
+            Err(WindowError::Unsupported("X11 support not compiled in"))
+        }
+    }
+
+    // Reparenting window managers put each client inside one or more frame
+    // windows, so the root's children are usually frames with no title and
+    // the application content a grandchild or deeper. The WM sets WM_STATE
+    // on every client it manages, so the client is the descendant with it.
+    #[cfg(feature = "x11")]
+    fn find_client_window(
+        &self,
+        conn: &impl x11rb::connection::Connection,
+        window_id: u32,
+        wm_state: u32,
+    ) -> Option<u32> {
+        use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+        let property = conn
+            .get_property(false, window_id, wm_state, AtomEnum::ANY, 0, 0)
+            .ok()?
+            .reply()
+            .ok()?;
+        if property.type_ != u32::from(AtomEnum::NONE) {
+            return Some(window_id);
+        }
+
+        // Children are listed bottom to top; the client is normally the
+        // topmost one
+        let tree = conn.query_tree(window_id).ok()?.reply().ok()?;
+        tree.children
+            .iter()
+            .rev()
+            .find_map(|&child| self.find_client_window(conn, child, wm_state))
+    }
+
+    fn window_at_point(&self, x: i32, y: i32) -> Result<WindowInfo> {
+        #[cfg(feature = "x11")]
+        {
+            use x11rb::connection::Connection;
+            use x11rb::protocol::xproto::ConnectionExt;
+
+            let conn = self
+                .connection
+                .as_ref()
+                .ok_or_else(|| WindowError::NoConnection("not connected".to_string()))?;
+            let root = conn.setup().roots[0].root;
+
+            // The server reports the topmost mapped top-level containing the
+            // point, honouring stacking order and window shapes
+            let reply = conn
+                .translate_coordinates(root, root, x as i16, y as i16)?
+                .reply()?;
+            if reply.child == x11rb::NONE {
+                return Err(WindowError::NoWindowAt { x, y });
+            }
+
+            let top_level = reply.child;
+            let wm_state = conn.intern_atom(false, b"WM_STATE")?.reply()?.atom;
+            let window_id = self
+                .find_client_window(conn, top_level, wm_state)
+                .unwrap_or(top_level);
+
+            let mut window_info = self.get_window_info(conn, window_id)?;
+            window_info.frame_id = top_level as u64;
+            info!(
+                "Window at ({}, {}): {} (ID: {})",
+                x, y, window_info.title, window_info.id
+            );
+            Ok(window_info)
+        }
+        #[cfg(not(feature = "x11"))]
+        {
+            let _ = (x, y);
+            Err(WindowError::Unsupported("X11 support not compiled in"))
+        }
+    }
+
+    // EWMH window managers keep the focused client in _NET_ACTIVE_WINDOW on
+    // the root window. Its frame is the ancestor directly below the root.
+    fn active_window(&self) -> Result<WindowInfo> {
+        #[cfg(feature = "x11")]
+        {
+            use x11rb::connection::Connection;
+            use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+            let conn = self
+                .connection
+                .as_ref()
+                .ok_or_else(|| WindowError::NoConnection("not connected".to_string()))?;
+            let root = conn.setup().roots[0].root;
+
+            let atom = conn
+                .intern_atom(false, b"_NET_ACTIVE_WINDOW")?
+                .reply()?
+                .atom;
+            let reply = conn
+                .get_property(false, root, atom, AtomEnum::WINDOW, 0, 1)?
+                .reply()?;
+            if reply.type_ == u32::from(AtomEnum::NONE) {
+                return Err(WindowError::MissingProperty(
+                    "_NET_ACTIVE_WINDOW".to_string(),
+                ));
+            }
+            let window_id = reply
+                .value32()
+                .and_then(|mut values| values.next())
+                .filter(|&window| window != x11rb::NONE)
+                .ok_or(WindowError::NoActiveWindow)?;
+
+            let mut top_level = window_id;
+            loop {
+                let tree = conn.query_tree(top_level)?.reply()?;
+                if tree.parent == root || tree.parent == x11rb::NONE {
+                    break;
+                }
+                top_level = tree.parent;
+            }
+
+            let mut window_info = self.get_window_info(conn, window_id)?;
+            window_info.frame_id = top_level as u64;
+            info!(
+                "Active window: {} (ID: {})",
+                window_info.title, window_info.id
+            );
+            Ok(window_info)
+        }
+        #[cfg(not(feature = "x11"))]
+        {
+            Err(WindowError::Unsupported("X11 support not compiled in"))
         }
     }
 
@@ -134,12 +450,17 @@ impl X11WindowManager {
         // Get window geometry
         let geom_reply = conn.get_geometry(window_id)?.reply()?;
 
+        // Geometry is relative to the parent, which for a client window is
+        // its frame, so translate the origin to root coordinates
+        let root = conn.setup().roots[0].root;
+        let origin = conn.translate_coordinates(window_id, root, 0, 0)?.reply()?;
+
         // Get window attributes
         let attrs_reply = conn.get_window_attributes(window_id)?.reply()?;
 
         // Skip invisible or unmapped windows
         if attrs_reply.map_state != MapState::VIEWABLE {
-            return Err(anyhow!("Window not viewable"));
+            return Err(WindowError::NotVisible);
         }
 
         // Get window title
@@ -154,8 +475,11 @@ impl X11WindowManager {
 
         Ok(WindowInfo {
             id: window_id as u64,
+            frame_id: window_id as u64,
             title,
             class,
+            x: origin.dst_x as i32,
+            y: origin.dst_y as i32,
             width: geom_reply.width as u32,
             height: geom_reply.height as u32,
             is_minimized: false, // We already filtered out non-viewable windows
@@ -202,8 +526,10 @@ impl X11WindowManager {
             .get_property(false, window_id, atom, AtomEnum::ANY, 0, 1024)?
             .reply()?;
 
+        let missing =
+            || WindowError::MissingProperty(String::from_utf8_lossy(property_name).into_owned());
         if reply.value.is_empty() {
-            return Err(anyhow!("Property not found"));
+            return Err(missing());
         }
 
         // Handle different text encodings
@@ -214,12 +540,12 @@ impl X11WindowManager {
                 .trim()
                 .to_string()
         } else {
-            return Err(anyhow!("Unsupported text format"));
+            return Err(missing());
         };
 
         // Ensure we don't return empty strings
         if text.is_empty() {
-            return Err(anyhow!("Property is empty"));
+            return Err(missing());
         }
 
         Ok(text)
@@ -228,33 +554,67 @@ impl X11WindowManager {
     fn capture_window(&self, window_id: u64) -> Result<Vec<u8>> {
         #[cfg(feature = "x11")]
         {
+            use x11rb::connection::Connection;
             use x11rb::protocol::xproto::{ConnectionExt, ImageFormat};
 
             let conn = self
                 .connection
                 .as_ref()
-                .ok_or_else(|| anyhow!("No X11 connection"))?;
+                .ok_or_else(|| WindowError::NoConnection("not connected".to_string()))?;
             let window_id = window_id as u32;
 
-            // Get window geometry
-            let geom_reply = conn.get_geometry(window_id)?.reply()?;
-            let width = geom_reply.width;
-            let height = geom_reply.height;
+            // Get window geometry, fresh rather than what was listed
+            let geom_reply = conn
+                .get_geometry(window_id)?
+                .reply()
+                .map_err(window_changed)?;
+            let border = geom_reply.border_width as i32;
+            let root = conn.setup().roots[0].root;
+            let root_geom = conn.get_geometry(root)?.reply()?;
+
+            // Geometry is relative to the parent (often a WM frame), so
+            // translate the window's origin to root coordinates
+            let origin = conn
+                .translate_coordinates(window_id, root, 0, 0)?
+                .reply()
+                .map_err(window_changed)?;
+            let origin_x = origin.dst_x as i32;
+            let origin_y = origin.dst_y as i32;
+
+            // Outer edges including the border, clipped to the screen since
+            // only the visible part of a window can be read back
+            let left = (origin_x - border).max(0);
+            let top = (origin_y - border).max(0);
+            let right = (origin_x + geom_reply.width as i32 + border).min(root_geom.width as i32);
+            let bottom =
+                (origin_y + geom_reply.height as i32 + border).min(root_geom.height as i32);
+
+            if right <= left || bottom <= top {
+                return Err(WindowError::NotVisible);
+            }
 
-            info!("Capturing window directly: {}x{}", width, height);
+            let width = (right - left) as u16;
+            let height = (bottom - top) as u16;
 
-            // Capture the window image directly using X11
+            info!(
+                "Capturing window directly: {}x{} at ({}, {}), border {}",
+                width, height, left, top, border
+            );
+
+            // Capture the window image directly using X11; coordinates are
+            // relative to the window's inside corner, so the border is negative
             let image_reply = conn
                 .get_image(
                     ImageFormat::Z_PIXMAP,
                     window_id,
-                    0,
-                    0,
+                    (left - origin_x) as i16,
+                    (top - origin_y) as i16,
                     width,
                     height,
                     u32::MAX,
                 )?
-                .reply()?;
+                .reply()
+                .map_err(window_changed)?;
 
             let image_data = image_reply.data;
             let depth = image_reply.depth;
@@ -272,7 +632,87 @@ impl X11WindowManager {
         }
         #[cfg(not(feature = "x11"))]
         {
-            Err(anyhow!("X11 support not compiled in"))
+            Err(WindowError::Unsupported("X11 support not compiled in"))
+        }
+    }
+
+    fn move_window(&self, window_id: u64, x: i32, y: i32) -> Result<()> {
+        #[cfg(feature = "x11")]
+        {
+            use x11rb::connection::Connection;
+            use x11rb::protocol::xproto::{ConfigureWindowAux, ConnectionExt};
+
+            let conn = self
+                .connection
+                .as_ref()
+                .ok_or_else(|| WindowError::NoConnection("not connected".to_string()))?;
+            conn.configure_window(window_id as u32, &ConfigureWindowAux::new().x(x).y(y))?;
+            conn.flush()?;
+
+            Ok(())
+        }
+        #[cfg(not(feature = "x11"))]
+        {
+            let _ = (window_id, x, y);
+            Err(WindowError::Unsupported("X11 support not compiled in"))
+        }
+    }
+
+    fn keep_above(&self, window_id: u64) -> Result<()> {
+        #[cfg(feature = "x11")]
+        {
+            use x11rb::connection::Connection;
+            use x11rb::protocol::xproto::{ClientMessageEvent, ConnectionExt, EventMask};
+
+            let conn = self
+                .connection
+                .as_ref()
+                .ok_or_else(|| WindowError::NoConnection("not connected".to_string()))?;
+            let wm_state = conn.intern_atom(false, b"_NET_WM_STATE")?.reply()?.atom;
+            let above = conn
+                .intern_atom(false, b"_NET_WM_STATE_ABOVE")?
+                .reply()?
+                .atom;
+            let root = conn.setup().roots[0].root;
+
+            // EWMH state change: 1 adds the state, and the last 1 marks the
+            // request as coming from a normal application
+            let event =
+                ClientMessageEvent::new(32, window_id as u32, wm_state, [1, above, 0, 1, 0]);
+            conn.send_event(
+                false,
+                root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )?;
+            conn.flush()?;
+
+            Ok(())
+        }
+        #[cfg(not(feature = "x11"))]
+        {
+            let _ = window_id;
+            Err(WindowError::Unsupported("X11 support not compiled in"))
+        }
+    }
+
+    fn is_window_mapped(&self, window_id: u64) -> Result<bool> {
+        #[cfg(feature = "x11")]
+        {
+            use x11rb::protocol::xproto::{ConnectionExt, MapState};
+
+            let conn = self
+                .connection
+                .as_ref()
+                .ok_or_else(|| WindowError::NoConnection("not connected".to_string()))?;
+            let attributes = conn.get_window_attributes(window_id as u32)?.reply()?;
+
+            Ok(attributes.map_state != MapState::UNMAPPED)
+        }
+        #[cfg(not(feature = "x11"))]
+        {
+            let _ = window_id;
+            Err(WindowError::Unsupported("X11 support not compiled in"))
         }
     }
 
@@ -291,39 +731,53 @@ impl X11WindowManager {
             width, height, depth
         );
 
-        let mut rgba_data = Vec::with_capacity((width * height * 4) as usize);
-
-        if depth == 24 || depth == 32 {
-            // Handle 24-bit or 32-bit color depth
-            let bytes_per_pixel = if depth == 24 { 4 } else { 4 }; // X11 typically uses 4 bytes even for 24-bit
+        if depth != 24 && depth != 32 {
+            return Err(WindowError::UnsupportedDepth(depth));
+        }
 
-            for chunk in image_data.chunks_exact(bytes_per_pixel) {
-                if chunk.len() >= 3 {
-                    // X11 typically stores as BGRA or BGRx
-                    let b = chunk[0];
-                    let g = chunk[1];
-                    let r = chunk[2];
-                    let a = if chunk.len() >= 4 { chunk[3] } else { 255 };
+        // X11 uses 4 bytes per pixel for both 24-bit and 32-bit color, with
+        // no row padding. Any other length means the window changed size
+        // between the geometry query and the image.
+        let bytes_per_pixel = 4;
+        let expected = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(bytes_per_pixel))
+            .ok_or_else(|| {
+                WindowError::InvalidImage(format!("{}x{} pixels is too large", width, height))
+            })?;
+        if image_data.len() != expected {
+            warn!(
+                "Window image has {} bytes, expected {} for {}x{}",
+                image_data.len(),
+                expected,
+                width,
+                height
+            );
+            return Err(WindowError::Changed);
+        }
 
-                    rgba_data.extend_from_slice(&[r, g, b, a]);
-                }
-            }
-        } else {
-            return Err(anyhow!("Unsupported color depth: {}", depth));
+        let mut rgba_data = Vec::with_capacity(expected);
+        for chunk in image_data.chunks_exact(bytes_per_pixel) {
+            // X11 stores pixels as BGRA or BGRx
+            let (b, g, r, a) = (chunk[0], chunk[1], chunk[2], chunk[3]);
+            rgba_data.extend_from_slice(&[r, g, b, a]);
         }
 
         // Create RGBA image
-        let img: ImageBuffer<Rgba<u8>, Vec<u8>> =
-            ImageBuffer::from_raw(width, height, rgba_data)
-                .ok_or_else(|| anyhow!("Failed to create image buffer from RGBA data"))?;
+        let img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(width, height, rgba_data)
+            .ok_or_else(|| {
+            WindowError::InvalidImage(format!(
+                "{}x{} pixels do not match the image data",
+                width, height
+            ))
+        })?;
 
         // Convert to PNG
         let mut buffer = Vec::new();
         img.write_to(
             &mut std::io::Cursor::new(&mut buffer),
             image::ImageFormat::Png,
-        )
-        .map_err(|e| anyhow!("Failed to convert to PNG: {}", e))?;
+        )?;
 
         info!("Successfully converted to PNG: {} bytes", buffer.len());
         Ok(buffer)
@@ -343,13 +797,13 @@ impl WaylandWindowManager {
             // For now, we'll return an error as Wayland window enumeration
             // is complex and requires compositor-specific protocols
             warn!("Wayland window enumeration is not fully implemented");
-            Err(anyhow!(
-                "Wayland window enumeration not yet supported. Window selection works only on X11."
+            Err(WindowError::Unsupported(
+                "Wayland window enumeration not yet supported. Window selection works only on X11.",
             ))
         }
         #[cfg(not(feature = "wayland"))]
         {
-            Err(anyhow!("Wayland support not compiled in"))
+            Err(WindowError::Unsupported("Wayland support not compiled in"))
         }
     }
 
@@ -359,24 +813,24 @@ impl WaylandWindowManager {
             // On Wayland, window enumeration is restricted for security reasons.
             // Most compositors don't provide a way to list all windows.
             // This would require compositor-specific protocols or using portals.
-            Err(anyhow!("Wayland window enumeration is not supported due to security restrictions. Use X11 or select a screen region instead."))
+            Err(WindowError::Unsupported("Wayland window enumeration is not supported due to security restrictions. Use X11 or select a screen region instead."))
         }
         #[cfg(not(feature = "wayland"))]
         {
-            Err(anyhow!("Wayland support not compiled in"))
+            Err(WindowError::Unsupported("Wayland support not compiled in"))
         }
     }
 
     fn capture_window(&self, _window_id: u64) -> Result<Vec<u8>> {
         #[cfg(feature = "wayland")]
         {
-            Err(anyhow!(
-                "Wayland window capture is not supported. Use screen or region capture instead."
+            Err(WindowError::Unsupported(
+                "Wayland window capture is not supported. Use screen or region capture instead.",
             ))
         }
         #[cfg(not(feature = "wayland"))]
         {
-            Err(anyhow!("Wayland support not compiled in"))
+            Err(WindowError::Unsupported("Wayland support not compiled in"))
         }
     }
 }
@@ -394,3 +848,37 @@ impl Default for WindowManager {
         })
     }
 }
+
+#[cfg(all(test, feature = "x11"))]
+mod tests {
+    use super::*;
+
+    fn manager() -> X11WindowManager {
+        X11WindowManager { connection: None }
+    }
+
+    #[test]
+    fn unsupported_depths_are_rejected() {
+        let result = manager().convert_x11_image_to_png(&[0; 16], 2, 2, 16);
+
+        assert!(matches!(result, Err(WindowError::UnsupportedDepth(16))));
+    }
+
+    #[test]
+    fn image_of_the_wrong_size_means_the_window_changed() {
+        let result = manager().convert_x11_image_to_png(&[0; 12], 2, 2, 24);
+
+        assert!(matches!(result, Err(WindowError::Changed)));
+    }
+
+    #[test]
+    fn bgra_window_image_converts_to_png() {
+        let png = manager()
+            .convert_x11_image_to_png(&[30, 20, 10, 255, 3, 2, 1, 128], 2, 1, 32)
+            .unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        assert_eq!(image.get_pixel(0, 0).0, [10, 20, 30, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [1, 2, 3, 128]);
+    }
+}